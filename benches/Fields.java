@@ -0,0 +1,23 @@
+public class Fields {
+    static class Base {
+        int total;
+    }
+
+    static class Counter extends Base {
+        int count;
+        int step = 3;
+
+        void run(int times) {
+            for (int i = 0; i < times; i++) {
+                total += step;
+                count++;
+            }
+        }
+    }
+
+    public static void main(String[] args) {
+        Counter counter = new Counter();
+        counter.run(500000);
+        System.out.println(counter.count + " " + counter.total);
+    }
+}
@@ -0,0 +1,32 @@
+//! Measure the interpreter on a recursive fibonacci, which is dominated by
+//! method calls and returns.
+//!
+//! Run with `cargo bench`.
+
+use std::io;
+use std::time::Instant;
+
+use jrm::class_file::ClassFile;
+use jrm::vm::{Options, Vm};
+
+const RUNS: u32 = 5;
+
+fn main() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/Fib.class");
+    let mut best = f64::MAX;
+    let mut instructions = 0;
+    for _ in 0..RUNS {
+        let class = ClassFile::parse(path).unwrap();
+        let mut vm = Vm::with_output(class, Options::default(), io::sink());
+        let start = Instant::now();
+        vm.run_main().unwrap();
+        best = best.min(start.elapsed().as_secs_f64());
+        instructions = vm.instructions();
+    }
+    println!(
+        "fib: {} instructions in {:.3}s ({:.1}M instructions/s)",
+        instructions,
+        best,
+        instructions as f64 / best / 1e6
+    );
+}
@@ -0,0 +1,39 @@
+//! Measure the interpreter on a loop reading and writing fields of `this`,
+//! one of them inherited, which is dominated by field accesses.
+//!
+//! Run with `cargo bench --bench fields`.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use jrm::class_file::ClassFile;
+use jrm::vm::{Options, Vm};
+
+const RUNS: u32 = 5;
+
+fn main() {
+    // the nested classes are looked up next to it
+    let dir = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/benches"));
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/Fields.class");
+    let mut best = f64::MAX;
+    let mut instructions = 0;
+    for _ in 0..RUNS {
+        let class = ClassFile::parse(path).unwrap();
+        let options = Options {
+            class_path: vec![dir.clone()],
+            ..Options::default()
+        };
+        let mut vm = Vm::with_output(class, options, io::sink());
+        let start = Instant::now();
+        vm.run_main().unwrap();
+        best = best.min(start.elapsed().as_secs_f64());
+        instructions = vm.instructions();
+    }
+    println!(
+        "fields: {} instructions in {:.3}s ({:.1}M instructions/s)",
+        instructions,
+        best,
+        instructions as f64 / best / 1e6
+    );
+}
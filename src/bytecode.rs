@@ -1,18 +1,68 @@
+/// The condition of a conditional branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+}
+
+impl Comparison {
+    /// The conditions in the order of their opcodes
+    const ALL: [Comparison; 6] = [
+        Comparison::Eq,
+        Comparison::Ne,
+        Comparison::Lt,
+        Comparison::Ge,
+        Comparison::Gt,
+        Comparison::Le,
+    ];
+
+    pub fn test(self, value1: i32, value2: i32) -> bool {
+        match self {
+            Comparison::Eq => value1 == value2,
+            Comparison::Ne => value1 != value2,
+            Comparison::Lt => value1 < value2,
+            Comparison::Ge => value1 >= value2,
+            Comparison::Gt => value1 > value2,
+            Comparison::Le => value1 <= value2,
+        }
+    }
+}
+
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ByteCode {
     /// Get static field from class
     GetStatic(u16),
+    /// Set static field in class
+    PutStatic(u16),
     /// Push item from run-time constant pool
     Ldc(u8),
+    /// Push item from run-time constant pool (wide index)
+    LdcW(u16),
+    /// Push long or double from run-time constant pool (wide index)
+    Ldc2W(u16),
     /// Invoke instance method; dispatch based on class
     InvokeVirtual(u16),
     /// Push byte
     Bipush(i8),
+    /// Push short
+    Sipush(i16),
     /// Return void from method
     Return,
     /// Push int constant
     IConst(i32),
+    /// Push float constant
+    FConst(f32),
+    /// Push long constant
+    LConst(i64),
+    /// Push double constant
+    DConst(f64),
+    /// Push null
+    AConstNull,
     /// Invoke a class (static) method
     InvokeStatic(u16),
     /// Store reference into local variable
@@ -23,12 +73,135 @@ pub enum ByteCode {
     ALoad(u8),
     /// Load int from local variable
     ILoad(u8),
+    /// Store float into local variable
+    FStore(u8),
+    /// Load float from local variable
+    FLoad(u8),
+    /// Store long into local variable, which takes it and the next one
+    LStore(u8),
+    /// Load long from local variable
+    LLoad(u8),
+    /// Store double into local variable, which takes it and the next one
+    DStore(u8),
+    /// Load double from local variable
+    DLoad(u8),
+    /// Convert int to long
+    I2L,
+    /// Convert long to int
+    L2I,
+    /// Compare long, pushing -1, 0 or 1
+    LCmp,
+    /// Compare float, pushing -1, 0 or 1, or the value given if either is
+    /// NaN, which is 1 for `fcmpg` and -1 for `fcmpl`
+    FCmp(i32),
+    /// Compare double like `FCmp`, for `dcmpg` and `dcmpl`
+    DCmp(i32),
     /// Add int
     IAdd,
+    /// Subtract int
+    ISub,
+    /// Multiply int
+    IMul,
+    /// Divide int
+    IDiv,
+    /// Remainder int
+    IRem,
+    /// Add float
+    FAdd,
+    /// Subtract float
+    FSub,
+    /// Multiply float
+    FMul,
+    /// Divide float
+    FDiv,
+    /// Remainder float
+    FRem,
+    /// Negate float
+    FNeg,
+    /// Negate int
+    INeg,
+    /// Shift left int
+    IShl,
+    /// Arithmetic shift right int
+    IShr,
+    /// Logical shift right int
+    IUShr,
+    /// Boolean AND int
+    IAnd,
+    /// Boolean OR int
+    IOr,
+    /// Boolean XOR int
+    IXor,
+    /// Add long
+    LAdd,
+    /// Subtract long
+    LSub,
+    /// Multiply long
+    LMul,
+    /// Negate long
+    LNeg,
+    /// Shift left long
+    LShl,
+    /// Arithmetic shift right long
+    LShr,
+    /// Logical shift right long
+    LUShr,
+    /// Branch if int comparison with zero succeeds
+    ///
+    /// The offset is relative to the address of the branch instruction.
+    If(Comparison, i16),
+    /// Branch if int comparison succeeds
+    IfICmp(Comparison, i16),
+    /// Branch if reference comparison succeeds, which is either `Eq` or `Ne`
+    IfACmp(Comparison, i16),
+    /// Branch always
+    Goto(i16),
+    /// Jump by the offset of the key if it is within `low..=high`, or by
+    /// `default` otherwise
+    ///
+    /// The offsets of the keys are 4 bytes each, starting at `table` in the
+    /// code, and like those of branches relative to the instruction.
+    TableSwitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        table: usize,
+    },
+    /// Jump by the offset matching the key, or by `default` if none does
+    ///
+    /// The `pairs` pairs of a key and an offset are 8 bytes each, starting at
+    /// `table` in the code and sorted by key.
+    LookupSwitch {
+        default: i32,
+        pairs: usize,
+        table: usize,
+    },
+    /// Increment the int local variable by a constant
+    ///
+    /// Both are widened from a byte unless the instruction is prefixed with
+    /// `wide`.
+    IInc(u16, i16),
     /// Create new object
     New(u16),
+    /// Pop the top operand stack value
+    Pop,
+    /// Pop the top one or two operand stack values, taking two slots
+    Pop2,
     /// Duplicate the top operand stack value
     Dup,
+    /// Duplicate the top one or two operand stack values, taking two slots
+    Dup2,
+    /// Duplicate the top operand stack value and insert two values down
+    DupX1,
+    /// Duplicate the top operand stack value and insert it below the two
+    /// slots under it
+    DupX2,
+    /// Duplicate the values of the top two slots and insert them below the
+    /// slot under them
+    Dup2X1,
+    /// Duplicate the values of the top two slots and insert them below the
+    /// two slots under them
+    Dup2X2,
     /// Invoke instance method
     InvokeSpecial(u16),
     /// Fetch field from object
@@ -37,99 +210,960 @@ pub enum ByteCode {
     PutField(u16),
     /// Return int from method
     IReturn,
+    /// Return float from method
+    FReturn,
+    /// Return long from method
+    LReturn,
+    /// Return double from method
+    DReturn,
+    /// Return reference from method
+    AReturn,
+    /// Invoke a dynamically-computed call site
+    InvokeDynamic(u16),
+    /// Invoke interface method; dispatch based on the class of the object
+    InvokeInterface(u16),
+    /// Create new array of the primitive type code
+    NewArray(u8),
+    /// Create new array of reference of the class
+    ANewArray(u16),
+    /// Get length of array
+    ArrayLength,
+    /// Load int from array
+    IALoad,
+    /// Store into int array
+    IAStore,
+    /// Load reference from array
+    AALoad,
+    /// Store into reference array
+    AAStore,
+    /// Load byte or boolean from array
+    BALoad,
+    /// Store into byte or boolean array
+    BAStore,
+    /// Load char from array
+    CALoad,
+    /// Store into char array
+    CAStore,
+    /// Load short from array
+    SALoad,
+    /// Store into short array
+    SAStore,
+    /// Load long from array
+    LALoad,
+    /// Store into long array
+    LAStore,
+    /// Load float from array
+    FALoad,
+    /// Store into float array
+    FAStore,
+    /// Load double from array
+    DALoad,
+    /// Store into double array
+    DAStore,
+    /// Throw exception or error
+    AThrow,
+    /// Check whether object is of given type
+    CheckCast(u16),
+    /// Determine if object is of given type
+    InstanceOf(u16),
+    /// Enter monitor for object
+    MonitorEnter,
+    /// Exit monitor for object
+    MonitorExit,
 }
 
+const ACONST_NULL: u8 = 0x01;
 const GETSTATIC: u8 = 0xb2;
+const PUTSTATIC: u8 = 0xb3;
 const LDC: u8 = 0x12;
+const LDC_W: u8 = 0x13;
+const LDC2_W: u8 = 0x14;
 const INVOKEVIRTUAL: u8 = 0xb6;
 const BIPUSH: u8 = 0x10;
+const SIPUSH: u8 = 0x11;
 const RETURN: u8 = 0xb1;
 const INVOKESTATIC: u8 = 0xb8;
 const IADD: u8 = 0x60;
+const ISUB: u8 = 0x64;
+const IDIV: u8 = 0x6c;
+const IREM: u8 = 0x70;
+const IMUL: u8 = 0x68;
+const INEG: u8 = 0x74;
+const ISHL: u8 = 0x78;
+const ISHR: u8 = 0x7a;
+const IUSHR: u8 = 0x7c;
+const IAND: u8 = 0x7e;
+const IOR: u8 = 0x80;
+const IXOR: u8 = 0x82;
+const FADD: u8 = 0x62;
+const FSUB: u8 = 0x66;
+const FMUL: u8 = 0x6a;
+const FDIV: u8 = 0x6e;
+const FREM: u8 = 0x72;
+const FNEG: u8 = 0x76;
+const LADD: u8 = 0x61;
+const LSUB: u8 = 0x65;
+const LMUL: u8 = 0x69;
+const LNEG: u8 = 0x75;
+const LSHL: u8 = 0x79;
+const LSHR: u8 = 0x7b;
+const LUSHR: u8 = 0x7d;
+const GOTO: u8 = 0xa7;
+const TABLESWITCH: u8 = 0xaa;
+const LOOKUPSWITCH: u8 = 0xab;
+const IINC: u8 = 0x84;
+const WIDE: u8 = 0xc4;
 const NEW: u8 = 0xbb;
+const POP: u8 = 0x57;
+const POP2: u8 = 0x58;
 const DUP: u8 = 0x59;
+const DUP2: u8 = 0x5c;
+const DUP_X1: u8 = 0x5a;
+const DUP_X2: u8 = 0x5b;
+const DUP2_X1: u8 = 0x5d;
+const DUP2_X2: u8 = 0x5e;
 const INVOKESPECIAL: u8 = 0xb7;
 const ISTORE: u8 = 0x36;
 const ILOAD: u8 = 0x15;
+const FSTORE: u8 = 0x38;
+const FLOAD: u8 = 0x17;
+const ASTORE: u8 = 0x3a;
+const ALOAD: u8 = 0x19;
 const GETFIELD: u8 = 0xb4;
 const PUTFIELD: u8 = 0xb5;
 const IRETURN: u8 = 0xac;
+const FRETURN: u8 = 0xae;
+const LRETURN: u8 = 0xad;
+const DRETURN: u8 = 0xaf;
+const LSTORE: u8 = 0x37;
+const LLOAD: u8 = 0x16;
+const DSTORE: u8 = 0x39;
+const DLOAD: u8 = 0x18;
+const LCMP: u8 = 0x94;
+const FCMPL: u8 = 0x95;
+const FCMPG: u8 = 0x96;
+const DCMPL: u8 = 0x97;
+const DCMPG: u8 = 0x98;
+const ARETURN: u8 = 0xb0;
+const INVOKEINTERFACE: u8 = 0xb9;
+const INVOKEDYNAMIC: u8 = 0xba;
+const NEWARRAY: u8 = 0xbc;
+const ARRAYLENGTH: u8 = 0xbe;
+const IALOAD: u8 = 0x2e;
+const IASTORE: u8 = 0x4f;
+const ANEWARRAY: u8 = 0xbd;
+const AALOAD: u8 = 0x32;
+const AASTORE: u8 = 0x53;
+const BALOAD: u8 = 0x33;
+const BASTORE: u8 = 0x54;
+const CALOAD: u8 = 0x34;
+const CASTORE: u8 = 0x55;
+const SALOAD: u8 = 0x35;
+const SASTORE: u8 = 0x56;
+const LALOAD: u8 = 0x2f;
+const LASTORE: u8 = 0x50;
+const FALOAD: u8 = 0x30;
+const FASTORE: u8 = 0x51;
+const DALOAD: u8 = 0x31;
+const DASTORE: u8 = 0x52;
+const ATHROW: u8 = 0xbf;
+const CHECKCAST: u8 = 0xc0;
+const INSTANCEOF: u8 = 0xc1;
+const MONITORENTER: u8 = 0xc2;
+const MONITOREXIT: u8 = 0xc3;
 
 impl ByteCode {
+    /// Decode the instruction at `pc`, returning the address of the next one
+    ///
+    /// Panics if there is no valid instruction at `pc`.
     pub fn parse(pc: usize, code: &[u8]) -> (usize, Self) {
+        Self::try_parse(pc, code)
+            .unwrap_or_else(|| panic!("Unknown byte code: {:x?} at {}", code.get(pc), pc))
+    }
+
+    /// The offset a `TableSwitch` or `LookupSwitch` of `code` jumps by for
+    /// `key`
+    pub fn switch_offset(self, code: &[u8], key: i32) -> i32 {
+        let i4 = |at: usize| i32::from_be_bytes(code[at..at + 4].try_into().unwrap());
+        match self {
+            ByteCode::TableSwitch {
+                default,
+                low,
+                high,
+                table,
+            } => match (low..=high).contains(&key) {
+                true => i4(table + 4 * (key as i64 - low as i64) as usize),
+                false => default,
+            },
+            ByteCode::LookupSwitch {
+                default,
+                pairs,
+                table,
+            } => (0..pairs)
+                .find(|pair| i4(table + 8 * pair) == key)
+                .map_or(default, |pair| i4(table + 8 * pair + 4)),
+            _ => panic!("Not a switch"),
+        }
+    }
+
+    /// Every offset a `TableSwitch` or `LookupSwitch` of `code` may jump by,
+    /// the default first
+    pub fn switch_offsets(self, code: &[u8]) -> Vec<i32> {
+        let i4 = |at: usize| i32::from_be_bytes(code[at..at + 4].try_into().unwrap());
+        match self {
+            ByteCode::TableSwitch {
+                default,
+                low,
+                high,
+                table,
+            } => {
+                let count = (high as i64 - low as i64 + 1) as usize;
+                let offsets = (0..count).map(|i| i4(table + 4 * i));
+                std::iter::once(default).chain(offsets).collect()
+            }
+            ByteCode::LookupSwitch {
+                default,
+                pairs,
+                table,
+            } => {
+                let offsets = (0..pairs).map(|pair| i4(table + 8 * pair + 4));
+                std::iter::once(default).chain(offsets).collect()
+            }
+            _ => panic!("Not a switch"),
+        }
+    }
+
+    /// Decode the instruction at `pc`, or `None` if the opcode is unknown
+    /// or its operands extend past the end of `code`
+    pub fn try_parse(pc: usize, code: &[u8]) -> Option<(usize, Self)> {
         use ByteCode::*;
-        let op = code[pc];
-        match op {
+        let u1 = |at: usize| code.get(at).copied();
+        let u2 = |at: usize| Some(u16::from_be_bytes([u1(at)?, u1(at + 1)?]));
+        let i4 = |at: usize| Some(i32::from_be_bytes(code.get(at..at + 4)?.try_into().ok()?));
+        // the operands of switches start at a multiple of 4 after the opcode
+        let aligned = (pc + 4) & !3;
+        let op = u1(pc)?;
+        let instruction = match op {
             NEW => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, New(index))
             }
             RETURN => (pc + 1, Return),
             BIPUSH => {
-                let value = code[pc + 1] as i8;
+                let value = u1(pc + 1)? as i8;
                 (pc + 2, Bipush(value))
             }
+            SIPUSH => {
+                let value = u2(pc + 1)? as i16;
+                (pc + 3, Sipush(value))
+            }
             GETSTATIC => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, GetStatic(index))
             }
+            PUTSTATIC => {
+                let index = u2(pc + 1)?;
+                (pc + 3, PutStatic(index))
+            }
             INVOKEVIRTUAL => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, InvokeVirtual(index))
             }
             INVOKESPECIAL => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, InvokeSpecial(index))
             }
             LDC => {
-                let index = code[pc + 1];
+                let index = u1(pc + 1)?;
                 (pc + 2, Ldc(index))
             }
+            LDC_W => {
+                let index = u2(pc + 1)?;
+                (pc + 3, LdcW(index))
+            }
+            LDC2_W => {
+                let index = u2(pc + 1)?;
+                (pc + 3, Ldc2W(index))
+            }
+            INVOKEINTERFACE => {
+                // followed by the number of argument slots and a zero byte,
+                // which the descriptor gives too
+                let index = u2(pc + 1)?;
+                (pc + 5, InvokeInterface(index))
+            }
+            INVOKEDYNAMIC => {
+                // followed by two zero bytes
+                let index = u2(pc + 1)?;
+                (pc + 5, InvokeDynamic(index))
+            }
             INVOKESTATIC => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, InvokeStatic(index))
             }
+            ACONST_NULL => (pc + 1, AConstNull),
+            POP => (pc + 1, Pop),
+            POP2 => (pc + 1, Pop2),
             DUP => (pc + 1, Dup),
+            DUP2 => (pc + 1, Dup2),
+            DUP_X1 => (pc + 1, DupX1),
+            DUP_X2 => (pc + 1, DupX2),
+            DUP2_X1 => (pc + 1, Dup2X1),
+            DUP2_X2 => (pc + 1, Dup2X2),
             IADD => (pc + 1, IAdd),
+            ISUB => (pc + 1, ISub),
+            IMUL => (pc + 1, IMul),
+            IDIV => (pc + 1, IDiv),
+            IREM => (pc + 1, IRem),
+            INEG => (pc + 1, INeg),
+            ISHL => (pc + 1, IShl),
+            ISHR => (pc + 1, IShr),
+            IUSHR => (pc + 1, IUShr),
+            IAND => (pc + 1, IAnd),
+            IOR => (pc + 1, IOr),
+            IXOR => (pc + 1, IXor),
+            LADD => (pc + 1, LAdd),
+            LSUB => (pc + 1, LSub),
+            LMUL => (pc + 1, LMul),
+            LNEG => (pc + 1, LNeg),
+            LSHL => (pc + 1, LShl),
+            LSHR => (pc + 1, LShr),
+            LUSHR => (pc + 1, LUShr),
+            FADD => (pc + 1, FAdd),
+            FSUB => (pc + 1, FSub),
+            FMUL => (pc + 1, FMul),
+            FDIV => (pc + 1, FDiv),
+            FREM => (pc + 1, FRem),
+            FNEG => (pc + 1, FNeg),
+            // ifeq..ifle
+            0x99..=0x9e => {
+                let offset = u2(pc + 1)? as i16;
+                (pc + 3, If(Comparison::ALL[(op - 0x99) as usize], offset))
+            }
+            // if_icmpeq..if_icmple
+            0x9f..=0xa4 => {
+                let offset = u2(pc + 1)? as i16;
+                (
+                    pc + 3,
+                    IfICmp(Comparison::ALL[(op - 0x9f) as usize], offset),
+                )
+            }
+            // if_acmpeq, if_acmpne
+            0xa5 | 0xa6 => {
+                let offset = u2(pc + 1)? as i16;
+                (
+                    pc + 3,
+                    IfACmp(Comparison::ALL[(op - 0xa5) as usize], offset),
+                )
+            }
+            GOTO => {
+                let offset = u2(pc + 1)? as i16;
+                (pc + 3, Goto(offset))
+            }
+            TABLESWITCH => {
+                let default = i4(aligned)?;
+                let low = i4(aligned + 4)?;
+                let high = i4(aligned + 8)?;
+                let table = aligned + 12;
+                let count = usize::try_from(high as i64 - low as i64 + 1).ok()?;
+                let next = table + 4 * count;
+                if count == 0 || next > code.len() {
+                    return None;
+                }
+                let switch = TableSwitch {
+                    default,
+                    low,
+                    high,
+                    table,
+                };
+                (next, switch)
+            }
+            LOOKUPSWITCH => {
+                let default = i4(aligned)?;
+                let pairs = usize::try_from(i4(aligned + 4)?).ok()?;
+                let table = aligned + 8;
+                let next = table + 8 * pairs;
+                if next > code.len() {
+                    return None;
+                }
+                let switch = LookupSwitch {
+                    default,
+                    pairs,
+                    table,
+                };
+                (next, switch)
+            }
+            IINC => (pc + 3, IInc(u1(pc + 1)? as u16, u1(pc + 2)? as i8 as i16)),
+            // only iinc is widened, the other instructions take a local
+            // variable with an index below 256
+            WIDE if u1(pc + 1)? == IINC => (pc + 6, IInc(u2(pc + 2)?, u2(pc + 4)? as i16)),
             // iconst_m1..iconst_5
-            0x3..=0x8 => {
-                let value = op - 0x3;
-                (pc + 1, IConst(value as i32))
+            0x2..=0x8 => {
+                let value = op as i32 - 0x3;
+                (pc + 1, IConst(value))
+            }
+            // fconst_0..fconst_2
+            0xb..=0xd => {
+                let value = (op - 0xb) as f32;
+                (pc + 1, FConst(value))
             }
             // astore_0..astore_3
             0x4b..=0x4e => {
                 let value = op - 0x4b;
                 (pc + 1, AStore(value))
             }
+            ASTORE => (pc + 2, AStore(u1(pc + 1)?)),
             // aload_0..aload_3
             0x2a..=0x2d => {
                 let value = op - 0x2a;
                 (pc + 1, ALoad(value))
             }
+            ALOAD => (pc + 2, ALoad(u1(pc + 1)?)),
             // istore_0..istore_3
             0x3b..=0x3e => {
                 let value = op - 0x3b;
                 (pc + 1, IStore(value))
             }
-            ISTORE => (pc + 2, IStore(code[pc + 1])),
+            ISTORE => (pc + 2, IStore(u1(pc + 1)?)),
             // iload_0..iload_3
             0x1a..=0x1d => {
                 let value = op - 0x1a;
                 (pc + 1, ILoad(value))
             }
-            ILOAD => (pc + 2, ILoad(code[pc + 1])),
+            ILOAD => (pc + 2, ILoad(u1(pc + 1)?)),
+            // fstore_0..fstore_3
+            0x43..=0x46 => {
+                let value = op - 0x43;
+                (pc + 1, FStore(value))
+            }
+            FSTORE => (pc + 2, FStore(u1(pc + 1)?)),
+            // fload_0..fload_3
+            0x22..=0x25 => {
+                let value = op - 0x22;
+                (pc + 1, FLoad(value))
+            }
+            FLOAD => (pc + 2, FLoad(u1(pc + 1)?)),
+            // lconst_0, lconst_1
+            0x9 | 0xa => (pc + 1, LConst((op - 0x9) as i64)),
+            // dconst_0, dconst_1
+            0xe | 0xf => (pc + 1, DConst((op - 0xe) as f64)),
+            // lstore_0..lstore_3
+            0x3f..=0x42 => (pc + 1, LStore(op - 0x3f)),
+            LSTORE => (pc + 2, LStore(u1(pc + 1)?)),
+            // lload_0..lload_3
+            0x1e..=0x21 => (pc + 1, LLoad(op - 0x1e)),
+            LLOAD => (pc + 2, LLoad(u1(pc + 1)?)),
+            // dstore_0..dstore_3
+            0x47..=0x4a => (pc + 1, DStore(op - 0x47)),
+            DSTORE => (pc + 2, DStore(u1(pc + 1)?)),
+            // dload_0..dload_3
+            0x26..=0x29 => (pc + 1, DLoad(op - 0x26)),
+            DLOAD => (pc + 2, DLoad(u1(pc + 1)?)),
+            // i2l, l2i
+            0x85 => (pc + 1, I2L),
+            0x88 => (pc + 1, L2I),
+            LCMP => (pc + 1, LCmp),
+            FCMPL => (pc + 1, FCmp(-1)),
+            FCMPG => (pc + 1, FCmp(1)),
+            DCMPL => (pc + 1, DCmp(-1)),
+            DCMPG => (pc + 1, DCmp(1)),
             GETFIELD => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, GetField(index))
             }
             PUTFIELD => {
-                let index = u16::from_be_bytes([code[pc + 1], code[pc + 2]]);
+                let index = u2(pc + 1)?;
                 (pc + 3, PutField(index))
             }
+            NEWARRAY => (pc + 2, NewArray(u1(pc + 1)?)),
+            ARRAYLENGTH => (pc + 1, ArrayLength),
+            IALOAD => (pc + 1, IALoad),
+            IASTORE => (pc + 1, IAStore),
+            ANEWARRAY => (pc + 3, ANewArray(u2(pc + 1)?)),
+            AALOAD => (pc + 1, AALoad),
+            AASTORE => (pc + 1, AAStore),
+            BALOAD => (pc + 1, BALoad),
+            BASTORE => (pc + 1, BAStore),
+            CALOAD => (pc + 1, CALoad),
+            CASTORE => (pc + 1, CAStore),
+            SALOAD => (pc + 1, SALoad),
+            SASTORE => (pc + 1, SAStore),
+            LALOAD => (pc + 1, LALoad),
+            LASTORE => (pc + 1, LAStore),
+            FALOAD => (pc + 1, FALoad),
+            FASTORE => (pc + 1, FAStore),
+            DALOAD => (pc + 1, DALoad),
+            DASTORE => (pc + 1, DAStore),
+            ATHROW => (pc + 1, AThrow),
+            CHECKCAST => (pc + 3, CheckCast(u2(pc + 1)?)),
+            INSTANCEOF => (pc + 3, InstanceOf(u2(pc + 1)?)),
+            MONITORENTER => (pc + 1, MonitorEnter),
+            MONITOREXIT => (pc + 1, MonitorExit),
             IRETURN => (pc + 1, IReturn),
-            _ => {
-                panic!("Unknown byte code: 0x{:x}", op);
+            FRETURN => (pc + 1, FReturn),
+            LRETURN => (pc + 1, LReturn),
+            DRETURN => (pc + 1, DReturn),
+            ARETURN => (pc + 1, AReturn),
+            _ => return None,
+        };
+        Some(instruction)
+    }
+
+    /// The mnemonic of the instruction with the opcode `op`, e.g. `ifnonnull`,
+    /// whether jrm supports it or not
+    pub fn mnemonic(op: u8) -> Option<&'static str> {
+        MNEMONICS.get(op as usize).copied()
+    }
+
+    /// Decode the instructions of `code` in order, stopping before the first
+    /// byte which doesn't start a valid instruction, e.g. unreachable padding
+    pub fn parse_all(code: &[u8]) -> Vec<(usize, Self)> {
+        let mut instructions = vec![];
+        let mut pc = 0;
+        while let Some((next, instruction)) = Self::try_parse(pc, code) {
+            instructions.push((pc, instruction));
+            pc = next;
+        }
+        instructions
+    }
+
+    /// List the instructions of `code` which jrm can't execute, with their
+    /// address and mnemonic
+    ///
+    /// Unlike `parse_all` this continues past them, skipping their operands
+    /// by the lengths the JVM specification gives, so that every one is
+    /// found rather than just the first.
+    pub fn unsupported(code: &[u8]) -> Vec<(usize, &'static str)> {
+        let mut unsupported = vec![];
+        let mut pc = 0;
+        while pc < code.len() {
+            if let Some((next, _)) = Self::try_parse(pc, code) {
+                pc = next;
+                continue;
+            }
+            let op = code[pc];
+            let Some(name) = MNEMONICS.get(op as usize) else {
+                // not an instruction at all, so its length is unknown too
+                unsupported.push((pc, "<invalid>"));
+                break;
+            };
+            unsupported.push((pc, *name));
+            pc += 1 + match op {
+                0x10 | 0x12 | 0x15..=0x19 | 0x36..=0x3a | 0xa9 | 0xbc => 1,
+                0x11 | 0x13 | 0x14 | 0x84 | 0x99..=0xa8 | 0xb2..=0xb8 | 0xbb | 0xbd..=0xc1 => 2,
+                0xc4 if code.get(pc + 1) == Some(&IINC) => 5,
+                0xc4 | 0xc5 => 3,
+                0xc6 | 0xc7 => 2,
+                0xb9 | 0xba | 0xc8 | 0xc9 => 4,
+                // a truncated switch, whose padding can't be skipped
+                0xaa | 0xab => break,
+                _ => 0,
+            };
+        }
+        unsupported
+    }
+}
+
+/// The mnemonics of the opcodes of the JVM specification, by opcode
+const MNEMONICS: [&str; 0xca] = [
+    "nop",
+    "aconst_null",
+    "iconst_m1",
+    "iconst_0",
+    "iconst_1",
+    "iconst_2",
+    "iconst_3",
+    "iconst_4",
+    "iconst_5",
+    "lconst_0",
+    "lconst_1",
+    "fconst_0",
+    "fconst_1",
+    "fconst_2",
+    "dconst_0",
+    "dconst_1",
+    "bipush",
+    "sipush",
+    "ldc",
+    "ldc_w",
+    "ldc2_w",
+    "iload",
+    "lload",
+    "fload",
+    "dload",
+    "aload",
+    "iload_0",
+    "iload_1",
+    "iload_2",
+    "iload_3",
+    "lload_0",
+    "lload_1",
+    "lload_2",
+    "lload_3",
+    "fload_0",
+    "fload_1",
+    "fload_2",
+    "fload_3",
+    "dload_0",
+    "dload_1",
+    "dload_2",
+    "dload_3",
+    "aload_0",
+    "aload_1",
+    "aload_2",
+    "aload_3",
+    "iaload",
+    "laload",
+    "faload",
+    "daload",
+    "aaload",
+    "baload",
+    "caload",
+    "saload",
+    "istore",
+    "lstore",
+    "fstore",
+    "dstore",
+    "astore",
+    "istore_0",
+    "istore_1",
+    "istore_2",
+    "istore_3",
+    "lstore_0",
+    "lstore_1",
+    "lstore_2",
+    "lstore_3",
+    "fstore_0",
+    "fstore_1",
+    "fstore_2",
+    "fstore_3",
+    "dstore_0",
+    "dstore_1",
+    "dstore_2",
+    "dstore_3",
+    "astore_0",
+    "astore_1",
+    "astore_2",
+    "astore_3",
+    "iastore",
+    "lastore",
+    "fastore",
+    "dastore",
+    "aastore",
+    "bastore",
+    "castore",
+    "sastore",
+    "pop",
+    "pop2",
+    "dup",
+    "dup_x1",
+    "dup_x2",
+    "dup2",
+    "dup2_x1",
+    "dup2_x2",
+    "swap",
+    "iadd",
+    "ladd",
+    "fadd",
+    "dadd",
+    "isub",
+    "lsub",
+    "fsub",
+    "dsub",
+    "imul",
+    "lmul",
+    "fmul",
+    "dmul",
+    "idiv",
+    "ldiv",
+    "fdiv",
+    "ddiv",
+    "irem",
+    "lrem",
+    "frem",
+    "drem",
+    "ineg",
+    "lneg",
+    "fneg",
+    "dneg",
+    "ishl",
+    "lshl",
+    "ishr",
+    "lshr",
+    "iushr",
+    "lushr",
+    "iand",
+    "land",
+    "ior",
+    "lor",
+    "ixor",
+    "lxor",
+    "iinc",
+    "i2l",
+    "i2f",
+    "i2d",
+    "l2i",
+    "l2f",
+    "l2d",
+    "f2i",
+    "f2l",
+    "f2d",
+    "d2i",
+    "d2l",
+    "d2f",
+    "i2b",
+    "i2c",
+    "i2s",
+    "lcmp",
+    "fcmpl",
+    "fcmpg",
+    "dcmpl",
+    "dcmpg",
+    "ifeq",
+    "ifne",
+    "iflt",
+    "ifge",
+    "ifgt",
+    "ifle",
+    "if_icmpeq",
+    "if_icmpne",
+    "if_icmplt",
+    "if_icmpge",
+    "if_icmpgt",
+    "if_icmple",
+    "if_acmpeq",
+    "if_acmpne",
+    "goto",
+    "jsr",
+    "ret",
+    "tableswitch",
+    "lookupswitch",
+    "ireturn",
+    "lreturn",
+    "freturn",
+    "dreturn",
+    "areturn",
+    "return",
+    "getstatic",
+    "putstatic",
+    "getfield",
+    "putfield",
+    "invokevirtual",
+    "invokespecial",
+    "invokestatic",
+    "invokeinterface",
+    "invokedynamic",
+    "new",
+    "newarray",
+    "anewarray",
+    "arraylength",
+    "athrow",
+    "checkcast",
+    "instanceof",
+    "monitorenter",
+    "monitorexit",
+    "wide",
+    "multianewarray",
+    "ifnull",
+    "ifnonnull",
+    "goto_w",
+    "jsr_w",
+];
+
+/// List the instructions of `code` one per line like `javap -c`, ending with
+/// the bytes which couldn't be decoded
+///
+/// Branches show the address they jump to like `javap` does, e.g. `goto 4`,
+/// rather than the offset relative to themselves which they encode.
+pub fn disassemble(code: &[u8]) -> String {
+    let mut output = String::new();
+    let mut pc = 0;
+    while let Some((next, instruction)) = ByteCode::try_parse(pc, code) {
+        let target = |offset: i32| pc as i64 + offset as i64;
+        let mnemonic = MNEMONICS[code[pc] as usize];
+        let line = match instruction {
+            ByteCode::If(_, offset)
+            | ByteCode::IfICmp(_, offset)
+            | ByteCode::IfACmp(_, offset)
+            | ByteCode::Goto(offset) => format!("{} {}", mnemonic, target(offset.into())),
+            ByteCode::TableSwitch { .. } | ByteCode::LookupSwitch { .. } => {
+                let mut targets = instruction
+                    .switch_offsets(code)
+                    .into_iter()
+                    .map(|offset| target(offset).to_string());
+                // the default is listed last, like javap does
+                let default = targets.next().unwrap();
+                let cases = targets.map(|target| format!("{}, ", target));
+                format!(
+                    "{} {}default: {}",
+                    mnemonic,
+                    cases.collect::<String>(),
+                    default
+                )
+            }
+            _ => format!("{:?}", instruction),
+        };
+        output.push_str(&format!("{:>6}: {}\n", pc, line));
+        pc = next;
+    }
+    if pc < code.len() {
+        output.push_str(&format!("{:>6}: <undecodable {:02x?}>\n", pc, &code[pc..]));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_generic_and_short_form_astore() {
+        // astore 5, astore_2, aload 5, aload_2
+        let code = [0x3a, 5, 0x4d, 0x19, 5, 0x2c];
+        assert_eq!(
+            ByteCode::parse_all(&code),
+            [
+                (0, ByteCode::AStore(5)),
+                (2, ByteCode::AStore(2)),
+                (3, ByteCode::ALoad(5)),
+                (5, ByteCode::ALoad(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_every_short_form_astore_and_aload() {
+        for index in 0..4 {
+            assert_eq!(
+                ByteCode::try_parse(0, &[0x4b + index]),
+                Some((1, ByteCode::AStore(index)))
+            );
+            assert_eq!(
+                ByteCode::try_parse(0, &[0x2a + index]),
+                Some((1, ByteCode::ALoad(index)))
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_int_loads_stores_and_pushes() {
+        let cases = [
+            (vec![0x15, 7], 2, ByteCode::ILoad(7)),
+            (vec![0x15, 0xff], 2, ByteCode::ILoad(255)),
+            (vec![0x1a], 1, ByteCode::ILoad(0)),
+            (vec![0x1b], 1, ByteCode::ILoad(1)),
+            (vec![0x1c], 1, ByteCode::ILoad(2)),
+            (vec![0x1d], 1, ByteCode::ILoad(3)),
+            (vec![0x36, 4], 2, ByteCode::IStore(4)),
+            (vec![0x3b], 1, ByteCode::IStore(0)),
+            (vec![0x3c], 1, ByteCode::IStore(1)),
+            (vec![0x3d], 1, ByteCode::IStore(2)),
+            (vec![0x3e], 1, ByteCode::IStore(3)),
+            (vec![0x10, 0x80], 2, ByteCode::Bipush(-128)),
+            (vec![0x10, 0x7f], 2, ByteCode::Bipush(127)),
+            (vec![0x11, 0x80, 0x00], 3, ByteCode::Sipush(-32768)),
+            (vec![0x11, 0x01, 0x2c], 3, ByteCode::Sipush(300)),
+        ];
+        for (code, next, instruction) in cases {
+            assert_eq!(
+                ByteCode::try_parse(0, &code),
+                Some((next, instruction)),
+                "{:02x?}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_pushes_and_stores_in_sequence() {
+        // bipush 5, istore 4, sipush 1000, istore_1, iload 4, iload_1
+        let code = [0x10, 5, 0x36, 4, 0x11, 0x03, 0xe8, 0x3c, 0x15, 4, 0x1b];
+        assert_eq!(
+            ByteCode::parse_all(&code),
+            [
+                (0, ByteCode::Bipush(5)),
+                (2, ByteCode::IStore(4)),
+                (4, ByteCode::Sipush(1000)),
+                (7, ByteCode::IStore(1)),
+                (8, ByteCode::ILoad(4)),
+                (10, ByteCode::ILoad(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_operands_are_not_decoded() {
+        assert_eq!(ByteCode::try_parse(0, &[0x15]), None);
+        assert_eq!(ByteCode::try_parse(0, &[0x11, 0x01]), None);
+    }
+
+    #[test]
+    fn stray_bytes_after_return_are_not_decoded() {
+        // iload_1, ireturn, return, then a stray byte and a truncated bipush
+        for stray in [[0xff].as_slice(), &[0x10]] {
+            let code = [&[0x1b, 0xac, 0xb1][..], stray].concat();
+            assert_eq!(
+                ByteCode::parse_all(&code),
+                [
+                    (0, ByteCode::ILoad(1)),
+                    (1, ByteCode::IReturn),
+                    (2, ByteCode::Return),
+                ]
+            );
+            let listing = disassemble(&code);
+            assert!(
+                listing.ends_with(&format!("     3: <undecodable {:02x?}>\n", stray)),
+                "{}",
+                listing
+            );
+        }
+    }
+
+    #[test]
+    fn switch_tables_are_aligned_to_four_bytes() {
+        // a tableswitch for the keys 1..=2 at every alignment, with the
+        // instructions before it as nops
+        for pc in 0..4 {
+            let padding = 3 - pc % 4;
+            let mut code = vec![0x00; pc];
+            code.push(0xaa);
+            code.extend(vec![0; padding]);
+            for value in [30, 1, 2, -10, 20] {
+                code.extend(i32::to_be_bytes(value));
             }
+            let (next, switch) = ByteCode::try_parse(pc, &code).unwrap();
+            assert_eq!(next, code.len(), "at {}", pc);
+            let table = pc + 1 + padding + 12;
+            assert_eq!(
+                switch,
+                ByteCode::TableSwitch {
+                    default: 30,
+                    low: 1,
+                    high: 2,
+                    table
+                }
+            );
+            let offsets = [0, 1, 2, 3].map(|key| switch.switch_offset(&code, key));
+            assert_eq!(offsets, [30, -10, 20, 30], "at {}", pc);
         }
     }
+
+    #[test]
+    fn loops_branch_back_to_their_head() {
+        // int i = 0; while (i < 10) i++;
+        let code = [
+            0x03, 0x3c, // iconst_0, istore_1
+            0x1b, 0x10, 10, // iload_1, bipush 10
+            0xa2, 0x00, 0x09, // if_icmpge +9
+            0x84, 0x01, 0x01, // iinc 1 1
+            0xa7, 0xff, 0xf7, // goto -9
+            0xb1, // return
+        ];
+        let listing = disassemble(&code);
+        let lines = listing.lines().map(str::trim).collect::<Vec<_>>();
+        assert_eq!(lines[1], "1: IStore(1)");
+        assert_eq!(lines[2], "2: ILoad(1)");
+        assert_eq!(lines[4], "5: if_icmpge 14");
+        assert_eq!(lines[6], "11: goto 2");
+        assert_eq!(lines[7], "14: Return");
+    }
 }
@@ -2,9 +2,11 @@
 //!
 //! Each class file contains the definition of a single class or interface.
 
-use std::fmt::Display;
+use std::{cell::OnceCell, fmt::Display, rc::Rc};
 
-use anyhow::{bail, Ok, Result};
+use anyhow::{anyhow, bail, Context, Ok, Result};
+
+use crate::runtime::java_decimal;
 
 type U1 = u8;
 type U2 = u16;
@@ -14,10 +16,21 @@ trait Read: Sized {
     fn read(bytes: &[u8], index: usize) -> Result<(usize, Self)>;
 }
 fn bound_check(bytes: &[u8], index: usize, len: usize) -> Result<()> {
-    if index + len > bytes.len() {
-        bail!(format!("index out of range: {}", index));
+    match index.checked_add(len) {
+        Some(end) if end <= bytes.len() => Ok(()),
+        _ => bail!(
+            "unexpected end of file: {} bytes needed at offset {}, but the file has {}",
+            len,
+            index,
+            bytes.len()
+        ),
     }
-    Ok(())
+}
+
+/// Read `len` raw bytes starting at `index`
+fn read_bytes(bytes: &[u8], index: usize, len: usize) -> Result<(usize, &[u8])> {
+    bound_check(bytes, index, len)?;
+    Ok((index + len, &bytes[index..(index + len)]))
 }
 
 #[rustfmt::skip]
@@ -47,6 +60,16 @@ pub struct ClassFile {
     pub methods:                Vec<MethodInfo>,
     pub attributes_count:       U2,
     pub attributes:             Vec<AttributeInfo>,
+    /// The resolved names of the field and method refs, by constant pool index
+    pub member_refs:            Vec<Option<MemberRef>>,
+}
+
+/// A field or method ref with its names looked up in the constant pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberRef {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
 }
 
 #[derive(Debug)]
@@ -72,6 +95,31 @@ pub enum ConstantInfo {
     },
     Utf8(String),
     String(U2),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    MethodHandle {
+        reference_kind: U1,
+        reference_index: U2,
+    },
+    MethodType {
+        descriptor_index: U2,
+    },
+    Dynamic {
+        bootstrap_method_attr_index: U2,
+        name_and_type_index: U2,
+    },
+    InvokeDynamic {
+        bootstrap_method_attr_index: U2,
+        name_and_type_index: U2,
+    },
+    Module {
+        name_index: U2,
+    },
+    Package {
+        name_index: U2,
+    },
 }
 
 #[rustfmt::skip]
@@ -112,12 +160,14 @@ pub struct MethodInfo {
     pub descriptor_index:   U2,
     pub attributes_count:   U2,
     pub attributes:         Vec<AttributeInfo>,
+    /// The `Code` attribute, parsed when the method is first invoked
+    code:                   OnceCell<Rc<CodeAttribute>>,
 }
 #[derive(Debug, Clone)]
 pub struct AttributeInfo {
-    attribute_name_index: U2,
-    attribute_length: U4,
-    info: Vec<U1>,
+    pub attribute_name_index: U2,
+    pub attribute_length: U4,
+    pub info: Vec<U1>,
 }
 
 #[derive(Debug, Clone)]
@@ -140,45 +190,108 @@ pub struct CodeAttribute {
     pub attributes: Vec<AttributeInfo>,
 }
 
-impl From<&[U1]> for CodeAttribute {
-    fn from(info: &[U1]) -> Self {
-        let index = 0;
-        let (index, max_stack) = U2::read(info, index).unwrap();
-        let (index, max_locals) = U2::read(info, index).unwrap();
-        let (mut index, code_length) = U4::read(info, index).unwrap();
-        let code = info[index..(index + code_length as usize)].to_vec();
-        index += code_length as usize;
-        let (index, exception_table_length) = U2::read(info, index).unwrap();
-        let exception_table = vec![];
-        // for _ in 0..exception_table_length {
-        //     let (index, start_pc) = U2::read(&attribute.info, index).unwrap();
-        //     let (index, end_pc) = U2::read(&attribute.info, index).unwrap();
-        //     let (index, handler_pc) = U2::read(&attribute.info, index).unwrap();
-        //     let (index, catch_type) = U2::read(&attribute.info, index).unwrap();
-        //     exception_table.push(ExceptionTableEntry {
-        //         start_pc,
-        //         end_pc,
-        //         handler_pc,
-        //         catch_type,
-        //     });
-        // }
-        let (mut index, attributes_count) = U2::read(info, index).unwrap();
+impl CodeAttribute {
+    /// Parse the contents `info` of a `Code` attribute, which must be used up
+    /// exactly by the structure they declare
+    pub fn parse(info: &[U1]) -> Result<Self> {
+        let (index, code) = Self::read(info, 0)?;
+        if index != info.len() {
+            bail!(
+                "Code attribute of {} bytes has {} more than its contents",
+                info.len(),
+                info.len() - index
+            );
+        }
+        Ok(code)
+    }
+
+    /// The source line of the instruction at `pc`, from the `LineNumberTable`
+    pub fn line_number(&self, pc: usize, constant_pool: &[ConstantInfo]) -> Option<u16> {
+        let attribute = find_attribute(&self.attributes, constant_pool, "LineNumberTable")?;
+        let (mut index, length) = U2::read(&attribute.info, 0).ok()?;
+        let mut line = None;
+        for _ in 0..length {
+            let (next, start_pc) = U2::read(&attribute.info, index).ok()?;
+            let (next, line_number) = U2::read(&attribute.info, next).ok()?;
+            index = next;
+            // the entries are not required to be sorted
+            if start_pc as usize <= pc && line.is_none_or(|(start, _)| start <= start_pc) {
+                line = Some((start_pc, line_number));
+            }
+        }
+        line.map(|(_, line_number)| line_number)
+    }
+
+    /// The name of the local variable in `slot` at the instruction at `pc`,
+    /// from the `LocalVariableTable` which `javac -g` emits
+    pub fn local_name<'a>(
+        &self,
+        slot: u16,
+        pc: usize,
+        constant_pool: &'a [ConstantInfo],
+    ) -> Option<&'a str> {
+        let attribute = find_attribute(&self.attributes, constant_pool, "LocalVariableTable")?;
+        let (mut index, length) = U2::read(&attribute.info, 0).ok()?;
+        for _ in 0..length {
+            let (next, start_pc) = U2::read(&attribute.info, index).ok()?;
+            let (next, scope) = U2::read(&attribute.info, next).ok()?;
+            let (next, name_index) = U2::read(&attribute.info, next).ok()?;
+            // followed by the descriptor, which isn't needed
+            let (next, _) = U2::read(&attribute.info, next).ok()?;
+            let (next, local) = U2::read(&attribute.info, next).ok()?;
+            index = next;
+            let start = start_pc as usize;
+            // a slot may be reused by variables of different scopes
+            if local == slot && (start..start + scope as usize).contains(&pc) {
+                return constant_pool.get(name_index as usize)?.as_utf8();
+            }
+        }
+        None
+    }
+}
+
+impl Read for CodeAttribute {
+    fn read(info: &[U1], index: usize) -> Result<(usize, Self)> {
+        let (index, max_stack) = U2::read(info, index)?;
+        let (index, max_locals) = U2::read(info, index)?;
+        let (index, code_length) = U4::read(info, index)?;
+        let (index, code) = read_bytes(info, index, code_length as usize)?;
+        let code = code.to_vec();
+        let (mut index, exception_table_length) = U2::read(info, index)?;
+        let mut exception_table = vec![];
+        for _ in 0..exception_table_length {
+            let (next, start_pc) = U2::read(info, index)?;
+            let (next, end_pc) = U2::read(info, next)?;
+            let (next, handler_pc) = U2::read(info, next)?;
+            let (next, catch_type) = U2::read(info, next)?;
+            index = next;
+            exception_table.push(ExceptionTableEntry {
+                start_pc,
+                end_pc,
+                handler_pc,
+                catch_type,
+            });
+        }
+        let (mut index, attributes_count) = U2::read(info, index)?;
         let mut attributes = vec![];
         for _ in 0..attributes_count {
-            let attribute = AttributeInfo::read(info, index).unwrap();
+            let attribute = AttributeInfo::read(info, index)?;
             index = attribute.0;
             attributes.push(attribute.1);
         }
-        Self {
-            max_stack,
-            max_locals,
-            code_length,
-            code,
-            exception_table_length,
-            exception_table,
-            attributes_count,
-            attributes,
-        }
+        Ok((
+            index,
+            Self {
+                max_stack,
+                max_locals,
+                code_length,
+                code,
+                exception_table_length,
+                exception_table,
+                attributes_count,
+                attributes,
+            },
+        ))
     }
 }
 
@@ -190,10 +303,17 @@ impl ClassFile {
         //     print!("{:02X} ", byte);
         // }
         let (index, class) = Self::read(&bytes, 0)?;
-        assert_eq!(index, bytes.len());
+        if index != bytes.len() {
+            bail!("{} unexpected bytes after the class", bytes.len() - index);
+        }
         Ok(class)
     }
 
+    /// The `(major, minor)` version of the class file format
+    pub fn version(&self) -> (u16, u16) {
+        (self.major_version, self.minor_version)
+    }
+
     pub fn name(&self) -> &str {
         let class_index = self.constant_pool[self.this_class as usize]
             .as_class()
@@ -201,6 +321,290 @@ impl ClassFile {
         self.constant_pool[class_index as usize].as_utf8().unwrap()
     }
 
+    /// The name of the direct superclass, `None` for `java/lang/Object`
+    pub fn super_class_name(&self) -> Option<&str> {
+        if self.super_class == 0 {
+            return None;
+        }
+        let class_index = self.constant_pool[self.super_class as usize]
+            .as_class()
+            .unwrap();
+        self.constant_pool[class_index as usize].as_utf8()
+    }
+
+    /// The class attribute named `name`
+    fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        find_attribute(&self.attributes, &self.constant_pool, name)
+    }
+
+    /// The name of the `Class` constant at `index`, `None` for index 0
+    fn class_name_at(&self, index: u16) -> Option<&str> {
+        if index == 0 {
+            return None;
+        }
+        let class_index = self.constant_pool.get(index as usize)?.as_class()?;
+        self.constant_pool[class_index as usize].as_utf8()
+    }
+
+    /// The UTF-8 constant at `index`, `None` for index 0
+    fn utf8_at(&self, index: u16) -> Option<&str> {
+        if index == 0 {
+            return None;
+        }
+        self.constant_pool.get(index as usize)?.as_utf8()
+    }
+
+    /// The name of the source file recorded by the `SourceFile` attribute
+    pub fn source_file(&self) -> Option<&str> {
+        let attribute = self.attribute("SourceFile")?;
+        let (_, index) = U2::read(&attribute.info, 0).ok()?;
+        self.utf8_at(index)
+    }
+
+    /// The nested classes recorded by the `InnerClasses` attribute, which
+    /// lists both the classes nested in this one and the classes this one
+    /// is nested in
+    pub fn inner_classes(&self) -> Vec<InnerClass<'_>> {
+        let Some(attribute) = self.attribute("InnerClasses") else {
+            return vec![];
+        };
+        let info = &attribute.info;
+        let entry = |index: usize| {
+            let (next, inner_class_info_index) = U2::read(info, index).ok()?;
+            let (next, outer_class_info_index) = U2::read(info, next).ok()?;
+            let (next, inner_name_index) = U2::read(info, next).ok()?;
+            let (next, access_flags) = U2::read(info, next).ok()?;
+            let inner_class = InnerClass {
+                inner_class: self.class_name_at(inner_class_info_index)?,
+                outer_class: self.class_name_at(outer_class_info_index),
+                inner_name: self.utf8_at(inner_name_index),
+                access_flags,
+            };
+            Some((next, inner_class))
+        };
+        let (mut index, length) = U2::read(info, 0).unwrap_or_default();
+        let mut inner_classes = vec![];
+        for _ in 0..length {
+            let Some((next, inner_class)) = entry(index) else {
+                break;
+            };
+            index = next;
+            inner_classes.push(inner_class);
+        }
+        inner_classes
+    }
+
+    /// The class and method a local or anonymous class is declared in, from
+    /// the `EnclosingMethod` attribute
+    pub fn enclosing_method(&self) -> Option<EnclosingMethod<'_>> {
+        let attribute = self.attribute("EnclosingMethod")?;
+        let (index, class_index) = U2::read(&attribute.info, 0).ok()?;
+        let (_, method_index) = U2::read(&attribute.info, index).ok()?;
+        // the method is absent for classes declared in initializers
+        let method = match self.constant_pool.get(method_index as usize) {
+            Some(ConstantInfo::NameAndType { .. }) => {
+                let (name_index, descriptor_index) =
+                    self.constant_pool[method_index as usize].as_name_and_type()?;
+                Some((self.utf8_at(name_index)?, self.utf8_at(descriptor_index)?))
+            }
+            _ => None,
+        };
+        Some(EnclosingMethod {
+            class: self.class_name_at(class_index)?,
+            method,
+        })
+    }
+
+    /// The class hosting the nest the class belongs to, from the `NestHost`
+    /// attribute of nested classes, or the class itself
+    ///
+    /// The classes of a nest may access each other's private members.
+    pub fn nest_host(&self) -> &str {
+        self.attribute("NestHost")
+            .and_then(|attribute| U2::read(&attribute.info, 0).ok())
+            .and_then(|(_, index)| self.class_name_at(index))
+            .unwrap_or_else(|| self.name())
+    }
+
+    /// The names of the interfaces the class implements directly
+    pub fn interface_names(&self) -> Vec<&str> {
+        self.interfaces
+            .iter()
+            .filter_map(|&index| self.class_name_at(index))
+            .collect()
+    }
+
+    /// The declaration of the class with its modifiers, superclass and
+    /// interfaces, e.g. `public final class Foo extends java/lang/Object`
+    pub fn declaration(&self) -> String {
+        let is = |flag: AccessFlag| self.access_flags & flag as U2 != 0;
+        let interface = is(AccessFlag::Interface);
+        let mut declaration = String::new();
+        if is(AccessFlag::Public) {
+            declaration.push_str("public ");
+        }
+        // interfaces are always abstract and never final
+        if !interface {
+            if is(AccessFlag::Final) {
+                declaration.push_str("final ");
+            }
+            if is(AccessFlag::Abstract) {
+                declaration.push_str("abstract ");
+            }
+        }
+        declaration.push_str(if interface { "interface " } else { "class " });
+        declaration.push_str(self.name());
+        let interfaces = self.interface_names().join(", ");
+        if interface {
+            if !interfaces.is_empty() {
+                declaration.push_str(&format!(" extends {}", interfaces));
+            }
+            return declaration;
+        }
+        if let Some(super_class) = self.super_class_name() {
+            declaration.push_str(&format!(" extends {}", super_class));
+        }
+        if !interfaces.is_empty() {
+            declaration.push_str(&format!(" implements {}", interfaces));
+        }
+        declaration
+    }
+
+    /// An overview of the class: its declaration, version, source file and
+    /// the number of its fields and methods
+    pub fn info(&self) -> String {
+        let mut info = format!("{}\n", self.declaration());
+        info.push_str(&format!(
+            "  version: {}.{}\n",
+            self.major_version, self.minor_version
+        ));
+        if let Some(source_file) = self.source_file() {
+            info.push_str(&format!("  source file: {}\n", source_file));
+        }
+        info.push_str(&format!("  fields: {}\n", self.fields.len()));
+        info.push_str(&format!("  methods: {}\n", self.methods.len()));
+        info
+    }
+
+    /// The value of the constant at `index` with the references between
+    /// constants followed, like the comments of `javap -v`, e.g.
+    /// `java/lang/Object."<init>":()V` for a method ref
+    ///
+    /// Returns `None` for constants which are values themselves.
+    pub fn resolve_constant(&self, index: usize) -> Option<String> {
+        use ConstantInfo::*;
+        let cp = &self.constant_pool;
+        // special method names are quoted, as they aren't valid identifiers
+        let name_and_type = |index: u16| {
+            let (name_index, descriptor_index) = cp.get(index as usize)?.as_name_and_type()?;
+            let name = self.utf8_at(name_index)?;
+            let descriptor = self.utf8_at(descriptor_index)?;
+            Some(match name.starts_with('<') {
+                true => format!("\"{}\":{}", name, descriptor),
+                false => format!("{}:{}", name, descriptor),
+            })
+        };
+        match cp.get(index)? {
+            Class { .. } => self.class_name_at(index as u16).map(str::to_string),
+            FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => Some(format!(
+                "{}.{}",
+                self.class_name_at(*class_index)?,
+                name_and_type(*name_and_type_index)?
+            )),
+            String(string_index) => self.utf8_at(*string_index).map(str::to_string),
+            NameAndType { .. } => name_and_type(index as u16),
+            MethodHandle {
+                reference_kind,
+                reference_index,
+            } => {
+                const KINDS: [&str; 9] = [
+                    "REF_getField",
+                    "REF_getStatic",
+                    "REF_putField",
+                    "REF_putStatic",
+                    "REF_invokeVirtual",
+                    "REF_invokeStatic",
+                    "REF_invokeSpecial",
+                    "REF_newInvokeSpecial",
+                    "REF_invokeInterface",
+                ];
+                let kind = KINDS.get((*reference_kind as usize).checked_sub(1)?)?;
+                let reference = self.resolve_constant(*reference_index as usize)?;
+                Some(format!("{} {}", kind, reference))
+            }
+            MethodType { descriptor_index } => self.utf8_at(*descriptor_index).map(str::to_string),
+            Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => Some(format!(
+                "#{}:{}",
+                bootstrap_method_attr_index,
+                name_and_type(*name_and_type_index)?
+            )),
+            Module { name_index } | Package { name_index } => {
+                self.utf8_at(*name_index).map(str::to_string)
+            }
+            Utf8(_) | Integer(_) | Float(_) | Long(_) | Double(_) => None,
+        }
+    }
+
+    /// List the constant pool like `javap -v` does, one entry per line with
+    /// its index, tag, operands and resolved value
+    pub fn dump_constant_pool(&self) -> String {
+        let mut output = "Constant pool:\n".to_string();
+        let mut index = 1;
+        while index < self.constant_pool.len() {
+            let constant = &self.constant_pool[index];
+            let operands = match constant {
+                ConstantInfo::Utf8(string) => escape_control(string),
+                constant => {
+                    let rendered = constant.to_string();
+                    rendered
+                        .split_once(' ')
+                        .map_or(String::new(), |(_, operands)| operands.to_string())
+                }
+            };
+            let line = match self.resolve_constant(index).as_deref().map(escape_control) {
+                Some(resolved) => format!(
+                    "{:>5} = {:<18} {:<14} // {}",
+                    format!("#{}", index),
+                    constant.tag_name(),
+                    operands,
+                    resolved
+                ),
+                None => format!(
+                    "{:>5} = {:<18} {}",
+                    format!("#{}", index),
+                    constant.tag_name(),
+                    operands
+                ),
+            };
+            output.push_str(line.trim_end());
+            output.push('\n');
+            // long and double constants take up two entries
+            index += match constant {
+                ConstantInfo::Long(_) | ConstantInfo::Double(_) => 2,
+                _ => 1,
+            };
+        }
+        output
+    }
+
     pub fn find_method(&self, target: &str) -> Option<&MethodInfo> {
         for method in &self.methods {
             let name = method.name(&self.constant_pool);
@@ -211,13 +615,101 @@ impl ClassFile {
         None
     }
 
+    /// Find the method named `name` of the `descriptor`, which tells
+    /// overloads apart
+    pub fn find_method_with_descriptor(&self, name: &str, descriptor: &str) -> Option<&MethodInfo> {
+        self.methods.iter().find(|method| {
+            method.name(&self.constant_pool) == name
+                && method.descriptor(&self.constant_pool) == descriptor
+        })
+    }
+
+    /// Find the field named `name` declared by the class itself
+    pub fn find_field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields
+            .iter()
+            .find(|field| field.name(&self.constant_pool) == name)
+    }
+
+    /// Find the field named `name` of the `descriptor`, which together
+    /// identify a field like the refs of `getfield` and friends do
+    pub fn find_field_with_descriptor(&self, name: &str, descriptor: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| {
+            field.name(&self.constant_pool) == name
+                && field.descriptor(&self.constant_pool) == descriptor
+        })
+    }
+
+    /// Find `public static void main(String[])`, ignoring overloads
     pub fn find_main_method(&self) -> Option<&MethodInfo> {
-        self.find_method("main")
+        self.methods.iter().find(|method| {
+            method.name(&self.constant_pool) == "main"
+                && method.descriptor(&self.constant_pool) == "([Ljava/lang/String;)V"
+                && method.is_public()
+                && method.is_static()
+        })
+    }
+
+    /// The field or method ref at `index` of the constant pool
+    pub fn member_ref(&self, index: usize) -> &MemberRef {
+        self.member_refs[index]
+            .as_ref()
+            .expect("Not a field or method ref")
     }
+
+    /// Iterate over the methods of the class
+    pub fn methods(&self) -> impl Iterator<Item = Member<'_>> {
+        self.methods.iter().map(|method| Member {
+            name: method.name(&self.constant_pool),
+            descriptor: method.descriptor(&self.constant_pool),
+            access_flags: method.access_flags,
+        })
+    }
+
+    /// Iterate over the fields of the class
+    pub fn fields(&self) -> impl Iterator<Item = Member<'_>> {
+        self.fields.iter().map(|field| Member {
+            name: field.name(&self.constant_pool),
+            descriptor: field.descriptor(&self.constant_pool),
+            access_flags: field.access_flags,
+        })
+    }
+}
+
+/// An entry of the `InnerClasses` attribute, with its names resolved through
+/// the constant pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InnerClass<'a> {
+    pub inner_class: &'a str,
+    /// `None` for local and anonymous classes
+    pub outer_class: Option<&'a str>,
+    /// The simple name, `None` for anonymous classes
+    pub inner_name: Option<&'a str>,
+    pub access_flags: U2,
+}
+
+/// The `EnclosingMethod` attribute of a local or anonymous class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnclosingMethod<'a> {
+    pub class: &'a str,
+    /// The name and descriptor of the method
+    pub method: Option<(&'a str, &'a str)>,
 }
 
-enum AccessFlag {
+/// A method or field of a class, with its name and descriptor
+/// resolved through the constant pool
+#[derive(Debug, Clone, Copy)]
+pub struct Member<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub access_flags: U2,
+}
+
+pub enum AccessFlag {
     Public = 0x0001,
+    Private = 0x0002,
+    Protected = 0x0004,
+    Static = 0x0008,
     Final = 0x0010,
     Super = 0x0020,
     Interface = 0x0200,
@@ -227,20 +719,72 @@ enum AccessFlag {
     Enum = 0x4000,
 }
 
+/// Access flags of methods which share their values with flags of classes
+pub enum MethodAccessFlag {
+    Synchronized = 0x0020,
+    Bridge = 0x0040,
+    Varargs = 0x0080,
+    Native = 0x0100,
+}
+
+/// Which classes may access a field or method, from its access flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    Protected,
+    /// Only the classes of the same package, without an access flag
+    Package,
+    Private,
+}
+
+impl Access {
+    fn of(is_public: bool, is_protected: bool, is_private: bool) -> Self {
+        match (is_public, is_protected, is_private) {
+            (true, _, _) => Access::Public,
+            (_, true, _) => Access::Protected,
+            (_, _, true) => Access::Private,
+            _ => Access::Package,
+        }
+    }
+
+    /// The modifier declaring it, e.g. `private`, or `package-private`
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Public => "public",
+            Access::Protected => "protected",
+            Access::Package => "package-private",
+            Access::Private => "private",
+        }
+    }
+}
+
 impl Read for ClassFile {
     fn read(bytes: &[u8], index: usize) -> Result<(usize, Self)> {
-        let (index, magic) = U4::read(bytes, index)?;
-        assert_eq!(magic, 0xCAFEBABE);
+        let (index, magic) = U4::read(bytes, index).context("reading the magic number")?;
+        if magic != 0xCAFEBABE {
+            bail!("invalid magic number {:#010X}, not a class file", magic);
+        }
         let (index, minor_version) = U2::read(bytes, index)?;
         let (index, major_version) = U2::read(bytes, index)?;
         let (mut index, constant_pool_count) = U2::read(bytes, index)?;
 
         // constant pool index starts from 1
         let mut constant_pool = vec![ConstantInfo::Utf8("".to_string())];
-        for _ in 1..constant_pool_count {
-            let constant = ConstantInfo::read(bytes, index)?;
+        while constant_pool.len() < constant_pool_count as usize {
+            let constant = ConstantInfo::read(bytes, index).with_context(|| {
+                format!(
+                    "reading constant #{} at offset {}",
+                    constant_pool.len(),
+                    index
+                )
+            })?;
             index = constant.0;
+            let wide = matches!(constant.1, ConstantInfo::Long(_) | ConstantInfo::Double(_));
             constant_pool.push(constant.1);
+            // long and double constants take up two entries
+            if wide {
+                constant_pool.push(ConstantInfo::Utf8("".to_string()));
+            }
         }
 
         let (index, access_flags) = U2::read(bytes, index)?;
@@ -249,36 +793,42 @@ impl Read for ClassFile {
 
         let (mut index, interfaces_count) = U2::read(bytes, index)?;
         let mut interfaces = vec![];
-        for _ in 0..interfaces_count {
-            let interface = U2::read(bytes, index)?;
+        for i in 0..interfaces_count {
+            let interface = U2::read(bytes, index)
+                .with_context(|| format!("reading interface #{} at offset {}", i, index))?;
             index = interface.0;
             interfaces.push(interface.1);
         }
 
         let (mut index, fields_count) = U2::read(bytes, index)?;
         let mut fields = vec![];
-        for _ in 0..fields_count {
-            let field = FieldInfo::read(bytes, index)?;
+        for i in 0..fields_count {
+            let field = FieldInfo::read(bytes, index)
+                .with_context(|| format!("reading field #{} at offset {}", i, index))?;
             index = field.0;
             fields.push(field.1);
         }
 
         let (mut index, methods_count) = U2::read(bytes, index)?;
         let mut methods = vec![];
-        for _ in 0..methods_count {
-            let method = MethodInfo::read(bytes, index)?;
+        for i in 0..methods_count {
+            let method = MethodInfo::read(bytes, index)
+                .with_context(|| format!("reading method #{} at offset {}", i, index))?;
             index = method.0;
             methods.push(method.1);
         }
 
         let (mut index, attributes_count) = U2::read(bytes, index)?;
         let mut attributes = vec![];
-        for _ in 0..attributes_count {
-            let attribute = AttributeInfo::read(bytes, index)?;
+        for i in 0..attributes_count {
+            let attribute = AttributeInfo::read(bytes, index)
+                .with_context(|| format!("reading attribute #{} at offset {}", i, index))?;
             index = attribute.0;
             attributes.push(attribute.1);
         }
 
+        let member_refs = resolve_member_refs(&constant_pool)?;
+
         Ok((
             index,
             Self {
@@ -298,11 +848,56 @@ impl Read for ClassFile {
                 methods,
                 attributes_count,
                 attributes,
+                member_refs,
             },
         ))
     }
 }
 
+/// Look up the names of all field and method refs once, so that executing
+/// an instruction doesn't have to walk the constant pool again
+fn resolve_member_refs(constant_pool: &[ConstantInfo]) -> Result<Vec<Option<MemberRef>>> {
+    let utf8 = |index: u16| {
+        constant_pool
+            .get(index as usize)
+            .and_then(ConstantInfo::as_utf8)
+            .ok_or_else(|| anyhow!("invalid Utf8 reference #{}", index))
+    };
+    constant_pool
+        .iter()
+        .map(|constant| {
+            let (class_index, name_and_type_index) = match constant {
+                ConstantInfo::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantInfo::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | ConstantInfo::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => (*class_index, *name_and_type_index),
+                _ => return Ok(None),
+            };
+            let class_name_index = constant_pool
+                .get(class_index as usize)
+                .and_then(ConstantInfo::as_class)
+                .ok_or_else(|| anyhow!("invalid Class reference #{}", class_index))?;
+            let (name_index, descriptor_index) = constant_pool
+                .get(name_and_type_index as usize)
+                .and_then(ConstantInfo::as_name_and_type)
+                .ok_or_else(|| anyhow!("invalid NameAndType reference #{}", name_and_type_index))?;
+            Ok(Some(MemberRef {
+                class: utf8(class_name_index)?.to_string(),
+                name: utf8(name_index)?.to_string(),
+                descriptor: utf8(descriptor_index)?.to_string(),
+            }))
+        })
+        .collect()
+}
+
 impl Read for U1 {
     fn read(bytes: &[u8], index: usize) -> Result<(usize, Self)> {
         bound_check(bytes, index, 1)?;
@@ -403,10 +998,26 @@ impl Read for ConstantInfo {
                 let (index, string_index) = U2::read(bytes, index)?;
                 (index, ConstantInfo::String(string_index))
             }
-            ConstantPoolTag::Integer => todo!("integer"),
-            ConstantPoolTag::Float => todo!("float"),
-            ConstantPoolTag::Long => todo!("long"),
-            ConstantPoolTag::Double => todo!("double"),
+            ConstantPoolTag::Integer => {
+                let (index, bytes) = U4::read(bytes, index)?;
+                (index, ConstantInfo::Integer(bytes as i32))
+            }
+            ConstantPoolTag::Float => {
+                let (index, bytes) = U4::read(bytes, index)?;
+                (index, ConstantInfo::Float(f32::from_bits(bytes)))
+            }
+            ConstantPoolTag::Long => {
+                let (index, high_bytes) = U4::read(bytes, index)?;
+                let (index, low_bytes) = U4::read(bytes, index)?;
+                let value = ((high_bytes as u64) << 32) | (low_bytes as u64);
+                (index, ConstantInfo::Long(value as i64))
+            }
+            ConstantPoolTag::Double => {
+                let (index, high_bytes) = U4::read(bytes, index)?;
+                let (index, low_bytes) = U4::read(bytes, index)?;
+                let value = ((high_bytes as u64) << 32) | (low_bytes as u64);
+                (index, ConstantInfo::Double(f64::from_bits(value)))
+            }
             ConstantPoolTag::NameAndType => {
                 let (index, name_index) = U2::read(bytes, index)?;
                 let (index, descriptor_index) = U2::read(bytes, index)?;
@@ -420,16 +1031,55 @@ impl Read for ConstantInfo {
             }
             ConstantPoolTag::Utf8 => {
                 let (index, length) = U2::read(bytes, index)?;
-                let length = length as usize;
-                let string = String::from_utf8_lossy(&bytes[index..(index + length)]).to_string();
-                (index + length, ConstantInfo::Utf8(string))
+                let (index, string) = read_bytes(bytes, index, length as usize)?;
+                let string = String::from_utf8_lossy(string).to_string();
+                (index, ConstantInfo::Utf8(string))
+            }
+            ConstantPoolTag::MethodHandle => {
+                let (index, reference_kind) = U1::read(bytes, index)?;
+                let (index, reference_index) = U2::read(bytes, index)?;
+                (
+                    index,
+                    ConstantInfo::MethodHandle {
+                        reference_kind,
+                        reference_index,
+                    },
+                )
+            }
+            ConstantPoolTag::MethodType => {
+                let (index, descriptor_index) = U2::read(bytes, index)?;
+                (index, ConstantInfo::MethodType { descriptor_index })
+            }
+            ConstantPoolTag::Dynamic => {
+                let (index, bootstrap_method_attr_index) = U2::read(bytes, index)?;
+                let (index, name_and_type_index) = U2::read(bytes, index)?;
+                (
+                    index,
+                    ConstantInfo::Dynamic {
+                        bootstrap_method_attr_index,
+                        name_and_type_index,
+                    },
+                )
+            }
+            ConstantPoolTag::InvokeDynamic => {
+                let (index, bootstrap_method_attr_index) = U2::read(bytes, index)?;
+                let (index, name_and_type_index) = U2::read(bytes, index)?;
+                (
+                    index,
+                    ConstantInfo::InvokeDynamic {
+                        bootstrap_method_attr_index,
+                        name_and_type_index,
+                    },
+                )
+            }
+            ConstantPoolTag::Module => {
+                let (index, name_index) = U2::read(bytes, index)?;
+                (index, ConstantInfo::Module { name_index })
+            }
+            ConstantPoolTag::Package => {
+                let (index, name_index) = U2::read(bytes, index)?;
+                (index, ConstantInfo::Package { name_index })
             }
-            ConstantPoolTag::MethodHandle => todo!("method handle"),
-            ConstantPoolTag::MethodType => todo!("method type"),
-            ConstantPoolTag::Dynamic => todo!("dynamic"),
-            ConstantPoolTag::InvokeDynamic => todo!("invoke dynamic"),
-            ConstantPoolTag::Module => todo!("module"),
-            ConstantPoolTag::Package => todo!("package"),
         })
     }
 }
@@ -441,8 +1091,9 @@ impl Read for FieldInfo {
         let (index, descriptor_index) = U2::read(bytes, index)?;
         let (mut index, attributes_count) = U2::read(bytes, index)?;
         let mut attributes = vec![];
-        for _ in 0..attributes_count {
-            let attribute = AttributeInfo::read(bytes, index)?;
+        for i in 0..attributes_count {
+            let attribute = AttributeInfo::read(bytes, index)
+                .with_context(|| format!("reading attribute #{} at offset {}", i, index))?;
             index = attribute.0;
             attributes.push(attribute.1);
         }
@@ -466,8 +1117,9 @@ impl Read for MethodInfo {
         let (index, descriptor_index) = U2::read(bytes, index)?;
         let (mut index, attributes_count) = U2::read(bytes, index)?;
         let mut attributes = vec![];
-        for _ in 0..attributes_count {
-            let attribute = AttributeInfo::read(bytes, index)?;
+        for i in 0..attributes_count {
+            let attribute = AttributeInfo::read(bytes, index)
+                .with_context(|| format!("reading attribute #{} at offset {}", i, index))?;
             index = attribute.0;
             attributes.push(attribute.1);
         }
@@ -479,6 +1131,7 @@ impl Read for MethodInfo {
                 descriptor_index,
                 attributes_count,
                 attributes,
+                code: OnceCell::new(),
             },
         ))
     }
@@ -488,13 +1141,21 @@ impl Read for AttributeInfo {
     fn read(bytes: &[u8], index: usize) -> Result<(usize, Self)> {
         let (index, attribute_name_index) = U2::read(bytes, index)?;
         let (index, attribute_length) = U4::read(bytes, index)?;
-        let info = bytes[index..(index + attribute_length as usize)].to_vec();
+        let remaining = bytes.len() - index;
+        if attribute_length as usize > remaining {
+            bail!(
+                "attribute length {} exceeds the {} bytes remaining",
+                attribute_length,
+                remaining
+            );
+        }
+        let (index, info) = read_bytes(bytes, index, attribute_length as usize)?;
         Ok((
-            index + attribute_length as usize,
+            index,
             Self {
                 attribute_name_index,
                 attribute_length,
-                info,
+                info: info.to_vec(),
             },
         ))
     }
@@ -522,26 +1183,67 @@ impl Display for ConstantInfo {
                 class_index, name_and_type_index
             ),
             String(string_index) => write!(f, "String #{}", string_index),
-            Integer => write!(f, "Integer"),
-            Float => write!(f, "Float"),
-            Long => write!(f, "Long"),
-            Double => write!(f, "Double"),
             NameAndType {
                 name_index,
                 descriptor_index,
             } => write!(f, "NameAndType #{}:#{}", name_index, descriptor_index),
             Utf8(string) => write!(f, "Utf8 \"{}\"", string),
-            MethodHandle => write!(f, "MethodHandle"),
-            MethodType => write!(f, "MethodType"),
-            Dynamic => write!(f, "Dynamic"),
-            InvokeDynamic => write!(f, "InvokeDynamic"),
-            Module => write!(f, "Module"),
-            Package => write!(f, "Package"),
+            Integer(value) => write!(f, "Integer {}", value),
+            Float(value) => write!(f, "Float {}f", java_decimal(*value)),
+            Long(value) => write!(f, "Long {}l", value),
+            Double(value) => write!(f, "Double {}d", java_decimal(*value)),
+            MethodHandle {
+                reference_kind,
+                reference_index,
+            } => write!(f, "MethodHandle {}:#{}", reference_kind, reference_index),
+            MethodType { descriptor_index } => write!(f, "MethodType #{}", descriptor_index),
+            Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => write!(
+                f,
+                "Dynamic #{}:#{}",
+                bootstrap_method_attr_index, name_and_type_index
+            ),
+            InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => write!(
+                f,
+                "InvokeDynamic #{}:#{}",
+                bootstrap_method_attr_index, name_and_type_index
+            ),
+            Module { name_index } => write!(f, "Module #{}", name_index),
+            Package { name_index } => write!(f, "Package #{}", name_index),
         }
     }
 }
 
 impl ConstantInfo {
+    /// The name of the tag of this constant, as used by the class file format
+    pub fn tag_name(&self) -> &'static str {
+        use ConstantInfo::*;
+        match self {
+            MethodRef { .. } => "Methodref",
+            FieldRef { .. } => "Fieldref",
+            InterfaceMethodRef { .. } => "InterfaceMethodref",
+            Class { .. } => "Class",
+            NameAndType { .. } => "NameAndType",
+            Utf8(_) => "Utf8",
+            String(_) => "String",
+            Integer(_) => "Integer",
+            Float(_) => "Float",
+            Long(_) => "Long",
+            Double(_) => "Double",
+            MethodHandle { .. } => "MethodHandle",
+            MethodType { .. } => "MethodType",
+            Dynamic { .. } => "Dynamic",
+            InvokeDynamic { .. } => "InvokeDynamic",
+            Module { .. } => "Module",
+            Package { .. } => "Package",
+        }
+    }
+
     pub fn as_utf8(&self) -> Option<&str> {
         match self {
             ConstantInfo::Utf8(string) => Some(string),
@@ -585,7 +1287,99 @@ impl ConstantInfo {
             _ => None,
         }
     }
-    
+}
+
+/// Escape the control characters of `string` like `\u0001`, which keeps a
+/// listing of constants to one line per entry
+fn escape_control(string: &str) -> String {
+    string
+        .chars()
+        .map(|c| match c.is_control() {
+            true => format!("\\u{:04x}", c as u32),
+            false => c.to_string(),
+        })
+        .collect()
+}
+
+/// The attribute named `name` among `attributes`
+///
+/// Attributes of other names, including those unknown to the VM, are
+/// skipped, and so are those whose name isn't a valid constant.
+fn find_attribute<'a>(
+    attributes: &'a [AttributeInfo],
+    constant_pool: &[ConstantInfo],
+    name: &str,
+) -> Option<&'a AttributeInfo> {
+    attributes.iter().find(|attribute| {
+        constant_pool
+            .get(attribute.attribute_name_index as usize)
+            .and_then(ConstantInfo::as_utf8)
+            == Some(name)
+    })
+}
+
+/// Whether one of `attributes` is named `name`
+fn has_attribute(attributes: &[AttributeInfo], constant_pool: &[ConstantInfo], name: &str) -> bool {
+    find_attribute(attributes, constant_pool, name).is_some()
+}
+
+impl<'a> FieldInfo {
+    pub fn name(&'a self, constant_pool: &'a [ConstantInfo]) -> &'a str {
+        constant_pool[self.name_index as usize].as_utf8().unwrap()
+    }
+
+    pub fn descriptor(&'a self, constant_pool: &'a [ConstantInfo]) -> &'a str {
+        constant_pool[self.descriptor_index as usize]
+            .as_utf8()
+            .unwrap()
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & AccessFlag::Static as U2 != 0
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.access_flags & AccessFlag::Final as U2 != 0
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.access_flags & AccessFlag::Public as U2 != 0
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.access_flags & AccessFlag::Protected as U2 != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & AccessFlag::Private as U2 != 0
+    }
+
+    pub fn access(&self) -> Access {
+        Access::of(self.is_public(), self.is_protected(), self.is_private())
+    }
+
+    /// Whether the field was generated by the compiler
+    pub fn is_synthetic(&self, constant_pool: &[ConstantInfo]) -> bool {
+        self.access_flags & AccessFlag::Synthetic as U2 != 0
+            || has_attribute(&self.attributes, constant_pool, "Synthetic")
+    }
+
+    pub fn is_deprecated(&self, constant_pool: &[ConstantInfo]) -> bool {
+        has_attribute(&self.attributes, constant_pool, "Deprecated")
+    }
+
+    /// The index of the constant in the `ConstantValue` attribute, which a
+    /// `static final` field of a primitive or string type is initialized to
+    ///
+    /// A long or double constant takes two entries of the pool, and this is
+    /// the index of the first.
+    pub fn constant_value(&self, constant_pool: &[ConstantInfo]) -> Option<u16> {
+        let attribute = find_attribute(&self.attributes, constant_pool, "ConstantValue")?;
+        let [high, low] = attribute.info[..] else {
+            return None;
+        };
+        Some(u16::from_be_bytes([high, low]))
+    }
 }
 
 impl<'a> MethodInfo {
@@ -593,16 +1387,481 @@ impl<'a> MethodInfo {
         constant_pool[self.name_index as usize].as_utf8().unwrap()
     }
 
-    pub fn code(&self, constant_pool: &[ConstantInfo]) -> CodeAttribute {
-        for attribute in &self.attributes {
-            if constant_pool[attribute.attribute_name_index as usize]
-                .as_utf8()
-                .unwrap()
-                == "Code"
-            {
-                return (*attribute.info).into();
+    pub fn descriptor(&'a self, constant_pool: &'a [ConstantInfo]) -> &'a str {
+        constant_pool[self.descriptor_index as usize]
+            .as_utf8()
+            .unwrap()
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.access_flags & AccessFlag::Public as U2 != 0
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.access_flags & AccessFlag::Protected as U2 != 0
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.access_flags & AccessFlag::Private as U2 != 0
+    }
+
+    pub fn access(&self) -> Access {
+        Access::of(self.is_public(), self.is_protected(), self.is_private())
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.access_flags & AccessFlag::Static as U2 != 0
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags & MethodAccessFlag::Synchronized as U2 != 0
+    }
+
+    /// Whether the last parameter takes a variable number of arguments
+    pub fn is_varargs(&self) -> bool {
+        self.access_flags & MethodAccessFlag::Varargs as U2 != 0
+    }
+
+    /// Whether the method was generated by the compiler, e.g. a bridge method
+    pub fn is_synthetic(&self, constant_pool: &[ConstantInfo]) -> bool {
+        self.access_flags & AccessFlag::Synthetic as U2 != 0
+            || has_attribute(&self.attributes, constant_pool, "Synthetic")
+    }
+
+    pub fn is_deprecated(&self, constant_pool: &[ConstantInfo]) -> bool {
+        has_attribute(&self.attributes, constant_pool, "Deprecated")
+    }
+
+    /// Whether the method has code, which abstract and native methods don't
+    pub fn has_code(&self, constant_pool: &[ConstantInfo]) -> bool {
+        has_attribute(&self.attributes, constant_pool, "Code")
+    }
+
+    /// The `Code` attribute, which is only parsed on the first call
+    ///
+    /// # Panics
+    ///
+    /// If the method has no code, like abstract and native methods, or
+    /// the attribute is malformed; use [`MethodInfo::try_code`] for methods
+    /// which may not have any.
+    pub fn code(&self, constant_pool: &[ConstantInfo]) -> Rc<CodeAttribute> {
+        self.read_code(constant_pool)
+            .unwrap_or_else(|err| panic!("{:#}", err))
+    }
+
+    /// The `Code` attribute like [`MethodInfo::code`], or `None` if there is
+    /// none or it is malformed
+    pub fn try_code(&self, constant_pool: &[ConstantInfo]) -> Option<Rc<CodeAttribute>> {
+        self.read_code(constant_pool).ok()
+    }
+
+    /// The `Code` attribute like [`MethodInfo::code`], or why it can't be read
+    pub(crate) fn read_code(&self, constant_pool: &[ConstantInfo]) -> Result<Rc<CodeAttribute>> {
+        if let Some(code) = self.code.get() {
+            return Ok(code.clone());
+        }
+        // the name is only for the error, which may be about a broken pool
+        let name = || {
+            constant_pool
+                .get(self.name_index as usize)
+                .and_then(ConstantInfo::as_utf8)
+                .unwrap_or("the method")
+        };
+        let attribute = find_attribute(&self.attributes, constant_pool, "Code")
+            .ok_or_else(|| anyhow!("{} has no Code attribute", name()))?;
+        let code = CodeAttribute::parse(&attribute.info)
+            .with_context(|| format!("reading the code of {}", name()))?;
+        Ok(self.code.get_or_init(|| Rc::new(code)).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{Options, Vm};
+
+    /// The bytes of the class file of the test program `name` of `tests/java`
+    fn test_class_bytes(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/java/{}.class", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    }
+
+    /// Parse a class file from `bytes`, giving the error with its context
+    fn parse(bytes: &[u8]) -> std::result::Result<ClassFile, String> {
+        ClassFile::read(bytes, 0)
+            .map(|(_, class)| class)
+            .map_err(|err| format!("{:#}", err))
+    }
+
+    #[test]
+    fn huge_attribute_length_is_a_parse_error() {
+        let mut bytes = test_class_bytes("Ids");
+        // the SourceFile attribute comes last, with a length of 2
+        let length = bytes.len() - 6;
+        assert_eq!(bytes[length..length + 4], [0, 0, 0, 2]);
+        bytes[length..length + 4].copy_from_slice(&[0xff; 4]);
+        let err = parse(&bytes).err().unwrap();
+        assert!(
+            err.contains("attribute length 4294967295 exceeds the 2 bytes remaining"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn enumerates_methods_and_fields() {
+        let class = parse(&test_class_bytes("CompoundField")).unwrap();
+        let methods = class
+            .methods()
+            .map(|method| (method.name, method.descriptor))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            [("<init>", "()V"), ("main", "([Ljava/lang/String;)V")]
+        );
+        let fields = class
+            .fields()
+            .map(|field| (field.name, field.descriptor, field.access_flags))
+            .collect::<Vec<_>>();
+        assert_eq!(fields, [("f", "I", 0)]);
+    }
+
+    #[test]
+    fn bridge_methods_are_synthetic() {
+        let class = parse(&test_class_bytes("Bridges")).unwrap();
+        let cp = &class.constant_pool;
+        let bridge = class
+            .find_method_with_descriptor("compareTo", "(Ljava/lang/Object;)I")
+            .unwrap();
+        assert!(bridge.is_synthetic(cp));
+        let method = class
+            .find_method_with_descriptor("compareTo", "(LBridges;)I")
+            .unwrap();
+        assert!(!method.is_synthetic(cp));
+        assert!(class.find_field("old").unwrap().is_deprecated(cp));
+        assert!(!class.find_field("value").unwrap().is_deprecated(cp));
+    }
+
+    #[test]
+    fn main_method_skips_overloads() {
+        let class = parse(&test_class_bytes("DecoyMain")).unwrap();
+        let main = class.find_main_method().unwrap();
+        assert_eq!(
+            main.descriptor(&class.constant_pool),
+            "([Ljava/lang/String;)V"
+        );
+    }
+
+    #[test]
+    fn member_refs_are_resolved_when_parsing() {
+        let class = parse(&test_class_bytes("CompoundField")).unwrap();
+        let refs = class
+            .member_refs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, member)| member.as_ref().map(|member| (index, member)))
+            .map(|(index, member)| {
+                let name = format!("{}.{}:{}", member.class, member.name, member.descriptor);
+                (index, name)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            refs,
+            [
+                (1, "java/lang/Object.<init>:()V".to_string()),
+                (9, "CompoundField.<init>:()V".to_string()),
+                (10, "CompoundField.f:I".to_string()),
+                (14, "java/lang/System.out:Ljava/io/PrintStream;".to_string()),
+                (20, "java/io/PrintStream.println:(I)V".to_string()),
+            ]
+        );
+        assert_eq!(class.member_ref(10).name, "f");
+    }
+
+    /// `attributes` as in a class file, with an attribute named by the
+    /// constant `unknown` added if there is one
+    fn write_attributes(attributes: &[AttributeInfo], unknown: Option<U2>) -> Vec<u8> {
+        let count = attributes.len() + unknown.iter().count();
+        let mut bytes = (count as U2).to_be_bytes().to_vec();
+        for attribute in attributes {
+            bytes.extend(attribute.attribute_name_index.to_be_bytes());
+            bytes.extend((attribute.info.len() as U4).to_be_bytes());
+            bytes.extend(&attribute.info);
+        }
+        if let Some(name_index) = unknown {
+            bytes.extend(name_index.to_be_bytes());
+            bytes.extend(4u32.to_be_bytes());
+            bytes.extend([0xca, 0xfe, 0xba, 0xbe]);
+        }
+        bytes
+    }
+
+    /// What follows the constant pool in the class file of `class`, with an
+    /// attribute named by the constant `unknown` added to the class and to
+    /// every field, method and `Code` attribute if there is one
+    fn write_after_constant_pool(class: &ClassFile, unknown: Option<U2>) -> Vec<u8> {
+        let cp = &class.constant_pool;
+        let mut bytes = vec![];
+        let header = [class.access_flags, class.this_class, class.super_class];
+        for u2 in header.into_iter().chain([class.interfaces.len() as U2]) {
+            bytes.extend(u2.to_be_bytes());
+        }
+        for interface in &class.interfaces {
+            bytes.extend(interface.to_be_bytes());
+        }
+        bytes.extend((class.fields.len() as U2).to_be_bytes());
+        for field in &class.fields {
+            let u2s = [field.access_flags, field.name_index, field.descriptor_index];
+            bytes.extend(u2s.into_iter().flat_map(U2::to_be_bytes));
+            bytes.extend(write_attributes(&field.attributes, unknown));
+        }
+        bytes.extend((class.methods.len() as U2).to_be_bytes());
+        for method in &class.methods {
+            let u2s = [
+                method.access_flags,
+                method.name_index,
+                method.descriptor_index,
+            ];
+            bytes.extend(u2s.into_iter().flat_map(U2::to_be_bytes));
+            let mut attributes = method.attributes.clone();
+            for attribute in &mut attributes {
+                let name = cp[attribute.attribute_name_index as usize].as_utf8();
+                if unknown.is_none() || name != Some("Code") {
+                    continue;
+                }
+                let code = CodeAttribute::parse(&attribute.info).unwrap();
+                let end = attribute.info.len() - write_attributes(&code.attributes, None).len();
+                attribute.info.truncate(end);
+                attribute
+                    .info
+                    .extend(write_attributes(&code.attributes, unknown));
             }
+            bytes.extend(write_attributes(&attributes, unknown));
+        }
+        bytes.extend(write_attributes(&class.attributes, unknown));
+        bytes
+    }
+
+    #[test]
+    fn unknown_attributes_are_kept_and_ignored() {
+        let bytes = test_class_bytes("Uncaught");
+        let class = parse(&bytes).unwrap();
+        let rest = write_after_constant_pool(&class, None);
+        let constant_pool_end = bytes.len() - rest.len();
+        assert_eq!(rest, bytes[constant_pool_end..]);
+        // a new constant naming the attribute, at the end of the pool
+        let unknown = class.constant_pool_count;
+        let name = b"com.example.Unknown";
+        let mut patched = bytes[..constant_pool_end].to_vec();
+        patched[8..10].copy_from_slice(&(unknown + 1).to_be_bytes());
+        patched.push(ConstantPoolTag::Utf8 as U1);
+        patched.extend((name.len() as U2).to_be_bytes());
+        patched.extend(name);
+        patched.extend(write_after_constant_pool(&class, Some(unknown)));
+
+        let patched = parse(&patched).unwrap();
+        let attribute = patched.attributes.last().unwrap();
+        assert_eq!(attribute.attribute_name_index, unknown);
+        assert_eq!(attribute.info, [0xca, 0xfe, 0xba, 0xbe]);
+        assert_eq!(patched.source_file(), Some("Uncaught.java"));
+        let mut out = vec![];
+        let result = Vm::with_output(patched, Options::default(), &mut out).run_main();
+        assert_eq!(out, b"before\n");
+        // the line numbers are still found next to the unknown attribute
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Exception in thread \"main\" java.lang.RuntimeException: boom\n\
+             \tat Uncaught.fail(Uncaught.java:3)\n\
+             \tat Uncaught.main(Uncaught.java:8)"
+        );
+    }
+
+    #[test]
+    fn reads_the_class_file_version() {
+        let class = parse(&test_class_bytes("Java8")).unwrap();
+        assert_eq!(class.version(), (52, 0));
+    }
+
+    #[test]
+    fn code_is_parsed_once() {
+        let mut class = parse(&test_class_bytes("CompoundField")).unwrap();
+        let main = class
+            .methods
+            .iter()
+            .position(|method| method.name(&class.constant_pool) == "main");
+        let main = &mut class.methods[main.unwrap()];
+        let code = main.code(&class.constant_pool);
+        // a second parse of the attribute would fail now
+        for attribute in &mut main.attributes {
+            attribute.info.clear();
+        }
+        for _ in 0..3 {
+            assert!(Rc::ptr_eq(&main.code(&class.constant_pool), &code));
+        }
+    }
+
+    #[test]
+    fn inner_class_entries_resolve_their_names() {
+        let class = parse(&test_class_bytes("Nesting")).unwrap();
+        let entry = |inner_class, outer_class, inner_name, access_flags| InnerClass {
+            inner_class,
+            outer_class,
+            inner_name,
+            access_flags,
+        };
+        assert_eq!(
+            class.inner_classes(),
+            [
+                entry("Nesting$1", None, None, 0),
+                entry("Nesting$Inner", Some("Nesting"), Some("Inner"), 0),
+                entry("Nesting$Nested", Some("Nesting"), Some("Nested"), 0x0008),
+                entry("Nesting$1Local", None, Some("Local"), 0),
+            ]
+        );
+        assert_eq!(class.enclosing_method(), None);
+        let anonymous = parse(&test_class_bytes("Nesting$1")).unwrap();
+        assert_eq!(
+            anonymous.enclosing_method(),
+            Some(EnclosingMethod {
+                class: "Nesting",
+                method: Some(("make", "()Ljava/lang/Object;")),
+            })
+        );
+    }
+
+    #[test]
+    fn dumps_the_constant_pool_like_javap() {
+        let class = parse(&test_class_bytes("CompoundField")).unwrap();
+        let dump = class.dump_constant_pool();
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Constant pool:");
+        // the pool is indexed from 1
+        assert_eq!(lines.len(), class.constant_pool_count as usize);
+        assert_eq!(
+            lines[10],
+            "  #10 = Fieldref           #7.#11         // CompoundField.f:I"
+        );
+        assert_eq!(
+            lines[20],
+            "  #20 = Methodref          #21.#22        // java/io/PrintStream.println:(I)V"
+        );
+        assert_eq!(lines[38], "  #38 = Utf8               CompoundField.java");
+    }
+
+    #[test]
+    fn info_declares_the_class() {
+        let class = parse(&test_class_bytes("Hello")).unwrap();
+        let info = class.info();
+        assert!(
+            info.starts_with("public class Hello extends java/lang/Object\n"),
+            "{}",
+            info
+        );
+        assert!(info.contains("  source file: Hello.java\n"), "{}", info);
+        let bridges = parse(&test_class_bytes("Bridges")).unwrap();
+        assert_eq!(
+            bridges.declaration(),
+            "public class Bridges extends java/lang/Object implements java/lang/Comparable"
+        );
+    }
+
+    #[test]
+    // the value is the one of the Java source, not meant to be `PI`
+    #[allow(clippy::approx_constant)]
+    fn constant_values_index_wide_constants() {
+        let class = parse(&test_class_bytes("WideConstants")).unwrap();
+        let cp = &class.constant_pool;
+        let constant = |name| {
+            let field = class.find_field(name).unwrap();
+            &cp[field.constant_value(cp).unwrap() as usize]
+        };
+        assert!(matches!(constant("PI"), ConstantInfo::Double(pi) if *pi == 3.14159));
+        assert!(matches!(constant("BIG"), ConstantInfo::Long(big) if *big == 1 << 40));
+        assert!(matches!(constant("HALF"), ConstantInfo::Float(half) if *half == 0.5));
+    }
+
+    #[test]
+    fn fields_are_found_by_name_and_descriptor() {
+        let class = parse(&test_class_bytes("WideFields")).unwrap();
+        let cp = &class.constant_pool;
+        assert_eq!(class.find_field("count").unwrap().descriptor(cp), "J");
+        assert_eq!(class.find_field("ratio").unwrap().descriptor(cp), "D");
+        assert!(class.find_field("missing").is_none());
+        let ratio = class.find_field_with_descriptor("ratio", "D").unwrap();
+        assert_eq!(ratio.name(cp), "ratio");
+        assert!(class.find_field_with_descriptor("ratio", "F").is_none());
+        // only the fields the class declares itself
+        let sub = parse(&test_class_bytes("InheritedField$Sub")).unwrap();
+        assert_eq!(
+            sub.find_field("y").unwrap().descriptor(&sub.constant_pool),
+            "I"
+        );
+        assert!(sub.find_field("x").is_none());
+    }
+
+    #[test]
+    fn truncation_inside_a_method_names_the_method() {
+        let mut bytes = test_class_bytes("Hello");
+        // drop the SourceFile attribute, the attribute count before it and
+        // the last 5 bytes of the Code attribute of main
+        bytes.truncate(bytes.len() - 8 - 2 - 5);
+        let err = parse(&bytes).err().unwrap();
+        assert!(err.starts_with("reading method #1 at offset "), "{}", err);
+        assert!(err.contains(": reading attribute #0 at offset "), "{}", err);
+    }
+
+    #[test]
+    fn abstract_methods_have_no_code() {
+        let shape = parse(&test_class_bytes("Interfaces$Shape")).unwrap();
+        let cp = &shape.constant_pool;
+        assert_eq!(shape.methods.len(), 1);
+        for method in &shape.methods {
+            assert!(!method.has_code(cp));
+            assert!(method.try_code(cp).is_none());
         }
-        panic!("no code attribute found")
+        let mut base = parse(&test_class_bytes("Interfaces$Base")).unwrap();
+        let sides = base.find_method("sides").unwrap();
+        assert!(sides.try_code(&base.constant_pool).is_some());
+        // an attribute name outside the pool names no attribute
+        let init = base
+            .methods
+            .iter_mut()
+            .find(|method| method.code.get().is_none());
+        let init = init.unwrap();
+        init.attributes[0].attribute_name_index = u16::MAX;
+        assert!(init.try_code(&base.constant_pool).is_none());
+    }
+
+    #[test]
+    fn attribute_just_longer_than_the_file_is_a_parse_error() {
+        let mut bytes = test_class_bytes("Ids");
+        let length = bytes.len() - 6;
+        bytes[length..length + 4].copy_from_slice(&[0, 0, 0, 3]);
+        let err = parse(&bytes).err().unwrap();
+        assert!(
+            err.contains("attribute length 3 exceeds the 2 bytes remaining"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn code_attribute_must_be_used_up_exactly() {
+        let class = parse(&test_class_bytes("Hello")).unwrap();
+        let main = class.find_main_method().unwrap();
+        let mut info = find_attribute(&main.attributes, &class.constant_pool, "Code")
+            .unwrap()
+            .info
+            .clone();
+        assert!(CodeAttribute::parse(&info).is_ok());
+        info.push(0);
+        let err = format!("{:#}", CodeAttribute::parse(&info).err().unwrap());
+        assert!(
+            err.ends_with(" bytes has 1 more than its contents"),
+            "{}",
+            err
+        );
+        info.truncate(info.len() - 2);
+        assert!(CodeAttribute::parse(&info).is_err());
     }
 }
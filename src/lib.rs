@@ -0,0 +1,5 @@
+pub mod bytecode;
+pub mod class_file;
+pub mod runtime;
+pub mod verifier;
+pub mod vm;
@@ -1,303 +1,288 @@
-mod bytecode;
-mod class_file;
-mod runtime;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
-use bytecode::ByteCode;
-use class_file::{ClassFile, ConstantInfo};
-use runtime::{Frame, Heap, Value};
+use jrm::class_file::ClassFile;
+use jrm::runtime::{GcMode, RuntimeError, Value};
+use jrm::verifier;
+use jrm::vm::{find_class_file, Options, Vm};
 
-fn args_size(descriptor: &str) -> usize {
-    let mut size = 0;
-    let mut chars = descriptor.chars();
-    while let Some(c) = chars.next() {
-        match c {
-            '(' => {}
-            ')' => break,
-            'I' => size += 1,
-            'L' => {
-                for c in chars.by_ref() {
-                    if c == ';' {
-                        break;
-                    }
-                }
-                size += 1;
+/// A static method to invoke instead of `main`, with its arguments
+struct Invocation {
+    class: String,
+    method: String,
+    args: Vec<Value>,
+}
+
+/// Parse `Class.method(args...)`, where the arguments are int, boolean or
+/// string literals separated by commas
+fn parse_invocation(target: &str) -> Option<Invocation> {
+    let (name, args) = target.strip_suffix(')')?.split_once('(')?;
+    let (class, method) = name.rsplit_once('.')?;
+    let mut values = vec![];
+    let mut rest = args.trim();
+    while !rest.is_empty() {
+        let (arg, next) = match rest.strip_prefix('"') {
+            // strings are quoted, so they may contain commas
+            Some(string) => {
+                let (string, next) = string.split_once('"')?;
+                (Value::from(string.to_string()), next.trim_start())
             }
-            _ => unimplemented!("Not implemented args_size for {}", c),
-        }
+            None => {
+                let (literal, next) = rest.split_at(rest.find(',').unwrap_or(rest.len()));
+                let literal = literal.trim();
+                let value = match literal {
+                    "true" => Value::from(true),
+                    "false" => Value::from(false),
+                    _ => Value::from(literal.parse::<i32>().ok()?),
+                };
+                (value, next)
+            }
+        };
+        values.push(arg);
+        rest = match next.strip_prefix(',') {
+            Some(next) => next.trim_start(),
+            None if next.trim().is_empty() => "",
+            None => return None,
+        };
     }
-    size
+    Some(Invocation {
+        class: class.replace('.', "/"),
+        method: method.to_string(),
+        args: values,
+    })
 }
 
-fn class_method(index: usize, constant_pool: &[ConstantInfo]) -> (&str, &str) {
-    let (class_index, name_and_type_index) = constant_pool[index].as_method_ref().unwrap();
-    let class_index = constant_pool[class_index as usize].as_class().unwrap();
-    let class_name = constant_pool[class_index as usize].as_utf8().unwrap();
-    let (name_index, type_index) = constant_pool[name_and_type_index as usize]
-        .as_name_and_type()
-        .unwrap();
-    let (method_name, _type) = (
-        constant_pool[name_index as usize].as_utf8().unwrap(),
-        constant_pool[type_index as usize].as_utf8().unwrap(),
-    );
-    (class_name, method_name)
+/// Check the class files at `paths` without running them, printing the
+/// problems found in any of their methods, and return the exit code, which
+/// is 1 if there were any
+fn verify(paths: &[String]) -> i32 {
+    let mut failed = false;
+    for path in paths {
+        let class = match ClassFile::parse(path) {
+            Ok(class) => class,
+            Err(err) => {
+                println!("{}: failed to parse: {:#}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+        let problems = verifier::problems(&class);
+        for problem in &problems {
+            println!("{}: {}", path, problem);
+        }
+        failed |= !problems.is_empty();
+    }
+    failed as i32
 }
 
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
-    if args.len() < 2 {
-        println!("Usage: {} <class file>", args[0]);
-        return;
+    if args.get(1).map(String::as_str) == Some("verify") {
+        if args.len() == 2 {
+            println!("Usage: {} verify <class file>...", args[0]);
+            std::process::exit(1);
+        }
+        std::process::exit(verify(&args[2..]));
     }
-    let path = &args[1];
-
-    let class = ClassFile::parse(path).unwrap();
-    let classes = [&class];
-    let constant_pool = &class.constant_pool;
-    let main_method = class.find_main_method().expect("No main method found");
-    let code_attribute = main_method.code(constant_pool);
-
-    let mut heap = Heap::new();
-    let frame = Frame::new(
-        main_method.name(constant_pool),
-        &code_attribute.code,
-        constant_pool,
-        code_attribute.max_locals,
-        code_attribute.max_stack,
-    );
-    let mut stack = vec![frame];
-    let mut current_frame_index = 0;
-
-    loop {
-        let current_frame = &mut stack[current_frame_index];
-        let func = current_frame.name.clone();
-        let mut next_frame_index = current_frame_index;
-        let bc = current_frame.fetch();
-        match bc {
-            ByteCode::Return => {
-                if current_frame_index == 0 {
-                    return;
-                } else {
-                    stack.pop();
-                    next_frame_index = current_frame_index - 1;
-                }
-            }
-            ByteCode::IReturn => {
-                assert_ne!(current_frame_index, 0);
-                let value = current_frame.operand_stack.pop().unwrap();
-                stack.pop();
-                next_frame_index = current_frame_index - 1;
-                let current_frame = &mut stack[next_frame_index];
-                current_frame.operand_stack.push(value);
-            }
-            ByteCode::IConst(value) => {
-                current_frame.operand_stack.push(Value::Int(value));
-            }
-            ByteCode::Bipush(value) => {
-                current_frame.operand_stack.push(Value::Int(value as i32));
-            }
-            ByteCode::IStore(index) => {
-                let value = current_frame.operand_stack.pop().unwrap();
-                assert!(matches!(value, Value::Int(_)));
-                current_frame.locals[index as usize] = value;
-            }
-            ByteCode::ILoad(index) => {
-                let value = current_frame.locals[index as usize].clone();
-                assert!(matches!(value, Value::Int(_)));
-                current_frame.operand_stack.push(value);
-            }
-            ByteCode::New(index) => {
-                let class_index = constant_pool[index as usize].as_class().unwrap();
-                let instance = heap.malloc_instance(class_index as usize);
-                current_frame.operand_stack.push(Value::Reference(instance));
-            }
-            ByteCode::Dup => {
-                let value = current_frame.operand_stack.pop().unwrap();
-                current_frame.operand_stack.push(value.clone());
-                current_frame.operand_stack.push(value);
-            }
-            ByteCode::GetField(index) => {
-                let reference = &current_frame
-                    .operand_stack
-                    .pop()
-                    .unwrap()
-                    .as_reference()
-                    .unwrap();
-                let instance = heap.get(reference);
-                let field_ref = &current_frame.constant_pool[index as usize]
-                    .as_field_ref()
-                    .unwrap();
-                let name_index = current_frame.constant_pool[field_ref.1 as usize]
-                    .as_name_and_type()
-                    .unwrap()
-                    .0;
-                let field_name = current_frame.constant_pool[name_index as usize]
-                    .as_utf8()
-                    .unwrap();
-                current_frame.operand_stack.push(instance.get_field(field_name).clone());
-            }
-            ByteCode::PutField(index) => {
-                let value = current_frame.operand_stack.pop().unwrap();
-                let reference = &current_frame
-                    .operand_stack
-                    .pop()
-                    .unwrap()
-                    .as_reference()
-                    .unwrap();
-                let instance = heap.get_mut(reference);
-                let field_ref = &current_frame.constant_pool[index as usize]
-                    .as_field_ref()
-                    .unwrap();
-                let name_index = current_frame.constant_pool[field_ref.1 as usize]
-                    .as_name_and_type()
-                    .unwrap()
-                    .0;
-                let field_name = current_frame.constant_pool[name_index as usize]
-                    .as_utf8()
-                    .unwrap();
-                instance.put_field(field_name, value);
-            }
-            ByteCode::GetStatic(index) => {
-                let value = &current_frame.constant_pool[index as usize];
-                let (class_index, name_and_type_index) = value.as_field_ref().unwrap();
-                let class_index = current_frame.constant_pool[class_index as usize]
-                    .as_class()
-                    .unwrap();
-                let class_name = current_frame.constant_pool[class_index as usize]
-                    .as_utf8()
-                    .unwrap();
-                let (name_index, type_index) = current_frame.constant_pool
-                    [name_and_type_index as usize]
-                    .as_name_and_type()
-                    .unwrap();
-                let (name, type_) = (
-                    current_frame.constant_pool[name_index as usize]
-                        .as_utf8()
-                        .unwrap(),
-                    current_frame.constant_pool[type_index as usize]
-                        .as_utf8()
-                        .unwrap(),
-                );
-                if class_name == "java/lang/System"
-                    && name == "out"
-                    && type_ == "Ljava/io/PrintStream;"
-                {
-                    let out = heap.malloc_instance(0);
-                    current_frame.operand_stack.push(Value::Reference(out));
-                } else {
-                    todo!("Not implemented getstatic");
-                }
+    let mut options = Options::default();
+    let mut path = None;
+    let mut invocation = None;
+    let mut run = None;
+    let mut class_path = vec![];
+    let mut dump_constant_pool = false;
+    let mut info = false;
+    let mut trace_file = None;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--deterministic-ids" => options.deterministic_ids = true,
+            "--trace" => options.trace = true,
+            "--step" => options.step = true,
+            "-ea" | "--enable-assertions" => options.enable_assertions = true,
+            "--profile" => options.profile = true,
+            "--checked" => options.checked = true,
+            "--fake-clock" => options.fake_clock = true,
+            "--strict-verify" => options.strict_verify = true,
+            "--strict-access" => options.strict_access = true,
+            "-verbose:gc" | "--verbose-gc" => options.verbose_gc = true,
+            "--trace-file" => trace_file = rest.next(),
+            "--dump-constant-pool" => dump_constant_pool = true,
+            "--info" => info = true,
+            "--gc" => {
+                let mode = rest.next().map(String::as_str).unwrap_or_default();
+                options.gc = GcMode::from_name(mode).unwrap_or_else(|| {
+                    eprintln!("Unknown gc mode: {} (none, mark-sweep, on-return)", mode);
+                    std::process::exit(1);
+                });
             }
-            ByteCode::IAdd => {
-                let value1 = current_frame.operand_stack.pop().unwrap();
-                let value2 = current_frame.operand_stack.pop().unwrap();
-                match (&value1, &value2) {
-                    (Value::Int(v1), Value::Int(v2)) => {
-                        current_frame.operand_stack.push(Value::Int(v1 + v2))
-                    }
-                    _ => {
-                        dbg!(&value1);
-                        dbg!(&value2);
-                        todo!("Not implemented iadd");
-                    }
-                }
+            "--max-heap" => {
+                let max = rest.next().map(String::as_str).unwrap_or_default();
+                options.max_heap = Some(max.parse().unwrap_or_else(|_| {
+                    eprintln!("Invalid maximum heap size: {} (number of objects)", max);
+                    std::process::exit(1);
+                }));
             }
-            ByteCode::ALoad(index) => {
-                let value = current_frame.locals[index as usize].clone();
-                assert!(matches!(value, Value::Reference(_)));
-                current_frame.operand_stack.push(value);
+            "--cp" | "--class-path" => {
+                let paths = rest.next().map(String::as_str).unwrap_or_default();
+                class_path.extend(std::env::split_paths(paths));
             }
-            ByteCode::AStore(index) => {
-                let value = current_frame.operand_stack.pop().unwrap();
-                assert!(matches!(value, Value::Reference(_)));
-                current_frame.locals[index as usize] = value;
+            "--run" => {
+                let name = rest.next().map(String::as_str).unwrap_or_default();
+                run = Some(name.replace('.', "/"));
             }
-            ByteCode::Ldc(index) => {
-                let value = &current_frame.constant_pool[index as usize];
-                match value {
-                    ConstantInfo::String(value) => {
-                        let value = current_frame.constant_pool[*value as usize]
-                            .as_utf8()
-                            .unwrap();
-                        current_frame
-                            .operand_stack
-                            .push(Value::String(value.to_string()));
-                    }
-                    _ => todo!("Not implemented ldc"),
-                }
+            "--invoke" => {
+                let target = rest.next().map(String::as_str).unwrap_or_default();
+                invocation = Some(parse_invocation(target).unwrap_or_else(|| {
+                    eprintln!("Invalid invocation: {} (Class.method(args...))", target);
+                    std::process::exit(1);
+                }));
             }
-            ByteCode::InvokeSpecial(index) => {
-                let (class_name, method_name) =
-                    class_method(index as usize, current_frame.constant_pool);
-                if class_name == "java/lang/Object" && method_name == "<init>" {
-                    // consume the reference, do nothing
-                    current_frame.operand_stack.pop().unwrap();
-                    continue;
-                }
-                let class = classes
-                    .iter()
-                    .find(|c| c.name() == class_name)
-                    .expect("Class not found");
-                let method = class.find_method(method_name).expect("Method not found");
-                let descriptor = &class.constant_pool[method.descriptor_index as usize]
-                    .as_utf8()
-                    .unwrap();
-                let args_size = args_size(descriptor);
-                let code = method.code(&class.constant_pool);
-                let mut frame = Frame::new(
-                    method.name(&class.constant_pool),
-                    &code.code,
-                    &class.constant_pool,
-                    code.max_locals,
-                    code.max_stack,
-                );
-                for i in 0..args_size + 1 {
-                    frame.locals[args_size - i] = current_frame.operand_stack.pop().unwrap();
-                }
-                stack.push(frame);
-                next_frame_index = current_frame_index + 1;
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option: {}", arg);
+                std::process::exit(1);
             }
-            ByteCode::InvokeVirtual(index) => {
-                let (class_name, method_name) =
-                    class_method(index as usize, current_frame.constant_pool);
-                // special case for println
-                if class_name == "java/io/PrintStream" && method_name == "println" {
-                    let value = current_frame.operand_stack.pop().unwrap();
+            _ => path = Some(arg),
+        }
+    }
+    if class_path.is_empty() {
+        class_path.push(PathBuf::from("."));
+    }
+    // the class to run or invoke by name is found on the class path
+    let name = run
+        .as_ref()
+        .or(invocation.as_ref().map(|invocation| &invocation.class));
+    let default_path = match (path, name) {
+        (None, Some(name)) => Some(find_class_file(&class_path, name).unwrap_or_else(|| {
+            eprintln!(
+                "Error: class {} not found on the class path",
+                name.replace('/', ".")
+            );
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+    let default_path = default_path.map(|path| path.to_string_lossy().into_owned());
+    let Some(path) = path.or(default_path.as_ref()) else {
+        println!("Usage: {} [options] <class file>", args[0]);
+        println!("       {} [options] --run <class>", args[0]);
+        println!("       {} verify <class file>...", args[0]);
+        println!();
+        println!("Options:");
+        println!("  --deterministic-ids  use allocation order ids as identity hashes");
+        println!("  --gc <mode>          when to collect garbage: none, mark-sweep (default)");
+        println!("                       or on-return after every method");
+        println!("  -verbose:gc, --verbose-gc");
+        println!("                       print the objects freed by every collection");
+        println!("  --max-heap <n>       allow at most n live objects, collecting garbage");
+        println!("                       before throwing OutOfMemoryError");
+        println!("  --trace              print every instruction, the operand stack and the");
+        println!("                       local variables, by name for classes compiled with -g");
+        println!("  --step               stop before every instruction and read a command:");
+        println!("                       s or enter to step, b to step back, c to continue");
+        println!("  --trace-file <path>  write every instruction, its method and address and");
+        println!("                       the top of the operand stack after it to the file,");
+        println!("                       to compare runs");
+        println!("  --profile            print the number of instructions executed by every");
+        println!("                       method when the program exits");
+        println!("  --fake-clock         make System.nanoTime and currentTimeMillis advance");
+        println!("                       by a millisecond per call, for reproducible output");
+        println!("  --checked            check that final fields are written once by an");
+        println!("                       initializer of their class");
+        println!("  --strict-verify      list the unsupported instructions of every class");
+        println!("                       before it runs, refusing to run it if there are any");
+        println!("  --strict-access      check that private, protected and package-private");
+        println!("                       fields and methods are only used where allowed");
+        println!("  -ea, --enable-assertions");
+        println!("                       check the conditions of assert statements");
+        println!("  --dump-constant-pool print the constant pool like javap -v and exit");
+        println!("  --info               print the declaration and overview of the class and exit");
+        println!("  --cp <paths>         directories to look up classes in, separated like");
+        println!("                       PATH; the current directory if not given");
+        println!("  --run <class>        run the main method of the class of this fully");
+        println!("                       qualified name, found on the class path");
+        println!("  --invoke <target>    invoke Class.method(args...) instead of main and");
+        println!("                       print the result; args are ints, booleans or");
+        println!("                       \"strings\"");
+        return;
+    };
+
+    // classes used by the program are looked up next to a class given by
+    // its file, before the class path
+    if default_path.is_none() {
+        let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+        options.class_path.push(dir.to_path_buf());
+    }
+    options.class_path.extend(class_path);
+
+    let class = match ClassFile::parse(path) {
+        Ok(class) => class,
+        Err(err) => {
+            // with the structure being read when it failed
+            eprintln!("Error: failed to parse {}: {:#}", path, err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(name) = name.filter(|name| *name != class.name()) {
+        eprintln!(
+            "Error: {} declares class {} instead of {}",
+            path,
+            class.name().replace('/', "."),
+            name.replace('/', ".")
+        );
+        std::process::exit(1);
+    }
+    if info {
+        print!("{}", class.info());
+    }
+    if dump_constant_pool {
+        print!("{}", class.dump_constant_pool());
+    }
+    if info || dump_constant_pool {
+        return;
+    }
+    let mut trace_out = match trace_file.map(std::fs::File::create).transpose() {
+        Ok(file) => file.map(BufWriter::new),
+        Err(err) => {
+            eprintln!("Error: failed to create trace file: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut vm = Vm::new(class, options);
+    if let Some(out) = &mut trace_out {
+        vm.set_trace_file(out);
+    }
+    let result = match invocation {
+        Some(invocation) => vm
+            .invoke_static(&invocation.method, invocation.args)
+            .map(|value| {
+                if let Some(value) = value {
                     println!("{}", value);
-                    continue;
                 }
-                // common case
-                let class = classes
-                    .iter()
-                    .find(|c| c.name() == class_name)
-                    .expect("Class not found");
-                let method = class.find_method(method_name).expect("Method not found");
-                let descriptor = &class.constant_pool[method.descriptor_index as usize]
-                    .as_utf8()
-                    .unwrap();
-                let args_size = args_size(descriptor);
-                let code = method.code(&class.constant_pool);
-                let mut frame = Frame::new(
-                    method.name(&class.constant_pool),
-                    &code.code,
-                    &class.constant_pool,
-                    code.max_locals,
-                    code.max_stack,
-                );
-                // + 1 for `this`
-                for i in 0..args_size + 1 {
-                    frame.locals[args_size-i] = current_frame.operand_stack.pop().unwrap();
-                }
-                stack.push(frame);
-                next_frame_index = current_frame_index + 1;
-            }
-            _ => {
-                println!("Unimplemented: {:?}", bc);
-            }
+            }),
+        None => vm.run_main(),
+    };
+    // also when the program failed, which may be what is being profiled
+    if let Some(profile) = vm.profile() {
+        eprintln!("Instructions per method:");
+        for (method, count) in profile {
+            eprintln!("{:>12}  {}", count, method.replace('/', "."));
+        }
+    }
+    drop(vm);
+    if let Some(Err(err)) = trace_out.as_mut().map(BufWriter::flush) {
+        eprintln!("Error: failed to write trace file: {}", err);
+        std::process::exit(1);
+    }
+    match result {
+        Ok(()) => {}
+        // printed like the JVM does, with the stack trace
+        Err(err @ RuntimeError::Uncaught { .. }) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
-        if next_frame_index < current_frame_index {
-            // garbage collection when returning from a method
-            heap.gc(&stack, &func);
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
         }
-        current_frame_index = next_frame_index;
     }
 }
@@ -1,140 +1,536 @@
 use core::fmt;
 use std::{
-    cell::Ref,
-    collections::{HashMap, HashSet},
+    collections::{hash_map::RandomState, BTreeMap, HashMap, HashSet, VecDeque},
+    hash::BuildHasher,
+    rc::Rc,
 };
 
-use crate::{bytecode::ByteCode, class_file::ConstantInfo};
+use crate::{
+    bytecode::ByteCode,
+    class_file::{ClassFile, CodeAttribute, MethodInfo},
+};
 
-pub struct Frame<'a> {
+pub struct Frame {
+    /// The address of the next instruction
     pub pc: usize,
+    /// The address of the instruction being executed
+    pub current_pc: usize,
     pub name: String,
-    pub codes: Vec<u8>,
+    pub code: Rc<CodeAttribute>,
     pub operand_stack: Vec<Value>,
     pub locals: Vec<Value>,
-    pub constant_pool: &'a Vec<ConstantInfo>,
-}
-
-impl<'a> Frame<'a> {
-    pub fn new(
-        name: &str,
-        codes: &[u8],
-        constant_pool: &'a Vec<ConstantInfo>,
-        max_locals: u16,
-        max_stack: u16,
-    ) -> Self {
-        let operand_stack = (0..max_stack).map(|_| Value::Int(0)).collect::<Vec<_>>();
-        let locals = (0..max_locals).map(|_| Value::Int(0)).collect::<Vec<_>>();
+    /// The class declaring the method, whose constant pool the code refers to
+    pub class: Rc<ClassFile>,
+    /// The object whose monitor a synchronized method holds until it returns
+    pub monitor: Option<Reference>,
+}
+
+impl Frame {
+    pub fn new(class: Rc<ClassFile>, method: &MethodInfo) -> Self {
+        let code = method.code(&class.constant_pool);
+        let operand_stack = Vec::with_capacity(code.max_stack as usize);
+        let locals = (0..code.max_locals)
+            .map(|_| Value::Int(0))
+            .collect::<Vec<_>>();
         Self {
             pc: 0,
-            name: name.to_string(),
-            codes: codes.to_vec(),
+            current_pc: 0,
+            name: method.name(&class.constant_pool).to_string(),
+            code,
             operand_stack,
             locals,
-            constant_pool,
+            class,
+            monitor: None,
         }
     }
 
-    pub fn fetch(&mut self) -> ByteCode {
-        let (pc, bc) = ByteCode::parse(self.pc, &self.codes);
+    /// Decode the instruction at `pc` and move past it, failing if the VM
+    /// can't execute it
+    pub fn fetch(&mut self) -> Result<ByteCode, RuntimeError> {
+        let Some((pc, bc)) = ByteCode::try_parse(self.pc, &self.code.code) else {
+            return Err(self.unsupported_instruction(self.pc));
+        };
+        self.current_pc = self.pc;
         self.pc = pc;
-        bc
+        Ok(bc)
+    }
+
+    /// The error for the instruction at `pc`, which the VM can't execute
+    pub fn unsupported_instruction(&self, pc: usize) -> RuntimeError {
+        RuntimeError::UnsupportedInstruction {
+            mnemonic: self
+                .code
+                .code
+                .get(pc)
+                .and_then(|&op| ByteCode::mnemonic(op))
+                .unwrap_or("<invalid>"),
+            method: format!("{}.{}", self.class.name(), self.name),
+            pc,
+        }
+    }
+
+    /// Jump to `offset` relative to the instruction being executed
+    pub fn branch(&mut self, offset: impl Into<i64>) {
+        self.pc = (self.current_pc as i64 + offset.into()) as usize;
+    }
+
+    /// The values of the operand stack, bottom first, and the number of slots
+    /// they take, e.g. `[1, 5000000000] (depth 3)`
+    ///
+    /// Longs and doubles are a single value here, but count as two slots
+    /// like they do for `max_stack`.
+    pub fn dump_operand_stack(&self, heap: &Heap) -> String {
+        let values = self
+            .operand_stack
+            .iter()
+            .map(|value| heap.render(value))
+            .collect::<Vec<_>>();
+        let slots = self.operand_stack.iter().map(Value::slots).sum::<usize>();
+        format!("[{}] (depth {})", values.join(", "), slots)
+    }
+
+    /// The values of the local variables, by their names at the next
+    /// instruction if the class has debug info and by their slots otherwise,
+    /// e.g. `{args = null, i = 3, locals[2] = 0}`
+    ///
+    /// Longs and doubles are shown once, for the first of their two slots.
+    pub fn dump_locals(&self, heap: &Heap) -> String {
+        let cp = &self.class.constant_pool;
+        let mut locals = vec![];
+        let mut slot = 0;
+        while let Some(value) = self.locals.get(slot) {
+            let name = match self.code.local_name(slot as u16, self.pc, cp) {
+                Some(name) => name.to_string(),
+                None => format!("locals[{}]", slot),
+            };
+            locals.push(format!("{} = {}", name, heap.render(value)));
+            slot += value.slots();
+        }
+        format!("{{{}}}", locals.join(", "))
+    }
+
+    /// Copy the state needed to go back to this point of the method
+    pub fn snapshot(&self) -> FrameSnapshot {
+        FrameSnapshot {
+            pc: self.pc,
+            current_pc: self.current_pc,
+            operand_stack: self.operand_stack.clone(),
+            locals: self.locals.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: FrameSnapshot) {
+        self.pc = snapshot.pc;
+        self.current_pc = snapshot.current_pc;
+        self.operand_stack = snapshot.operand_stack;
+        self.locals = snapshot.locals;
+    }
+}
+
+/// The addresses, operand stack and locals of a frame at some point
+///
+/// References are copied rather than the objects they point to, so restoring
+/// a snapshot doesn't undo changes made to the heap since it was taken.
+#[derive(Debug, Clone)]
+pub struct FrameSnapshot {
+    pub pc: usize,
+    pub current_pc: usize,
+    pub operand_stack: Vec<Value>,
+    pub locals: Vec<Value>,
+}
+
+/// The most recent snapshots of a frame, dropping the oldest once full, so
+/// a debugger can step backward
+#[derive(Debug)]
+pub struct History {
+    snapshots: VecDeque<FrameSnapshot>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Remember the state of `frame` before executing its next instruction
+    pub fn record(&mut self, frame: &Frame) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(frame.snapshot());
+    }
+
+    /// Restore `frame` to the last recorded state, returning `false` if
+    /// there is none left
+    pub fn step_back(&mut self, frame: &mut Frame) -> bool {
+        match self.snapshots.pop_back() {
+            Some(snapshot) => {
+                frame.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Forget all snapshots, e.g. once they are of another frame than the
+    /// one to step back
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+/// When the garbage collector runs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GcMode {
+    /// Never collect garbage
+    None,
+    /// Collect when returning from a method, once enough objects have been
+    /// allocated since the last collection
+    #[default]
+    MarkSweep,
+    /// Collect whenever returning from a method
+    OnReturn,
+}
+
+impl GcMode {
+    /// The number of allocations after which `MarkSweep` collects again
+    pub const THRESHOLD: usize = 1024;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(GcMode::None),
+            "mark-sweep" => Some(GcMode::MarkSweep),
+            "on-return" => Some(GcMode::OnReturn),
+            _ => None,
+        }
+    }
+}
+
+/// A garbage collection which freed objects, as reported to the GC log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcEvent {
+    /// The ids of the collected objects, ascending
+    pub collected: Vec<usize>,
+    /// The method returning from or allocating when the collection ran,
+    /// like `com/example/App.main`
+    pub method: String,
+}
+
+impl fmt::Display for GcEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = self.collected.iter().map(usize::to_string);
+        let objects = if self.collected.len() == 1 {
+            "object"
+        } else {
+            "objects"
+        };
+        write!(
+            f,
+            "GC: collected {} {} after {}: [{}]",
+            self.collected.len(),
+            objects,
+            self.method.replace('/', "."),
+            ids.collect::<Vec<_>>().join(", ")
+        )
     }
 }
 
 pub struct Heap {
     /// Option<Instantce> is used to allow for null values and garbage collection
     pub instances: Vec<Option<Instantce>>,
+    /// The arrays, indexed by `Reference::Array` like the instances
+    pub arrays: Vec<Option<ArrayObject>>,
+    /// The id given to the next allocated instance
+    next_id: usize,
+    /// The number of objects allocated since the last collection
+    allocated: usize,
+    /// The number of objects allocated and not collected yet
+    live: usize,
+    /// Use the id of an instance as its identity hash, for reproducible output
+    deterministic_ids: bool,
+    random_state: RandomState,
+    /// The names of the classes whose class object has been requested,
+    /// indexed by `Reference::Class`
+    class_objects: Vec<String>,
+    /// The string constants loaded so far, shared by every load of the same
+    /// string like the JVM interns them
+    interned: HashSet<Rc<str>>,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Heap {
     pub fn new() -> Self {
         Self {
             instances: Vec::new(),
+            arrays: Vec::new(),
+            next_id: 0,
+            allocated: 0,
+            live: 0,
+            deterministic_ids: false,
+            random_state: RandomState::new(),
+            class_objects: Vec::new(),
+            interned: HashSet::new(),
         }
     }
 
-    pub fn malloc_instance(&mut self, class: usize) -> Reference {
-        let index = self.instances.len();
-        let instance = Instantce::new(class, index);
+    pub fn with_deterministic_ids(deterministic_ids: bool) -> Self {
+        Self {
+            deterministic_ids,
+            ..Self::new()
+        }
+    }
+
+    /// The id and identity hash of a newly allocated object
+    fn next_identity(&mut self) -> (usize, i32) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.allocated += 1;
+        self.live += 1;
+        let hash = if self.deterministic_ids {
+            id as i32
+        } else {
+            // identity hashes are positive like the ones of HotSpot
+            (self.random_state.hash_one(id) & 0x7fff_ffff) as i32
+        };
+        (id, hash)
+    }
+
+    pub fn malloc_instance(&mut self, class: &str) -> Reference {
         let index = self.instances.len();
+        let (id, hash) = self.next_identity();
+        let instance = Instantce::new(class, index, id, hash);
         self.instances.push(Some(instance));
         Reference::Object(index)
     }
 
-    pub fn get(&self, reference: &Reference) -> &Instantce {
+    /// Allocate an array of the array class `class`, e.g. `[I`
+    pub fn malloc_array(&mut self, class: &str, elements: Vec<Value>) -> Reference {
+        let index = self.arrays.len();
+        let (id, hash) = self.next_identity();
+        self.arrays.push(Some(ArrayObject {
+            class: class.to_string(),
+            id,
+            hash,
+            elements,
+        }));
+        Reference::Array(index)
+    }
+
+    pub fn get_array(&self, reference: &Reference) -> &ArrayObject {
         match reference {
-            Reference::Object(index) => self.instances[*index].as_ref().unwrap(),
-            _ => panic!("Not implemented"),
+            Reference::Array(index) => self.arrays[*index].as_ref().unwrap(),
+            _ => panic!("Not an array"),
         }
     }
 
-    pub fn get_mut(&mut self, reference: &Reference) -> &mut Instantce {
+    pub fn get_array_mut(&mut self, reference: &Reference) -> &mut ArrayObject {
         match reference {
-            Reference::Object(index) => self.instances[*index].as_mut().unwrap(),
-            _ => panic!("Not implemented"),
+            Reference::Array(index) => self.arrays[*index].as_mut().unwrap(),
+            _ => panic!("Not an array"),
         }
     }
 
-    /// Garbage collection
-    pub fn gc(&mut self, stack: &[Frame], func: &str) {
-        // all references in the world
-        let mut all_reference = HashSet::new();
-        for instance in self.instances.iter().flatten() {
-            all_reference.insert(instance.index);
+    /// The class object of the named class, which is the same for every call
+    ///
+    /// Class objects are never collected, as a class is never unloaded.
+    pub fn class_object(&mut self, name: &str) -> Reference {
+        let index = match self.class_objects.iter().position(|class| class == name) {
+            Some(index) => index,
+            None => {
+                self.class_objects.push(name.to_string());
+                self.class_objects.len() - 1
+            }
+        };
+        Reference::Class(index)
+    }
+
+    /// The interned instance of the string `string`, which is the same for
+    /// every call with equal contents
+    pub fn intern(&mut self, string: &str) -> Rc<str> {
+        if let Some(interned) = self.interned.get(string) {
+            return interned.clone();
+        }
+        let interned = Rc::<str>::from(string);
+        self.interned.insert(interned.clone());
+        interned
+    }
+
+    /// `value` for debugging output, with the class of what a reference
+    /// points to, e.g. `Object(3 : com/example/Foo)` or `Array(1 : [I)`
+    ///
+    /// Class objects show the class they represent, strings are quoted, and
+    /// references to collected objects don't panic.
+    pub fn render(&self, value: &Value) -> String {
+        let (kind, index, class) = match value {
+            Value::String(string) => return format!("{:?}", string),
+            Value::Reference(Reference::Object(index)) => (
+                "Object",
+                index,
+                self.instances
+                    .get(*index)
+                    .and_then(Option::as_ref)
+                    .map(|instance| instance.class.as_str()),
+            ),
+            Value::Reference(Reference::Array(index)) => (
+                "Array",
+                index,
+                self.arrays
+                    .get(*index)
+                    .and_then(Option::as_ref)
+                    .map(|array| array.class.as_str()),
+            ),
+            Value::Reference(Reference::Class(index)) => (
+                "Class",
+                index,
+                self.class_objects.get(*index).map(String::as_str),
+            ),
+            value => return value.to_string(),
+        };
+        format!("{}({} : {})", kind, index, class.unwrap_or("collected"))
+    }
+
+    /// The name of the class represented by a class object
+    pub fn class_name(&self, reference: &Reference) -> &str {
+        match reference {
+            Reference::Class(index) => &self.class_objects[*index],
+            _ => panic!("Not a class object"),
         }
+    }
+
+    /// The name of the runtime class of the object `reference` points to,
+    /// e.g. `[I` for an int array
+    pub fn class_of(&self, reference: &Reference) -> Result<&str, RuntimeError> {
+        Ok(match reference {
+            Reference::Object(_) => &self.get(reference)?.class,
+            Reference::Array(_) => &self.get_array(reference).class,
+            Reference::Class(_) => "java/lang/Class",
+        })
+    }
 
-        // find all references in the stack
+    /// The instance `reference` refers to, failing if it has been collected
+    /// or refers to an array or class object instead
+    pub fn get(&self, reference: &Reference) -> Result<&Instantce, RuntimeError> {
+        let instance = match reference {
+            Reference::Object(index) => self.instances.get(*index).and_then(Option::as_ref),
+            _ => None,
+        };
+        instance.ok_or_else(|| RuntimeError::DanglingReference(reference.clone()))
+    }
+
+    /// The instance `reference` refers to, to modify it, failing like
+    /// [`Heap::get`]
+    pub fn get_mut(&mut self, reference: &Reference) -> Result<&mut Instantce, RuntimeError> {
+        let instance = match reference {
+            Reference::Object(index) => self.instances.get_mut(*index).and_then(Option::as_mut),
+            _ => None,
+        };
+        instance.ok_or_else(|| RuntimeError::DanglingReference(reference.clone()))
+    }
+
+    /// Acquire the monitor of the object
+    ///
+    /// There is only a single thread, so this can't block and only counts
+    /// how often the monitor is held.
+    pub fn monitor_enter(&mut self, reference: &Reference) -> Result<(), RuntimeError> {
+        self.get_mut(reference)?.monitor += 1;
+        Ok(())
+    }
+
+    pub fn monitor_exit(&mut self, reference: &Reference) -> Result<(), RuntimeError> {
+        let instance = self.get_mut(reference)?;
+        if instance.monitor == 0 {
+            return Err(RuntimeError::IllegalMonitorState);
+        }
+        instance.monitor -= 1;
+        Ok(())
+    }
+
+    /// The number of objects allocated since the last collection
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    /// The number of objects allocated and not collected yet, reachable or not
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+    /// Garbage collection
+    ///
+    /// The objects referenced by the frames of `stack` and by `roots` which
+    /// the VM itself holds on to are kept. Returns the ids of the collected
+    /// objects, ascending.
+    pub fn gc(&mut self, stack: &[Frame], roots: impl Iterator<Item = Reference>) -> Vec<usize> {
+        self.allocated = 0;
+        // mark all instances and arrays reachable from the values in the stack
+        let mut marked = vec![false; self.instances.len()];
+        let mut marked_arrays = vec![false; self.arrays.len()];
+        let mut pending = roots.collect::<Vec<_>>();
         for frame in stack {
-            for value in frame.operand_stack.iter() {
-                if let Some(Reference::Object(index)) = value.as_reference() {
-                    let should_keep = self.get_field_ref(index);
-                    for index in should_keep {
-                        all_reference.remove(&index);
+            for value in frame.operand_stack.iter().chain(frame.locals.iter()) {
+                pending.extend(value.as_reference());
+            }
+        }
+        while let Some(reference) = pending.pop() {
+            match reference {
+                Reference::Object(index) if !marked[index] => {
+                    marked[index] = true;
+                    if let Some(instance) = &self.instances[index] {
+                        let fields = instance.fields.values();
+                        let entries = instance.entries.values().flatten();
+                        let entries = entries.flat_map(|(key, value)| [key, value]);
+                        pending.extend(fields.chain(entries).filter_map(Value::as_reference));
                     }
                 }
-            }
-            for value in frame.locals.iter() {
-                if let Some(Reference::Object(index)) = value.as_reference() {
-                    let should_keep = self.get_field_ref(index);
-                    for index in should_keep {
-                        all_reference.remove(&index);
+                Reference::Array(index) if !marked_arrays[index] => {
+                    marked_arrays[index] = true;
+                    if let Some(array) = &self.arrays[index] {
+                        pending.extend(array.elements.iter().filter_map(Value::as_reference));
                     }
                 }
+                _ => {}
             }
         }
 
-        // remove all instances that are not referenced
-        for non_ref in all_reference.iter() {
-            // use take to let the value be dropped by the compiler
-            self.instances[*non_ref].take();
-        }
-        if !all_reference.is_empty() {
-            println!(
-                "GC: Remove Objects: [{}] after {}",
-                all_reference
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                func
-            );
+        // remove all instances and arrays that are not referenced
+        let mut removed = vec![];
+        for (index, instance) in self.instances.iter_mut().enumerate() {
+            if !marked[index] {
+                // use take to let the value be dropped by the compiler
+                if let Some(instance) = instance.take() {
+                    removed.push(instance.id);
+                }
+            }
         }
-    }
-
-    /// Get all fields that are referenced by the given index
-    fn get_field_ref(&self, index: usize) -> HashSet<usize> {
-        let mut result = HashSet::new();
-        result.insert(index);
-        let instance = self.instances[index].as_ref().unwrap();
-        for (_, value) in instance.fields.iter() {
-            if let Some(Reference::Object(index)) = value.as_reference() {
-                let should_keep = self.get_field_ref(index);
-                result.extend(should_keep);
-                result.insert(index);
+        for (index, array) in self.arrays.iter_mut().enumerate() {
+            if !marked_arrays[index] {
+                if let Some(array) = array.take() {
+                    removed.push(array.id);
+                }
             }
         }
-        result
+        self.live -= removed.len();
+        removed.sort_unstable();
+        removed
     }
 }
 
@@ -145,13 +541,43 @@ pub enum Value {
     Char(char),
     Short(i16),
     Int(i32),
+    Long(i64),
     Float(f32),
-    String(String),
+    Double(f64),
+    /// A `java.lang.String`, whose identity is that of the shared contents
+    String(Rc<str>),
     Reference(Reference),
+    /// The null reference
+    Null,
     ReturnAddress(usize),
 }
 
 impl Value {
+    /// The number of slots the value takes in the operand stack or the
+    /// locals, which is 2 for longs and doubles
+    pub fn slots(&self) -> usize {
+        match self {
+            Value::Long(_) | Value::Double(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// The initial value of a field of type `descriptor`
+    pub fn default_for(descriptor: &str) -> Value {
+        match descriptor.as_bytes()[0] {
+            b'B' | b'C' | b'I' | b'S' | b'Z' => Value::Int(0),
+            b'J' => Value::Long(0),
+            b'F' => Value::Float(0.0),
+            b'D' => Value::Double(0.0),
+            _ => Value::Null,
+        }
+    }
+
+    /// Whether the value can be held by a variable of reference type
+    pub fn is_reference(&self) -> bool {
+        matches!(self, Value::Reference(_) | Value::String(_) | Value::Null)
+    }
+
     pub fn as_reference(&self) -> Option<Reference> {
         match self {
             Value::Reference(reference) => Some(reference.clone()),
@@ -160,6 +586,119 @@ impl Value {
     }
 }
 
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Long(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Double(value)
+    }
+}
+
+/// Booleans are ints on the operand stack, so this gives `Int(0)` or `Int(1)`
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Int(value as i32)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value.into())
+    }
+}
+
+/// The error of converting `value` which isn't of type `expected`
+fn mismatch(expected: &'static str, value: &Value) -> RuntimeError {
+    RuntimeError::TypeMismatch {
+        expected,
+        found: format!("{:?}", value),
+    }
+}
+
+impl TryFrom<Value> for i32 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(value) => Ok(value),
+            _ => Err(mismatch("int", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Long(value) => Ok(value),
+            _ => Err(mismatch("long", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f32 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Float(value) => Ok(value),
+            _ => Err(mismatch("float", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Double(value) => Ok(value),
+            _ => Err(mismatch("double", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(value) => Ok(value),
+            Value::Int(0) => Ok(false),
+            Value::Int(1) => Ok(true),
+            _ => Err(mismatch("boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(value) => Ok(value.to_string()),
+            _ => Err(mismatch("String", &value)),
+        }
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -168,15 +707,350 @@ impl fmt::Display for Value {
             Value::Char(value) => write!(f, "{}", value),
             Value::Short(value) => write!(f, "{}", value),
             Value::Int(value) => write!(f, "{}", value),
-            Value::Float(value) => write!(f, "{}", value),
+            Value::Long(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", java_decimal(*value)),
+            Value::Double(value) => write!(f, "{}", java_decimal(*value)),
             Value::Reference(reference) => write!(f, "{:?}", reference),
+            Value::Null => write!(f, "null"),
             Value::ReturnAddress(value) => write!(f, "{}", value),
             Value::String(s) => write!(f, "{}", s),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// Format a float or double like `Double.toString`, with the shortest digits
+/// which round-trip
+///
+/// Magnitudes from 10^-3 up to 10^7 are written as decimals with at least one
+/// fractional digit, the others as `1.5E-7`.
+pub(crate) fn java_decimal(value: impl fmt::LowerExp) -> String {
+    let mut scientific = format!("{:e}", value);
+    // at least two digits are written, which are the nearest ones rather
+    // than the shortest digit padded with a zero
+    if !scientific.contains('.') {
+        scientific = format!("{:.1e}", value);
+    }
+    match scientific.as_str() {
+        "NaN" => return "NaN".to_string(),
+        "inf" => return "Infinity".to_string(),
+        "-inf" => return "-Infinity".to_string(),
+        _ => {}
+    }
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent = exponent.parse::<i32>().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    if !(-3..7).contains(&exponent) {
+        return format!("{}{}.{}E{}", sign, &digits[..1], &digits[1..], exponent);
+    }
+    let digits = match digits.trim_end_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+    if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        return format!("{}0.{}{}", sign, zeros, digits);
+    }
+    let point = exponent as usize + 1;
+    if digits.len() <= point {
+        format!("{}{:0<width$}.0", sign, digits, width = point)
+    } else {
+        format!("{}{}.{}", sign, &digits[..point], &digits[point..])
+    }
+}
+
+/// Format a double with `precision` fractional digits like `%.nf` of
+/// `String.format`
+///
+/// Like Java, this rounds the shortest digits which round-trip half up,
+/// rather than the exact binary value, so 2.675 becomes `2.68`.
+pub(crate) fn java_fixed(value: f64, precision: usize) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value.is_infinite() {
+        return format!("{}Infinity", sign);
+    }
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent = exponent.parse::<isize>().unwrap();
+    let digits = mantissa.replace('.', "").into_bytes();
+    // the digits up to the last one printed, as a number scaled by
+    // 10^precision
+    let keep = exponent + 1 + precision as isize;
+    let mut scaled = vec![b'0'];
+    if keep >= 0 {
+        let keep = keep as usize;
+        scaled.extend((0..keep).map(|i| digits.get(i).copied().unwrap_or(b'0')));
+        if digits.get(keep).is_some_and(|digit| *digit >= b'5') {
+            let mut i = scaled.len() - 1;
+            while scaled[i] == b'9' {
+                scaled[i] = b'0';
+                i -= 1;
+            }
+            scaled[i] += 1;
+        }
+    }
+    let scaled = String::from_utf8(scaled).unwrap();
+    let scaled = format!(
+        "{:0>width$}",
+        scaled.trim_start_matches('0'),
+        width = precision + 1
+    );
+    let (integer, fraction) = scaled.split_at(scaled.len() - precision);
+    match precision {
+        0 => format!("{}{}", sign, integer),
+        _ => format!("{}{}.{}", sign, integer, fraction),
+    }
+}
+
+/// An error raised while executing bytecode
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// A constant of the named tag which the VM can't load
+    UnsupportedConstant(&'static str),
+    /// A method of the Java library which the VM doesn't implement
+    UnsupportedMethod {
+        class: String,
+        name: String,
+        descriptor: String,
+    },
+    /// An instruction which the VM can't execute, by its mnemonic or
+    /// `<invalid>`, in the method named like `Foo.bar` at the address
+    UnsupportedInstruction {
+        mnemonic: &'static str,
+        method: String,
+        pc: usize,
+    },
+    /// An instruction found a value of the wrong type
+    TypeMismatch {
+        expected: &'static str,
+        found: String,
+    },
+    /// An exception thrown by the program, unwinding the stack until a
+    /// handler catches it
+    Throw(Reference),
+    /// An exception which no handler of the main thread caught
+    Uncaught {
+        class: String,
+        message: Option<String>,
+        /// The methods active when the exception was created, innermost first
+        stack_trace: Vec<String>,
+    },
+    /// A null reference was used where an object is required
+    NullPointer(String),
+    /// A monitor was exited which isn't held
+    IllegalMonitorState,
+    /// The named class declares no `public static void main(String[])`
+    MainMethodNotFound(String),
+    /// An array was indexed outside of its bounds, with the message
+    ArrayIndexOutOfBounds(String),
+    /// A string or `StringBuilder` was indexed outside of its bounds, with
+    /// the message
+    StringIndexOutOfBounds(String),
+    /// A value can't be stored into an array of its type, with the message
+    ArrayStore(String),
+    /// An array was created with a negative length
+    NegativeArraySize(i32),
+    /// An arithmetic operation failed, e.g. a division by zero, with the message
+    Arithmetic(String),
+    /// An object was cast to a class it isn't an instance of, with the message
+    ClassCast(String),
+    /// Allocating an object would exceed the maximum number of live objects
+    OutOfMemory,
+    /// A `Scanner` found no more input, with the message if there is one
+    NoSuchElement(Option<String>),
+    /// The next token a `Scanner` read isn't of the type requested
+    InputMismatch,
+    /// A format string doesn't fit the arguments of `String.format`, with
+    /// the class of the `IllegalFormatException` thrown and the message
+    IllegalFormat(&'static str, String),
+    /// The instance has no field of the name
+    NoSuchField { class: String, name: String },
+    /// The class has no method of the name which fits the invocation
+    NoSuchMethod { class: String, name: String },
+    /// No class file for the class was found on the class path
+    NoClassDefFound(String),
+    /// The code of the named class fails verification
+    Verify { class: String, message: String },
+    /// The class file of the named class is malformed
+    ClassFormat { class: String, message: String },
+    /// A field or method was used where that isn't allowed, like a final
+    /// field written twice in checked mode, with the message
+    IllegalAccess(String),
+    /// An object was used after it had been collected, which is a bug of
+    /// the VM or of the bytecode, or a reference isn't to an instance
+    DanglingReference(Reference),
+    /// Writing the output of the program failed
+    Io(std::io::Error),
+}
+
+impl RuntimeError {
+    /// The class and message of the exception the JVM throws for the error,
+    /// which the program can catch, or `None` for errors of the VM itself
+    pub fn exception(&self) -> Option<(&'static str, Option<String>)> {
+        let exception = match self {
+            RuntimeError::NullPointer(message) => {
+                ("java/lang/NullPointerException", Some(message.clone()))
+            }
+            RuntimeError::IllegalMonitorState => ("java/lang/IllegalMonitorStateException", None),
+            RuntimeError::ArrayIndexOutOfBounds(message) => (
+                "java/lang/ArrayIndexOutOfBoundsException",
+                Some(message.clone()),
+            ),
+            RuntimeError::StringIndexOutOfBounds(message) => (
+                "java/lang/StringIndexOutOfBoundsException",
+                Some(message.clone()),
+            ),
+            RuntimeError::ArrayStore(message) => {
+                ("java/lang/ArrayStoreException", Some(message.clone()))
+            }
+            RuntimeError::NegativeArraySize(length) => (
+                "java/lang/NegativeArraySizeException",
+                Some(length.to_string()),
+            ),
+            RuntimeError::Arithmetic(message) => {
+                ("java/lang/ArithmeticException", Some(message.clone()))
+            }
+            RuntimeError::ClassCast(message) => {
+                ("java/lang/ClassCastException", Some(message.clone()))
+            }
+            RuntimeError::OutOfMemory => (
+                "java/lang/OutOfMemoryError",
+                Some("Java heap space".to_string()),
+            ),
+            RuntimeError::NoSuchElement(message) => {
+                ("java/util/NoSuchElementException", message.clone())
+            }
+            RuntimeError::InputMismatch => ("java/util/InputMismatchException", None),
+            RuntimeError::IllegalFormat(class, message) => (*class, Some(message.clone())),
+            _ => return None,
+        };
+        Some(exception)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::UnsupportedConstant(tag) => {
+                write!(f, "unsupported constant: {}", tag)
+            }
+            RuntimeError::UnsupportedMethod {
+                class,
+                name,
+                descriptor,
+            } => write!(f, "unsupported method: {}.{}{}", class, name, descriptor),
+            RuntimeError::UnsupportedInstruction {
+                mnemonic,
+                method,
+                pc,
+            } => write!(
+                f,
+                "unsupported instruction: {} ({} at {})",
+                mnemonic, method, pc
+            ),
+            RuntimeError::TypeMismatch { expected, found } => {
+                write!(f, "type mismatch: expected {}, found {}", expected, found)
+            }
+            RuntimeError::Throw(reference) => write!(f, "exception thrown: {:?}", reference),
+            RuntimeError::Uncaught {
+                class,
+                message,
+                stack_trace,
+            } => {
+                write!(f, "Exception in thread \"main\" {}", class)?;
+                if let Some(message) = message {
+                    write!(f, ": {}", message)?;
+                }
+                for element in stack_trace {
+                    write!(f, "\n\tat {}", element)?;
+                }
+                Ok(())
+            }
+            RuntimeError::NullPointer(message) => {
+                write!(f, "java.lang.NullPointerException: {}", message)
+            }
+            RuntimeError::IllegalMonitorState => {
+                write!(f, "java.lang.IllegalMonitorStateException")
+            }
+            RuntimeError::MainMethodNotFound(class) => write!(
+                f,
+                "Main method not found in class {}, please define the main method as:\n   \
+                 public static void main(String[] args)",
+                class.replace('/', ".")
+            ),
+            RuntimeError::ArrayIndexOutOfBounds(message) => {
+                write!(f, "java.lang.ArrayIndexOutOfBoundsException: {}", message)
+            }
+            RuntimeError::StringIndexOutOfBounds(message) => {
+                write!(f, "java.lang.StringIndexOutOfBoundsException: {}", message)
+            }
+            RuntimeError::ArrayStore(message) => {
+                write!(f, "java.lang.ArrayStoreException: {}", message)
+            }
+            RuntimeError::NegativeArraySize(length) => {
+                write!(f, "java.lang.NegativeArraySizeException: {}", length)
+            }
+            RuntimeError::Arithmetic(message) => {
+                write!(f, "java.lang.ArithmeticException: {}", message)
+            }
+            RuntimeError::ClassCast(message) => {
+                write!(f, "java.lang.ClassCastException: {}", message)
+            }
+            RuntimeError::OutOfMemory => {
+                write!(f, "java.lang.OutOfMemoryError: Java heap space")
+            }
+            RuntimeError::NoSuchElement(message) => {
+                write!(f, "java.util.NoSuchElementException")?;
+                if let Some(message) = message {
+                    write!(f, ": {}", message)?;
+                }
+                Ok(())
+            }
+            RuntimeError::InputMismatch => write!(f, "java.util.InputMismatchException"),
+            RuntimeError::IllegalFormat(class, message) => {
+                write!(f, "{}: {}", class.replace('/', "."), message)
+            }
+            RuntimeError::NoSuchField { class, name } => {
+                write!(f, "java.lang.NoSuchFieldError: {}.{}", class, name)
+            }
+            RuntimeError::NoSuchMethod { class, name } => {
+                write!(f, "java.lang.NoSuchMethodError: {}.{}", class, name)
+            }
+            RuntimeError::NoClassDefFound(class) => {
+                write!(f, "java.lang.NoClassDefFoundError: {}", class)
+            }
+            RuntimeError::Verify { class, message } => {
+                write!(f, "java.lang.VerifyError: {}: {}", class, message)
+            }
+            RuntimeError::IllegalAccess(message) => {
+                write!(f, "java.lang.IllegalAccessError: {}", message)
+            }
+            RuntimeError::DanglingReference(reference) => {
+                write!(f, "dangling reference: {:?}", reference)
+            }
+            RuntimeError::ClassFormat { class, message } => {
+                write!(f, "java.lang.ClassFormatError: {}: {}", class, message)
+            }
+            RuntimeError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<std::io::Error> for RuntimeError {
+    fn from(err: std::io::Error) -> Self {
+        RuntimeError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Reference {
     Class(usize),
     Array(usize),
@@ -186,26 +1060,296 @@ pub enum Reference {
     Object(usize),
 }
 
+/// What a key of a `java.util.HashMap` is looked up by
+///
+/// Strings and boxed primitives are equal by value, so they are their own
+/// key. Other objects are grouped by their `hashCode`, and the entries of a
+/// group are told apart with `equals`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Null,
+    String(Rc<str>),
+    /// A boxed primitive, by its class and the bits of its value
+    Boxed(String, i64),
+    Hash(i32),
+}
+
 pub struct Instantce {
-    pub class: usize,
+    /// The name of the class this is an instance of
+    pub class: String,
     pub index: usize,
-    pub fields: HashMap<String, Value>,
+    /// Unique id of the instance, increasing in allocation order
+    pub id: usize,
+    /// The identity hash code, as returned by `Object.hashCode`
+    pub hash: i32,
+    /// How often the monitor of the instance is currently entered
+    pub monitor: usize,
+    /// The values of the fields, keyed by the declaring class and the name
+    /// of the field, e.g. `Point.x`
+    ///
+    /// They are ordered by key, so anything listing them is reproducible.
+    pub fields: BTreeMap<String, Value>,
+    /// For a Throwable, the methods active when it was created, innermost first
+    pub stack_trace: Vec<String>,
+    /// For a `java.util.HashMap`, its keys and values by what they are looked
+    /// up by
+    pub entries: HashMap<MapKey, Vec<(Value, Value)>>,
 }
 
 impl Instantce {
-    pub fn new(class: usize, index: usize) -> Self {
+    pub fn new(class: &str, index: usize, id: usize, hash: i32) -> Self {
         Self {
-            class,
+            class: class.to_string(),
             index,
-            fields: HashMap::new(),
+            id,
+            hash,
+            monitor: 0,
+            fields: BTreeMap::new(),
+            stack_trace: Vec::new(),
+            entries: HashMap::new(),
         }
     }
 
-    pub fn get_field(&self, name: &str) -> &Value {
-        self.fields.get(name).unwrap()
+    pub fn get_field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
     }
 
     pub fn put_field(&mut self, name: &str, value: Value) {
         self.fields.insert(name.to_string(), value);
     }
+
+    /// The class, id and fields of the instance, e.g.
+    /// `Point#3 {Point.next = Object(4 : Point), Point.x = 1}`
+    pub fn dump(&self, heap: &Heap) -> String {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, heap.render(value)))
+            .collect::<Vec<_>>();
+        format!("{}#{} {{{}}}", self.class, self.id, fields.join(", "))
+    }
+}
+
+pub struct ArrayObject {
+    /// The name of the array class, e.g. `[I` for `int[]`
+    pub class: String,
+    /// Unique id of the array, shared with the ids of instances
+    pub id: usize,
+    pub hash: i32,
+    pub elements: Vec<Value>,
+}
+
+impl ArrayObject {
+    fn out_of_bounds(&self, index: i32) -> RuntimeError {
+        RuntimeError::ArrayIndexOutOfBounds(format!(
+            "Index {} out of bounds for length {}",
+            index,
+            self.elements.len()
+        ))
+    }
+
+    /// The element at `index`, checking that it is within the bounds
+    pub fn get(&self, index: i32) -> Result<&Value, RuntimeError> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.elements.get(i))
+            .ok_or_else(|| self.out_of_bounds(index))
+    }
+
+    pub fn set(&mut self, index: i32, value: Value) -> Result<(), RuntimeError> {
+        let i = usize::try_from(index)
+            .ok()
+            .filter(|&i| i < self.elements.len())
+            .ok_or_else(|| self.out_of_bounds(index))?;
+        self.elements[i] = value;
+        Ok(())
+    }
+
+    /// The element type as used by the messages of the JVM, e.g. `int`
+    pub fn element_name(&self) -> &'static str {
+        match &self.class[1..] {
+            "Z" => "boolean",
+            "B" => "byte",
+            "C" => "char",
+            "S" => "short",
+            "I" => "int",
+            "J" => "long",
+            "F" => "float",
+            "D" => "double",
+            _ => "object array",
+        }
+    }
+
+    /// The element type and length, e.g. `int[3]`
+    pub fn describe(&self) -> String {
+        format!("{}[{}]", self.element_name(), self.elements.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int_array(elements: &[i32]) -> ArrayObject {
+        ArrayObject {
+            class: "[I".to_string(),
+            id: 0,
+            hash: 0,
+            elements: elements.iter().map(|&value| Value::Int(value)).collect(),
+        }
+    }
+
+    #[test]
+    fn array_elements_are_read_and_written_within_bounds() {
+        let mut array = int_array(&[1, 2, 3]);
+        array.set(2, Value::Int(7)).unwrap();
+        assert!(matches!(array.get(0), Ok(Value::Int(1))));
+        assert!(matches!(array.get(2), Ok(Value::Int(7))));
+        assert_eq!(array.describe(), "int[3]");
+    }
+
+    #[test]
+    fn array_indices_out_of_bounds_are_errors() {
+        let mut array = int_array(&[1, 2, 3]);
+        for index in [-1, 3, i32::MAX] {
+            let message = format!("Index {} out of bounds for length 3", index);
+            assert!(matches!(
+                array.get(index),
+                Err(RuntimeError::ArrayIndexOutOfBounds(m)) if m == message
+            ));
+            assert!(matches!(
+                array.set(index, Value::Int(0)),
+                Err(RuntimeError::ArrayIndexOutOfBounds(m)) if m == message
+            ));
+        }
+        assert!(matches!(array.get(1), Ok(Value::Int(2))));
+    }
+
+    #[test]
+    fn values_round_trip_through_the_primitives() {
+        assert_eq!(i32::try_from(Value::from(-7)).unwrap(), -7);
+        assert_eq!(i64::try_from(Value::from(1i64 << 40)).unwrap(), 1 << 40);
+        assert_eq!(f32::try_from(Value::from(1.5f32)).unwrap(), 1.5);
+        assert_eq!(f64::try_from(Value::from(0.1)).unwrap(), 0.1);
+        assert!(matches!(Value::from(true), Value::Int(1)));
+        assert!(bool::try_from(Value::from(true)).unwrap());
+        assert!(!bool::try_from(Value::from(false)).unwrap());
+        let string = Value::from("jrm".to_string());
+        assert_eq!(String::try_from(string).unwrap(), "jrm");
+    }
+
+    #[test]
+    fn converting_the_wrong_type_is_a_type_mismatch() {
+        let mismatch = |result: Result<(), RuntimeError>, expected: &str| {
+            assert!(matches!(
+                result,
+                Err(RuntimeError::TypeMismatch { expected: e, .. }) if e == expected
+            ));
+        };
+        mismatch(i32::try_from(Value::Long(1)).map(drop), "int");
+        mismatch(i64::try_from(Value::Int(1)).map(drop), "long");
+        mismatch(f32::try_from(Value::Double(1.0)).map(drop), "float");
+        mismatch(f64::try_from(Value::Float(1.0)).map(drop), "double");
+        mismatch(bool::try_from(Value::Int(2)).map(drop), "boolean");
+        mismatch(String::try_from(Value::Null).map(drop), "String");
+    }
+
+    #[test]
+    fn doubles_and_floats_print_like_the_jdk() {
+        // the outputs of `Double.toString` and `Float.toString`
+        let doubles = [
+            (0.1, "0.1"),
+            (100.0, "100.0"),
+            (1e7, "1.0E7"),
+            (9999999.0, "9999999.0"),
+            (0.001, "0.001"),
+            (0.0001234, "1.234E-4"),
+            (-2.5e-10, "-2.5E-10"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (4.9e-324, "4.9E-324"),
+            (f64::MAX, "1.7976931348623157E308"),
+        ];
+        for (value, expected) in doubles {
+            assert_eq!(Value::Double(value).to_string(), expected);
+        }
+        let floats = [
+            (0.1f32, "0.1"),
+            (0.1 + 0.2, "0.3"),
+            (1.0 / 3.0, "0.33333334"),
+            (1234567.0, "1234567.0"),
+            (f32::MAX, "3.4028235E38"),
+        ];
+        for (value, expected) in floats {
+            assert_eq!(Value::Float(value).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn instance_dumps_list_fields_in_key_order() {
+        let mut heap = Heap::with_deterministic_ids(true);
+        let point = heap.malloc_instance("Point");
+        let next = heap.malloc_instance("Point");
+        let instance = heap.get_mut(&point).unwrap();
+        instance.put_field("Point.y", Value::Int(2));
+        instance.put_field("Point.next", Value::Reference(next));
+        instance.put_field("Point.x", Value::Int(1));
+        let dump = heap.get(&point).unwrap().dump(&heap);
+        assert_eq!(
+            dump,
+            "Point#0 {Point.next = Object(1 : Point), Point.x = 1, Point.y = 2}"
+        );
+    }
+
+    #[test]
+    fn references_render_with_their_class() {
+        let mut heap = Heap::with_deterministic_ids(true);
+        let foo = heap.malloc_instance("com/example/Foo");
+        let array = heap.malloc_array("[I", vec![Value::Int(1)]);
+        let garbage = heap.malloc_instance("Garbage");
+        heap.gc(&[], [foo.clone(), array.clone()].into_iter());
+        assert_eq!(
+            heap.render(&Value::Reference(foo)),
+            "Object(0 : com/example/Foo)"
+        );
+        assert_eq!(heap.render(&Value::Reference(array)), "Array(0 : [I)");
+        assert_eq!(
+            heap.render(&Value::Reference(garbage)),
+            "Object(1 : collected)"
+        );
+        assert_eq!(heap.render(&Value::String("a".into())), "\"a\"");
+        assert_eq!(heap.render(&Value::Int(3)), "3");
+    }
+
+    #[test]
+    fn collected_ids_are_ascending() {
+        let mut heap = Heap::with_deterministic_ids(true);
+        let kept = heap.malloc_instance("Kept");
+        heap.malloc_array("[I", vec![]);
+        heap.malloc_instance("Garbage");
+        heap.malloc_array("[I", vec![]);
+        assert_eq!(heap.gc(&[], [kept].into_iter()), [1, 2, 3]);
+    }
+
+    #[test]
+    fn collected_objects_are_dangling_references() {
+        let mut heap = Heap::new();
+        let kept = heap.malloc_instance("Kept");
+        let collected = heap.malloc_instance("Garbage");
+        heap.gc(&[], [kept.clone()].into_iter());
+        assert!(heap.get(&kept).is_ok());
+        assert!(matches!(
+            heap.get(&collected),
+            Err(RuntimeError::DanglingReference(r)) if r == collected
+        ));
+        assert!(matches!(
+            heap.get_mut(&collected),
+            Err(RuntimeError::DanglingReference(_))
+        ));
+        // an array isn't an instance either
+        let array = heap.malloc_array("[I", vec![]);
+        assert!(matches!(
+            heap.get(&array),
+            Err(RuntimeError::DanglingReference(_))
+        ));
+    }
 }
@@ -0,0 +1,470 @@
+//! Checks of the code of methods which don't need to execute it.
+
+use crate::bytecode::ByteCode;
+use crate::class_file::{ClassFile, CodeAttribute, ConstantInfo};
+use crate::runtime::RuntimeError;
+
+/// The sizes of the operand stack and the local variables of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSizes {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+/// The number of slots taken by a value of the type starting with `c`
+fn slots(c: u8) -> u16 {
+    match c {
+        b'J' | b'D' => 2,
+        b'V' => 0,
+        _ => 1,
+    }
+}
+
+/// The slots taken by the parameters and by the return value of a method,
+/// or `None` if `descriptor` is malformed
+fn method_slots(descriptor: &str) -> Option<(u16, u16)> {
+    let bytes = descriptor.as_bytes();
+    if bytes.first() != Some(&b'(') {
+        return None;
+    }
+    let mut parameters = 0;
+    let mut index = 1;
+    while *bytes.get(index)? != b')' {
+        let start = index;
+        while *bytes.get(index)? == b'[' {
+            index += 1;
+        }
+        if bytes[index] == b'L' {
+            while *bytes.get(index)? != b';' {
+                index += 1;
+            }
+        }
+        index += 1;
+        // arrays and objects are a single reference
+        parameters += if index == start + 1 {
+            slots(bytes[start])
+        } else {
+            1
+        };
+    }
+    Some((parameters, slots(*bytes.get(index + 1)?)))
+}
+
+/// The number of slots popped from and pushed onto the operand stack by `bc`,
+/// or `None` if the member or descriptor it refers to is malformed
+fn stack_effect(class: &ClassFile, bc: ByteCode) -> Option<(u16, u16)> {
+    use ByteCode::*;
+    let member = |index: u16| class.member_refs.get(index as usize)?.as_ref();
+    let descriptor = |index: u16| member(index)?.descriptor.as_bytes().first().copied();
+    Some(match bc {
+        IConst(_) | FConst(_) | Bipush(_) | Sipush(_) | Ldc(_) | LdcW(_) | AConstNull
+        | ILoad(_) | FLoad(_) | ALoad(_) | New(_) => (0, 1),
+        Pop
+        | IStore(_)
+        | FStore(_)
+        | AStore(_)
+        | If(..)
+        | IReturn
+        | FReturn
+        | AReturn
+        | AThrow
+        | MonitorEnter
+        | MonitorExit
+        | TableSwitch { .. }
+        | LookupSwitch { .. } => (1, 0),
+        IfICmp(..) | IfACmp(..) | Pop2 => (2, 0),
+        Ldc2W(_) | LConst(_) | DConst(_) | LLoad(_) | DLoad(_) => (0, 2),
+        LStore(_) | DStore(_) | LReturn | DReturn => (2, 0),
+        I2L => (1, 2),
+        L2I => (2, 1),
+        LCmp | DCmp(_) => (4, 1),
+        FCmp(_) => (2, 1),
+        Return | Goto(_) | IInc(..) => (0, 0),
+        IAdd | ISub | IMul | IDiv | IRem | IShl | IShr | IUShr | IAnd | IOr | IXor => (2, 1),
+        IALoad | AALoad | BALoad | CALoad | SALoad | FALoad => (2, 1),
+        LALoad | DALoad => (2, 2),
+        FAdd | FSub | FMul | FDiv | FRem => (2, 1),
+        INeg | FNeg => (1, 1),
+        LAdd | LSub | LMul => (4, 2),
+        LNeg => (2, 2),
+        // the distance of a shift is an int
+        LShl | LShr | LUShr => (3, 2),
+        IAStore | AAStore | BAStore | CAStore | SAStore | FAStore => (3, 0),
+        LAStore | DAStore => (4, 0),
+        NewArray(_) | ANewArray(_) | ArrayLength | CheckCast(_) | InstanceOf(_) => (1, 1),
+        Dup => (1, 2),
+        DupX1 => (2, 3),
+        Dup2 => (2, 4),
+        DupX2 => (3, 4),
+        Dup2X1 => (3, 5),
+        Dup2X2 => (4, 6),
+        GetStatic(index) => (0, slots(descriptor(index)?)),
+        GetField(index) => (1, slots(descriptor(index)?)),
+        PutStatic(index) => (slots(descriptor(index)?), 0),
+        PutField(index) => (1 + slots(descriptor(index)?), 0),
+        InvokeStatic(index) => method_slots(&member(index)?.descriptor)?,
+        InvokeVirtual(index) | InvokeSpecial(index) | InvokeInterface(index) => {
+            let (parameters, result) = method_slots(&member(index)?.descriptor)?;
+            (parameters + 1, result)
+        }
+        InvokeDynamic(index) => {
+            let cp = &class.constant_pool;
+            let Some(ConstantInfo::InvokeDynamic {
+                name_and_type_index,
+                ..
+            }) = cp.get(index as usize)
+            else {
+                return None;
+            };
+            let (_, descriptor_index) =
+                cp.get(*name_and_type_index as usize)?.as_name_and_type()?;
+            method_slots(cp.get(descriptor_index as usize)?.as_utf8()?)?
+        }
+    })
+}
+
+/// Compute how large the operand stack and the local variables of a method
+/// of `class` with the `code` need to be, so they don't have to be counted
+/// by hand
+///
+/// Every path through the code is followed while tracking the depth of the
+/// operand stack, which must be the same wherever paths merge.
+pub fn compute_frame_sizes(
+    class: &ClassFile,
+    code: &CodeAttribute,
+    descriptor: &str,
+    is_static: bool,
+) -> Result<FrameSizes, RuntimeError> {
+    let error = |message: String| RuntimeError::Verify {
+        class: class.name().to_string(),
+        message,
+    };
+    let (parameters, _) = method_slots(descriptor)
+        .ok_or_else(|| error(format!("malformed descriptor {}", descriptor)))?;
+    let mut sizes = FrameSizes {
+        max_stack: 0,
+        max_locals: parameters + !is_static as u16,
+    };
+    let mut depths = vec![None; code.code.len()];
+    // the thrown exception is the only operand of a handler
+    let mut pending = vec![(0, 0u16)];
+    for entry in &code.exception_table {
+        pending.push((entry.handler_pc as usize, 1));
+    }
+    while let Some((pc, depth)) = pending.pop() {
+        match depths.get(pc) {
+            None => return Err(error(format!("control flow leaves the code at {}", pc))),
+            Some(Some(known)) if *known == depth => continue,
+            Some(Some(known)) => {
+                return Err(error(format!(
+                    "stack depths {} and {} merge at {}",
+                    known, depth, pc
+                )))
+            }
+            Some(None) => depths[pc] = Some(depth),
+        }
+        let (next, bc) =
+            ByteCode::try_parse(pc, &code.code).ok_or_else(|| {
+                match ByteCode::mnemonic(code.code[pc]) {
+                    Some(name) => error(format!("unsupported instruction {} at {}", name, pc)),
+                    None => error(format!("invalid instruction at {}", pc)),
+                }
+            })?;
+        let (pop, push) = stack_effect(class, bc)
+            .ok_or_else(|| error(format!("malformed member or descriptor at {}", pc)))?;
+        let depth = depth
+            .checked_sub(pop)
+            .ok_or_else(|| error(format!("operand stack underflow at {}", pc)))?
+            + push;
+        sizes.max_stack = sizes.max_stack.max(depth);
+        if let ByteCode::ILoad(index)
+        | ByteCode::IStore(index)
+        | ByteCode::FLoad(index)
+        | ByteCode::FStore(index)
+        | ByteCode::ALoad(index)
+        | ByteCode::AStore(index) = bc
+        {
+            sizes.max_locals = sizes.max_locals.max(index as u16 + 1);
+        }
+        if let ByteCode::IInc(index, _) = bc {
+            sizes.max_locals = sizes.max_locals.max(index + 1);
+        }
+        // longs and doubles take two local variables
+        if let ByteCode::LLoad(index)
+        | ByteCode::LStore(index)
+        | ByteCode::DLoad(index)
+        | ByteCode::DStore(index) = bc
+        {
+            sizes.max_locals = sizes.max_locals.max(index as u16 + 2);
+        }
+        let target = |offset: i32| (pc as i64 + offset as i64) as usize;
+        match bc {
+            ByteCode::Return
+            | ByteCode::IReturn
+            | ByteCode::FReturn
+            | ByteCode::LReturn
+            | ByteCode::DReturn
+            | ByteCode::AReturn
+            | ByteCode::AThrow => {}
+            ByteCode::Goto(offset) => pending.push((target(offset.into()), depth)),
+            ByteCode::If(_, offset) | ByteCode::IfICmp(_, offset) | ByteCode::IfACmp(_, offset) => {
+                pending.push((next, depth));
+                pending.push((target(offset.into()), depth));
+            }
+            ByteCode::TableSwitch { .. } | ByteCode::LookupSwitch { .. } => {
+                for offset in bc.switch_offsets(&code.code) {
+                    pending.push((target(offset), depth));
+                }
+            }
+            _ => pending.push((next, depth)),
+        }
+    }
+    Ok(sizes)
+}
+
+/// Check that the code of every method of `class` keeps the operand stack
+/// consistent and within the sizes it declares
+///
+/// This follows the control flow itself rather than relying on a
+/// `StackMapTable`, so it works for old class files without one too.
+pub fn verify(class: &ClassFile) -> Result<(), RuntimeError> {
+    match problems(class).into_iter().next() {
+        Some(message) => Err(RuntimeError::Verify {
+            class: class.name().to_string(),
+            message,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The problems [`verify`] finds in `class`, the first of every method with
+/// any, e.g. `f()V: control flow leaves the code at 12`
+///
+/// A method's problem may hide later ones of the same method, as its control
+/// flow can't be followed past it, but not those of other methods.
+pub fn problems(class: &ClassFile) -> Vec<String> {
+    let cp = &class.constant_pool;
+    let mut problems = vec![];
+    let utf8 = |index: u16| cp.get(index as usize).and_then(ConstantInfo::as_utf8);
+    for (index, method) in class.methods.iter().enumerate() {
+        if !method.has_code(cp) {
+            continue;
+        }
+        // the name and descriptor are only indices into the constant pool
+        let Some(name) = utf8(method.name_index) else {
+            problems.push(format!("method {}: malformed name index", index));
+            continue;
+        };
+        let Some(descriptor) = utf8(method.descriptor_index) else {
+            problems.push(format!("{}: malformed descriptor index", name));
+            continue;
+        };
+        let name = format!("{}{}", name, descriptor);
+        let code = match method.read_code(cp) {
+            Ok(code) => code,
+            Err(err) => {
+                problems.push(format!("{}: {:#}", name, err));
+                continue;
+            }
+        };
+        match compute_frame_sizes(class, &code, descriptor, method.is_static()) {
+            Ok(sizes) if sizes.max_stack > code.max_stack || sizes.max_locals > code.max_locals => {
+                problems.push(format!(
+                    "{}: needs a stack of {} and {} locals, but declares {} and {}",
+                    name, sizes.max_stack, sizes.max_locals, code.max_stack, code.max_locals
+                ));
+            }
+            Ok(_) => {}
+            Err(RuntimeError::Verify { message, .. }) => {
+                problems.push(format!("{}: {}", name, message))
+            }
+            Err(err) => problems.push(format!("{}: {}", name, err)),
+        }
+    }
+    problems
+}
+
+/// Check that every method of `class` only uses instructions jrm supports,
+/// listing all the ones it doesn't otherwise
+///
+/// This lets a program be rejected before it runs, rather than failing
+/// partway through when it first reaches one of them.
+pub fn check_supported(class: &ClassFile) -> Result<(), RuntimeError> {
+    let cp = &class.constant_pool;
+    let mut unsupported = vec![];
+    for (index, method) in class.methods.iter().enumerate() {
+        if !method.has_code(cp) {
+            continue;
+        }
+        let method_name = match cp
+            .get(method.name_index as usize)
+            .and_then(ConstantInfo::as_utf8)
+        {
+            Some(name) => name.to_string(),
+            None => format!("method {}", index),
+        };
+        // a method whose code can't be read can't run either
+        let code = method.read_code(cp).map_err(|err| RuntimeError::Verify {
+            class: class.name().to_string(),
+            message: format!("{}: {:#}", method_name, err),
+        })?;
+        for (pc, name) in ByteCode::unsupported(&code.code) {
+            unsupported.push(format!("{} ({} at {})", name, method_name, pc));
+        }
+    }
+    if unsupported.is_empty() {
+        return Ok(());
+    }
+    Err(RuntimeError::Verify {
+        class: class.name().to_string(),
+        message: format!(
+            "uses instructions jrm doesn't support: {}",
+            unsupported.join(", ")
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::{Options, Vm};
+
+    fn test_class(name: &str) -> ClassFile {
+        let path = format!("{}/tests/java/{}.class", env!("CARGO_MANIFEST_DIR"), name);
+        ClassFile::parse(&path).unwrap()
+    }
+
+    /// A `Code` attribute with `code` and no exception handlers
+    fn code_attribute(code: &[u8]) -> CodeAttribute {
+        CodeAttribute {
+            max_stack: 0,
+            max_locals: 0,
+            code_length: code.len() as u32,
+            code: code.to_vec(),
+            exception_table_length: 0,
+            exception_table: vec![],
+            attributes_count: 0,
+            attributes: vec![],
+        }
+    }
+
+    #[test]
+    fn diamond_with_equal_depths_merges() {
+        // return x == 0 ? 2 : 1
+        let code = code_attribute(&[0x1a, 0x99, 0, 7, 0x04, 0xa7, 0, 4, 0x05, 0xac]);
+        let sizes = compute_frame_sizes(&test_class("Hello"), &code, "(I)I", true).unwrap();
+        assert_eq!(
+            sizes,
+            FrameSizes {
+                max_stack: 1,
+                max_locals: 1
+            }
+        );
+    }
+
+    #[test]
+    fn diamond_with_different_depths_is_a_verify_error() {
+        // iload_0, ifeq 5, iconst_1, then at 5: iconst_3, ireturn
+        let code = code_attribute(&[0x1a, 0x99, 0, 4, 0x04, 0x06, 0xac]);
+        let result = compute_frame_sizes(&test_class("Hello"), &code, "(I)I", true);
+        match result {
+            Err(RuntimeError::Verify { class, message }) => {
+                assert_eq!(class, "Hello");
+                assert!(message.ends_with("merge at 5"), "{}", message);
+            }
+            result => panic!("expected a VerifyError, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn computed_frame_sizes_run_the_method() {
+        let mut class = test_class("ArrayInit");
+        let main = class
+            .methods
+            .iter()
+            .position(|method| method.name(&class.constant_pool) == "main")
+            .unwrap();
+        // the Code attribute is its only one, and starts with the sizes
+        let info = &mut class.methods[main].attributes[0].info;
+        info[..4].fill(0);
+        let code = CodeAttribute::parse(info).unwrap();
+        let sizes = compute_frame_sizes(&class, &code, "([Ljava/lang/String;)V", true).unwrap();
+        assert_eq!(
+            sizes,
+            FrameSizes {
+                max_stack: 4,
+                max_locals: 3
+            }
+        );
+        let info = &mut class.methods[main].attributes[0].info;
+        info[..2].copy_from_slice(&sizes.max_stack.to_be_bytes());
+        info[2..4].copy_from_slice(&sizes.max_locals.to_be_bytes());
+        let mut out = vec![];
+        Vm::with_output(class, Options::default(), &mut out)
+            .run_main()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "3\n1\n2\n3\nIndex 3 out of bounds for length 3\n"
+        );
+    }
+
+    #[test]
+    fn malformed_descriptors_have_no_slots() {
+        assert_eq!(method_slots("(IJ[JLjava/lang/String;)D"), Some((5, 2)));
+        assert_eq!(method_slots("()V"), Some((0, 0)));
+        for descriptor in ["", "I", "(I", "(Ljava/lang/String", "([", "(I)"] {
+            assert_eq!(method_slots(descriptor), None, "{}", descriptor);
+        }
+        let code = code_attribute(&[0xb1]);
+        let result = compute_frame_sizes(&test_class("Hello"), &code, "(I", true);
+        assert!(
+            matches!(&result, Err(RuntimeError::Verify { message, .. }) if message == "malformed descriptor (I"),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn malformed_code_is_a_problem_of_its_method() {
+        let mut class = test_class("Hello");
+        let main = class
+            .methods
+            .iter()
+            .position(|method| method.name(&class.constant_pool) == "main")
+            .unwrap();
+        // cut the Code attribute off in the middle of the instructions
+        class.methods[main].attributes[0].info.truncate(10);
+        let problems = problems(&class);
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("main([Ljava/lang/String;)V: reading the code of main"),
+            "{}",
+            problems[0]
+        );
+        assert!(matches!(
+            check_supported(&class),
+            Err(RuntimeError::Verify { message, .. }) if message.starts_with("main: reading the code")
+        ));
+    }
+
+    #[test]
+    fn malformed_names_and_descriptors_are_problems_of_their_method() {
+        let mut class = test_class("Hello");
+        let main = class
+            .methods
+            .iter()
+            .position(|method| method.name(&class.constant_pool) == "main")
+            .unwrap();
+        // point the descriptor at the Class constant of Hello itself
+        class.methods[main].descriptor_index = class.this_class;
+        assert_eq!(problems(&class), ["main: malformed descriptor index"]);
+        class.methods[main].name_index = class.constant_pool.len() as u16;
+        assert_eq!(
+            problems(&class),
+            [format!("method {}: malformed name index", main)]
+        );
+        // which doesn't keep the instructions from being checked
+        assert!(check_supported(&class).is_ok());
+    }
+}
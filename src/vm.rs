@@ -0,0 +1,4164 @@
+//! The interpreter which executes the methods of the loaded classes.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::bytecode::ByteCode;
+use crate::class_file::{
+    Access, ClassFile, CodeAttribute, ConstantInfo, FieldInfo, MemberRef, MethodInfo,
+};
+use crate::runtime::{
+    java_fixed, Frame, GcEvent, GcMode, Heap, History, MapKey, Reference, RuntimeError, Value,
+};
+use crate::verifier;
+
+/// The number of local variables taken by each parameter of the method
+/// `descriptor`, which is 2 for longs and doubles and 1 otherwise
+fn parameter_slots(descriptor: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut rest = &descriptor.as_bytes()[1..];
+    std::iter::from_fn(move || {
+        // an array of any element type is a single reference
+        let dimensions = rest.iter().take_while(|&&c| c == b'[').count();
+        let (slots, len) = match rest.get(dimensions)? {
+            b')' => return None,
+            b'L' => (1, rest.iter().position(|&c| c == b';')? + 1),
+            b'J' | b'D' if dimensions == 0 => (2, 1),
+            _ => (1, dimensions + 1),
+        };
+        rest = &rest[len..];
+        Some(slots)
+    })
+}
+
+/// The number of arguments of the method `descriptor`, which are a single
+/// value each on the operand stack
+fn args_size(descriptor: &str) -> usize {
+    parameter_slots(descriptor).count()
+}
+
+/// Move the arguments `args` of a call of the method `descriptor`, `this`
+/// first unless it is static, into the local variables of its `frame`
+///
+/// Longs and doubles are a single value on the operand stack, but take two
+/// local variables.
+fn pass_arguments(
+    frame: &mut Frame,
+    descriptor: &str,
+    is_static: bool,
+    args: impl Iterator<Item = Value>,
+) {
+    let this = std::iter::repeat_n(1, !is_static as usize);
+    let mut local = 0;
+    for (arg, slots) in args.zip(this.chain(parameter_slots(descriptor))) {
+        frame.locals[local] = arg;
+        local += slots;
+    }
+}
+
+/// The descriptors of the parameters of the method `descriptor`, e.g.
+/// `["I", "[Ljava/lang/Object;"]` for `(I[Ljava/lang/Object;)V`
+fn parameters(descriptor: &str) -> Vec<&str> {
+    let (parameters, _) = descriptor[1..].split_once(')').unwrap();
+    let mut result = vec![];
+    let mut rest = parameters;
+    while !rest.is_empty() {
+        let dimensions = rest.len() - rest.trim_start_matches('[').len();
+        let end = match rest.as_bytes()[dimensions] {
+            b'L' => rest.find(';').unwrap() + 1,
+            _ => dimensions + 1,
+        };
+        let (parameter, next) = rest.split_at(end);
+        result.push(parameter);
+        rest = next;
+    }
+    result
+}
+
+/// The package of the class named `name`, like `com/example`, which is
+/// empty for classes without one
+fn package_name(name: &str) -> &str {
+    name.rsplit_once('/').map_or("", |(package, _)| package)
+}
+
+/// The key of a field in the fields of an instance
+///
+/// Fields are qualified by the declaring class, as a subclass may declare a
+/// field of the same name as one of its superclasses.
+fn field_key(class: &str, name: &str) -> String {
+    format!("{}.{}", class, name)
+}
+
+/// The field holding the message of a Throwable
+const THROWABLE_MESSAGE: &str = "java/lang/Throwable.detailMessage";
+
+/// The field holding the cause of a Throwable
+const THROWABLE_CAUSE: &str = "java/lang/Throwable.cause";
+
+/// The key of the name of a `java.lang.Thread`
+const THREAD_NAME: &str = "java/lang/Thread.name";
+
+/// The key of the contents of a `java.lang.StringBuilder`, which are a string
+const STRING_BUILDER_VALUE: &str = "java/lang/AbstractStringBuilder.value";
+
+/// The key of the value of a `java.lang.Integer`
+const INTEGER_VALUE: &str = "java/lang/Integer.value";
+
+/// The values `Integer.valueOf` returns the same instance for every time
+const INTEGER_CACHE: std::ops::RangeInclusive<i32> = -128..=127;
+
+/// Pop the array reference operand of an array instruction
+fn pop_array(operand_stack: &mut Vec<Value>) -> Result<Reference, RuntimeError> {
+    match operand_stack.pop().unwrap() {
+        Value::Reference(reference @ Reference::Array(_)) => Ok(reference),
+        Value::Null => Err(RuntimeError::NullPointer(
+            "cannot access an array through null".to_string(),
+        )),
+        value => Err(type_mismatch("array", &value)),
+    }
+}
+
+/// Pop the object operand of a `getfield` or `putfield` of the field `name`,
+/// where `access` says which
+fn pop_object(
+    operand_stack: &mut Vec<Value>,
+    access: &str,
+    name: &str,
+) -> Result<Reference, RuntimeError> {
+    match operand_stack.pop().unwrap() {
+        Value::Reference(reference) => Ok(reference),
+        Value::Null => Err(RuntimeError::NullPointer(format!(
+            "cannot {} field {} of null",
+            access, name
+        ))),
+        value => Err(type_mismatch("reference", &value)),
+    }
+}
+
+/// The class of the elements of the array class `class`, e.g.
+/// `java/lang/String` for `[Ljava/lang/String;` and `I` for `[I`
+fn element_class(class: &str) -> &str {
+    let element = &class[1..];
+    element
+        .strip_prefix('L')
+        .and_then(|element| element.strip_suffix(';'))
+        .unwrap_or(element)
+}
+
+/// Whether the elements of the array class `class` are references
+fn has_reference_elements(class: &str) -> bool {
+    matches!(class.as_bytes()[1], b'L' | b'[')
+}
+
+/// The name of the class or primitive type as written in Java, e.g.
+/// `java.lang.String[]` for `[Ljava/lang/String;`
+fn java_type_name(class: &str) -> String {
+    if class.starts_with('[') {
+        return format!("{}[]", java_type_name(element_class(class)));
+    }
+    let primitive = match class {
+        "B" => "byte",
+        "C" => "char",
+        "D" => "double",
+        "F" => "float",
+        "I" => "int",
+        "J" => "long",
+        "S" => "short",
+        "Z" => "boolean",
+        _ => return class.replace('/', "."),
+    };
+    primitive.to_string()
+}
+
+/// The superclass of the classes of the standard library which the VM knows
+/// about, mostly the Throwables it can raise itself
+fn java_super_class(name: &str) -> Option<&'static str> {
+    let super_class = match name {
+        "java/lang/Throwable" | "java/lang/String" | "java/lang/Number" => "java/lang/Object",
+        "java/lang/Boolean" | "java/lang/Character" | "java/lang/Class" => "java/lang/Object",
+        "java/lang/Thread" => "java/lang/Object",
+        "java/lang/Byte" | "java/lang/Short" | "java/lang/Integer" | "java/lang/Long" => {
+            "java/lang/Number"
+        }
+        "java/lang/Float" | "java/lang/Double" => "java/lang/Number",
+        "java/lang/Exception" | "java/lang/Error" => "java/lang/Throwable",
+        "java/lang/RuntimeException" => "java/lang/Exception",
+        "java/lang/ArithmeticException"
+        | "java/lang/ArrayStoreException"
+        | "java/lang/ClassCastException"
+        | "java/lang/IllegalArgumentException"
+        | "java/lang/IllegalMonitorStateException"
+        | "java/lang/IllegalStateException"
+        | "java/lang/IndexOutOfBoundsException"
+        | "java/lang/NegativeArraySizeException"
+        | "java/lang/NullPointerException"
+        | "java/util/NoSuchElementException"
+        | "java/lang/UnsupportedOperationException" => "java/lang/RuntimeException",
+        "java/lang/ArrayIndexOutOfBoundsException"
+        | "java/lang/StringIndexOutOfBoundsException" => "java/lang/IndexOutOfBoundsException",
+        "java/util/IllegalFormatException" => "java/lang/IllegalArgumentException",
+        "java/util/InputMismatchException" => "java/util/NoSuchElementException",
+        "java/util/IllegalFormatConversionException"
+        | "java/util/MissingFormatArgumentException"
+        | "java/util/UnknownFormatConversionException" => "java/util/IllegalFormatException",
+        "java/lang/AssertionError" | "java/lang/VirtualMachineError" => "java/lang/Error",
+        "java/lang/OutOfMemoryError" | "java/lang/StackOverflowError" => {
+            "java/lang/VirtualMachineError"
+        }
+        _ => return None,
+    };
+    Some(super_class)
+}
+
+/// The value of the constant `name` of the standard library class `class`,
+/// for the well-known ones of the wrapper classes
+///
+/// javac inlines these, but other compilers and hand-written bytecode read
+/// them with `getstatic`, and the classes declaring them are never loaded.
+fn library_constant(class: &str, name: &str) -> Option<Value> {
+    let value = match (class, name) {
+        ("java/lang/Integer", "MAX_VALUE") => Value::Int(i32::MAX),
+        ("java/lang/Integer", "MIN_VALUE") => Value::Int(i32::MIN),
+        ("java/lang/Long", "MAX_VALUE") => Value::Long(i64::MAX),
+        ("java/lang/Long", "MIN_VALUE") => Value::Long(i64::MIN),
+        ("java/lang/Short", "MAX_VALUE") => Value::Int(i16::MAX.into()),
+        ("java/lang/Short", "MIN_VALUE") => Value::Int(i16::MIN.into()),
+        ("java/lang/Byte", "MAX_VALUE") => Value::Int(i8::MAX.into()),
+        ("java/lang/Byte", "MIN_VALUE") => Value::Int(i8::MIN.into()),
+        ("java/lang/Character", "MAX_VALUE") => Value::Int(u16::MAX.into()),
+        ("java/lang/Character", "MIN_VALUE") => Value::Int(0),
+        ("java/lang/Integer" | "java/lang/Float", "SIZE") => Value::Int(32),
+        ("java/lang/Long" | "java/lang/Double", "SIZE") => Value::Int(64),
+        ("java/lang/Integer" | "java/lang/Float", "BYTES") => Value::Int(4),
+        ("java/lang/Long" | "java/lang/Double", "BYTES") => Value::Int(8),
+        ("java/lang/Float", "MAX_VALUE") => Value::Float(f32::MAX),
+        // the smallest positive value, not the most negative one
+        ("java/lang/Float", "MIN_VALUE") => Value::Float(f32::from_bits(1)),
+        ("java/lang/Float", "POSITIVE_INFINITY") => Value::Float(f32::INFINITY),
+        ("java/lang/Float", "NEGATIVE_INFINITY") => Value::Float(f32::NEG_INFINITY),
+        ("java/lang/Float", "NaN") => Value::Float(f32::NAN),
+        ("java/lang/Double", "MAX_VALUE") => Value::Double(f64::MAX),
+        ("java/lang/Double", "MIN_VALUE") => Value::Double(f64::from_bits(1)),
+        ("java/lang/Double", "POSITIVE_INFINITY") => Value::Double(f64::INFINITY),
+        ("java/lang/Double", "NEGATIVE_INFINITY") => Value::Double(f64::NEG_INFINITY),
+        ("java/lang/Double", "NaN") => Value::Double(f64::NAN),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The descriptor of the primitive type boxed by the wrapper class `class`,
+/// for those whose `valueOf` the VM implements
+fn boxed_type(class: &str) -> Option<&'static str> {
+    match class {
+        "java/lang/Integer" => Some("I"),
+        "java/lang/Character" => Some("C"),
+        "java/lang/Long" => Some("J"),
+        "java/lang/Float" => Some("F"),
+        "java/lang/Double" => Some("D"),
+        _ => None,
+    }
+}
+
+/// The zeros of the decimal digits (Unicode category Nd) `Character.isDigit`
+/// of Java 17 accepts, which all come in runs of ten from zero to nine
+///
+/// Rust's `char::is_numeric` also counts other numbers like `½` or `Ⅻ`.
+const DECIMAL_DIGIT_ZEROS: [u32; 65] = [
+    0x30, 0x660, 0x6f0, 0x7c0, 0x966, 0x9e6, 0xa66, 0xae6, 0xb66, 0xbe6, 0xc66, 0xce6, 0xd66,
+    0xde6, 0xe50, 0xed0, 0xf20, 0x1040, 0x1090, 0x17e0, 0x1810, 0x1946, 0x19d0, 0x1a80, 0x1a90,
+    0x1b50, 0x1bb0, 0x1c40, 0x1c50, 0xa620, 0xa8d0, 0xa900, 0xa9d0, 0xa9f0, 0xaa50, 0xabf0, 0xff10,
+    0x104a0, 0x10d30, 0x11066, 0x110f0, 0x11136, 0x111d0, 0x112f0, 0x11450, 0x114d0, 0x11650,
+    0x116c0, 0x11730, 0x118e0, 0x11950, 0x11c50, 0x11d50, 0x11da0, 0x16a60, 0x16b50, 0x1d7ce,
+    0x1d7d8, 0x1d7e2, 0x1d7ec, 0x1d7f6, 0x1e140, 0x1e2f0, 0x1e950, 0x1fbf0,
+];
+
+/// The value of `c` if it is a decimal digit of any script
+fn decimal_digit(c: char) -> Option<u32> {
+    let c = c as u32;
+    DECIMAL_DIGIT_ZEROS
+        .iter()
+        .find(|&&zero| (zero..zero + 10).contains(&c))
+        .map(|zero| c - zero)
+}
+
+/// Load the constant at `index` of the constant pool as a value
+fn load_constant(
+    constant_pool: &[ConstantInfo],
+    index: usize,
+    heap: &mut Heap,
+) -> Result<Value, RuntimeError> {
+    match &constant_pool[index] {
+        ConstantInfo::Integer(value) => Ok(Value::Int(*value)),
+        ConstantInfo::Float(value) => Ok(Value::Float(*value)),
+        ConstantInfo::Long(value) => Ok(Value::Long(*value)),
+        ConstantInfo::Double(value) => Ok(Value::Double(*value)),
+        ConstantInfo::String(value) => {
+            let value = constant_pool[*value as usize].as_utf8().unwrap();
+            Ok(Value::String(heap.intern(value)))
+        }
+        ConstantInfo::Class { name_index } => {
+            let name = constant_pool[*name_index as usize].as_utf8().unwrap();
+            Ok(Value::Reference(heap.class_object(name)))
+        }
+        constant => Err(RuntimeError::UnsupportedConstant(constant.tag_name())),
+    }
+}
+
+/// Pop the values taking the top `slots` slots of `operand_stack`, bottom
+/// first
+fn pop_slots(operand_stack: &mut Vec<Value>, slots: usize) -> Vec<Value> {
+    let mut values = vec![];
+    let mut popped = 0;
+    while popped < slots {
+        let value = operand_stack.pop().unwrap();
+        popped += value.slots();
+        values.insert(0, value);
+    }
+    values
+}
+
+/// Duplicate the values taking the top `top` slots of `operand_stack` and
+/// insert them below those taking the `below` slots under them, like the
+/// forms of `dup_x2`, `dup2_x1` and `dup2_x2` for values of either category
+fn dup_below(operand_stack: &mut Vec<Value>, top: usize, below: usize) {
+    let values = pop_slots(operand_stack, top);
+    let under = pop_slots(operand_stack, below);
+    operand_stack.extend(values.iter().cloned());
+    operand_stack.extend(under);
+    operand_stack.extend(values);
+}
+
+/// What executing an instruction with [`execute_instruction`] did
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// The frame continues with its next instruction
+    Next,
+    /// The frame continues at the instruction branched to, which is its `pc`
+    Branch,
+    /// The frame returns the value, which has been popped off its operand
+    /// stack, but the frame itself is still to be popped
+    Return(Option<Value>),
+    /// The instruction needs the rest of the VM, e.g. to load a class or
+    /// invoke a method, and nothing was done
+    Unhandled,
+}
+
+/// The instruction at `pc` of `code` as traces show it, or only its mnemonic
+/// if the VM can't decode it, which fails once it is fetched
+fn traced_instruction(code: &[u8], pc: usize) -> String {
+    match ByteCode::try_parse(pc, code) {
+        Some((_, bc)) => format!("{:?}", bc),
+        None => code
+            .get(pc)
+            .and_then(|&op| ByteCode::mnemonic(op))
+            .unwrap_or("<undecodable>")
+            .to_string(),
+    }
+}
+
+/// Execute the instruction `bc`, which has just been fetched from `frame`,
+/// if it only works on the frame and the heap
+///
+/// These are the constants, loads and stores of locals, the operand stack
+/// manipulations, arithmetic, branches and returns, so they can be run on
+/// a frame without a whole VM, e.g. to try out a single instruction.
+pub fn execute_instruction(
+    bc: ByteCode,
+    frame: &mut Frame,
+    heap: &mut Heap,
+) -> Result<Outcome, RuntimeError> {
+    match bc {
+        ByteCode::Return => return Ok(Outcome::Return(None)),
+        ByteCode::IReturn
+        | ByteCode::FReturn
+        | ByteCode::LReturn
+        | ByteCode::DReturn
+        | ByteCode::AReturn => {
+            let value = frame.operand_stack.pop().unwrap();
+            return Ok(Outcome::Return(Some(value)));
+        }
+        ByteCode::IConst(value) => {
+            frame.operand_stack.push(Value::Int(value));
+        }
+        ByteCode::Bipush(value) => {
+            frame.operand_stack.push(Value::Int(value as i32));
+        }
+        ByteCode::Sipush(value) => {
+            frame.operand_stack.push(Value::Int(value as i32));
+        }
+        ByteCode::IStore(index) => {
+            let value = as_int(frame.operand_stack.pop().unwrap())?;
+            frame.locals[index as usize] = Value::Int(value);
+        }
+        ByteCode::ILoad(index) => {
+            let value = as_int(frame.locals[index as usize].clone())?;
+            frame.operand_stack.push(Value::Int(value));
+        }
+        ByteCode::FConst(value) => {
+            frame.operand_stack.push(Value::Float(value));
+        }
+        ByteCode::FStore(index) => {
+            let value = as_float(frame.operand_stack.pop().unwrap())?;
+            frame.locals[index as usize] = Value::Float(value);
+        }
+        ByteCode::FLoad(index) => {
+            let value = as_float(frame.locals[index as usize].clone())?;
+            frame.operand_stack.push(Value::Float(value));
+        }
+        ByteCode::LConst(value) => {
+            frame.operand_stack.push(Value::Long(value));
+        }
+        ByteCode::DConst(value) => {
+            frame.operand_stack.push(Value::Double(value));
+        }
+        // the second local variable of a long or double is never read
+        ByteCode::LStore(index) => {
+            let value = as_long(frame.operand_stack.pop().unwrap())?;
+            frame.locals[index as usize] = Value::Long(value);
+        }
+        ByteCode::LLoad(index) => {
+            let value = as_long(frame.locals[index as usize].clone())?;
+            frame.operand_stack.push(Value::Long(value));
+        }
+        ByteCode::DStore(index) => {
+            let value = as_double(frame.operand_stack.pop().unwrap())?;
+            frame.locals[index as usize] = Value::Double(value);
+        }
+        ByteCode::DLoad(index) => {
+            let value = as_double(frame.locals[index as usize].clone())?;
+            frame.operand_stack.push(Value::Double(value));
+        }
+        ByteCode::I2L => {
+            let value = as_int(frame.operand_stack.pop().unwrap())?;
+            frame.operand_stack.push(Value::Long(value as i64));
+        }
+        ByteCode::L2I => {
+            let value = as_long(frame.operand_stack.pop().unwrap())?;
+            frame.operand_stack.push(Value::Int(value as i32));
+        }
+        ByteCode::LCmp => {
+            let value2 = as_long(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_long(frame.operand_stack.pop().unwrap())?;
+            let result = value1.cmp(&value2) as i32;
+            frame.operand_stack.push(Value::Int(result));
+        }
+        ByteCode::FCmp(nan) => {
+            let value2 = as_float(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_float(frame.operand_stack.pop().unwrap())?;
+            let result = value1
+                .partial_cmp(&value2)
+                .map_or(nan, |order| order as i32);
+            frame.operand_stack.push(Value::Int(result));
+        }
+        ByteCode::DCmp(nan) => {
+            let value2 = as_double(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_double(frame.operand_stack.pop().unwrap())?;
+            let result = value1
+                .partial_cmp(&value2)
+                .map_or(nan, |order| order as i32);
+            frame.operand_stack.push(Value::Int(result));
+        }
+        ByteCode::AConstNull => {
+            frame.operand_stack.push(Value::Null);
+        }
+        ByteCode::Pop => {
+            frame.operand_stack.pop().unwrap();
+        }
+        ByteCode::Dup => {
+            let value = frame.operand_stack.pop().unwrap();
+            frame.operand_stack.push(value.clone());
+            frame.operand_stack.push(value);
+        }
+        // a long or double is a single value taking both slots, so its
+        // category decides how many values there are
+        ByteCode::Pop2 => {
+            let value = frame.operand_stack.pop().unwrap();
+            if value.slots() == 1 {
+                frame.operand_stack.pop().unwrap();
+            }
+        }
+        ByteCode::Dup2 => {
+            let value1 = frame.operand_stack.pop().unwrap();
+            if value1.slots() == 2 {
+                frame.operand_stack.push(value1.clone());
+            } else {
+                let value2 = frame.operand_stack.pop().unwrap();
+                frame.operand_stack.push(value2.clone());
+                frame.operand_stack.push(value1.clone());
+                frame.operand_stack.push(value2);
+            }
+            frame.operand_stack.push(value1);
+        }
+        ByteCode::DupX1 => {
+            let value1 = frame.operand_stack.pop().unwrap();
+            let value2 = frame.operand_stack.pop().unwrap();
+            frame.operand_stack.push(value1.clone());
+            frame.operand_stack.push(value2);
+            frame.operand_stack.push(value1);
+        }
+        // which are used by compound assignments whose value is used, like
+        // `x = a[i] += 1L`, and may shuffle longs and doubles too
+        ByteCode::DupX2 => dup_below(&mut frame.operand_stack, 1, 2),
+        ByteCode::Dup2X1 => dup_below(&mut frame.operand_stack, 2, 1),
+        ByteCode::Dup2X2 => dup_below(&mut frame.operand_stack, 2, 2),
+        // arithmetic wraps around on overflow, and shifts only use the
+        // low 5 (or 6 for longs) bits of the distance
+        ByteCode::IAdd
+        | ByteCode::ISub
+        | ByteCode::IMul
+        | ByteCode::IShl
+        | ByteCode::IShr
+        | ByteCode::IUShr
+        | ByteCode::IAnd
+        | ByteCode::IOr
+        | ByteCode::IXor => {
+            let value2 = as_int(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_int(frame.operand_stack.pop().unwrap())?;
+            let result = match bc {
+                ByteCode::IAdd => value1.wrapping_add(value2),
+                ByteCode::ISub => value1.wrapping_sub(value2),
+                ByteCode::IMul => value1.wrapping_mul(value2),
+                ByteCode::IShl => value1.wrapping_shl(value2 as u32),
+                ByteCode::IShr => value1.wrapping_shr(value2 as u32),
+                ByteCode::IAnd => value1 & value2,
+                ByteCode::IOr => value1 | value2,
+                ByteCode::IXor => value1 ^ value2,
+                _ => (value1 as u32).wrapping_shr(value2 as u32) as i32,
+            };
+            frame.operand_stack.push(Value::Int(result));
+        }
+        ByteCode::INeg => {
+            let value = as_int(frame.operand_stack.pop().unwrap())?;
+            frame.operand_stack.push(Value::Int(value.wrapping_neg()));
+        }
+        // IEEE 754 arithmetic like Java's, where `%` truncates like fmod
+        ByteCode::FAdd | ByteCode::FSub | ByteCode::FMul | ByteCode::FDiv | ByteCode::FRem => {
+            let value2 = as_float(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_float(frame.operand_stack.pop().unwrap())?;
+            let result = match bc {
+                ByteCode::FAdd => value1 + value2,
+                ByteCode::FSub => value1 - value2,
+                ByteCode::FMul => value1 * value2,
+                ByteCode::FDiv => value1 / value2,
+                _ => value1 % value2,
+            };
+            frame.operand_stack.push(Value::Float(result));
+        }
+        ByteCode::FNeg => {
+            let value = as_float(frame.operand_stack.pop().unwrap())?;
+            frame.operand_stack.push(Value::Float(-value));
+        }
+        ByteCode::LAdd | ByteCode::LSub | ByteCode::LMul => {
+            let value2 = as_long(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_long(frame.operand_stack.pop().unwrap())?;
+            let result = match bc {
+                ByteCode::LAdd => value1.wrapping_add(value2),
+                ByteCode::LSub => value1.wrapping_sub(value2),
+                _ => value1.wrapping_mul(value2),
+            };
+            frame.operand_stack.push(Value::Long(result));
+        }
+        ByteCode::LShl | ByteCode::LShr | ByteCode::LUShr => {
+            let distance = as_int(frame.operand_stack.pop().unwrap())? as u32;
+            let value = as_long(frame.operand_stack.pop().unwrap())?;
+            let result = match bc {
+                ByteCode::LShl => value.wrapping_shl(distance),
+                ByteCode::LShr => value.wrapping_shr(distance),
+                _ => (value as u64).wrapping_shr(distance) as i64,
+            };
+            frame.operand_stack.push(Value::Long(result));
+        }
+        ByteCode::LNeg => {
+            let value = as_long(frame.operand_stack.pop().unwrap())?;
+            frame.operand_stack.push(Value::Long(value.wrapping_neg()));
+        }
+        ByteCode::IDiv | ByteCode::IRem => {
+            let value2 = as_int(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_int(frame.operand_stack.pop().unwrap())?;
+            if value2 == 0 {
+                return Err(RuntimeError::Arithmetic("/ by zero".to_string()));
+            }
+            // Integer.MIN_VALUE / -1 overflows back to Integer.MIN_VALUE
+            let result = match bc {
+                ByteCode::IDiv => value1.wrapping_div(value2),
+                _ => value1.wrapping_rem(value2),
+            };
+            frame.operand_stack.push(Value::Int(result));
+        }
+        ByteCode::If(comparison, offset) => {
+            let value = as_int(frame.operand_stack.pop().unwrap())?;
+            if comparison.test(value, 0) {
+                frame.branch(offset);
+                return Ok(Outcome::Branch);
+            }
+        }
+        ByteCode::IfICmp(comparison, offset) => {
+            let value2 = as_int(frame.operand_stack.pop().unwrap())?;
+            let value1 = as_int(frame.operand_stack.pop().unwrap())?;
+            if comparison.test(value1, value2) {
+                frame.branch(offset);
+                return Ok(Outcome::Branch);
+            }
+        }
+        ByteCode::IfACmp(comparison, offset) => {
+            let value2 = frame.operand_stack.pop().unwrap();
+            let value1 = frame.operand_stack.pop().unwrap();
+            let same = match (&value1, &value2) {
+                (Value::Reference(reference1), Value::Reference(reference2)) => {
+                    reference1 == reference2
+                }
+                (Value::Null, Value::Null) => true,
+                // strings aren't heap objects, but share their contents
+                // when they are the same instance
+                (Value::String(string1), Value::String(string2)) => Rc::ptr_eq(string1, string2),
+                _ => false,
+            };
+            if comparison.test(same as i32, 1) {
+                frame.branch(offset);
+                return Ok(Outcome::Branch);
+            }
+        }
+        ByteCode::Goto(offset) => {
+            frame.branch(offset);
+            return Ok(Outcome::Branch);
+        }
+        ByteCode::TableSwitch { .. } | ByteCode::LookupSwitch { .. } => {
+            let key = as_int(frame.operand_stack.pop().unwrap())?;
+            let offset = bc.switch_offset(&frame.code.code, key);
+            frame.branch(offset);
+            return Ok(Outcome::Branch);
+        }
+        ByteCode::IInc(index, constant) => {
+            let local = &mut frame.locals[index as usize];
+            *local = Value::Int(as_int(local.clone())?.wrapping_add(constant as i32));
+        }
+        ByteCode::ALoad(index) => {
+            let value = frame.locals[index as usize].clone();
+            if !value.is_reference() {
+                return Err(type_mismatch("reference", &value));
+            }
+            frame.operand_stack.push(value);
+        }
+        ByteCode::AStore(index) => {
+            let value = frame.operand_stack.pop().unwrap();
+            if !value.is_reference() {
+                return Err(type_mismatch("reference", &value));
+            }
+            frame.locals[index as usize] = value;
+        }
+        ByteCode::Ldc(index) => {
+            let value = load_constant(&frame.class.constant_pool, index as usize, heap)?;
+            frame.operand_stack.push(value);
+        }
+        ByteCode::LdcW(index) | ByteCode::Ldc2W(index) => {
+            let value = load_constant(&frame.class.constant_pool, index as usize, heap)?;
+            frame.operand_stack.push(value);
+        }
+        _ => return Ok(Outcome::Unhandled),
+    }
+    Ok(Outcome::Next)
+}
+
+/// Options controlling the behaviour of the VM
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Use allocation order ids instead of random identity hashes
+    pub deterministic_ids: bool,
+    /// Directories searched for the class files of classes loaded on demand
+    pub class_path: Vec<PathBuf>,
+    /// When to collect garbage
+    pub gc: GcMode,
+    /// Print every instruction with the operand stack before it to `System.err`
+    pub trace: bool,
+    /// Make `assert` statements check their condition, like `java -ea`
+    pub enable_assertions: bool,
+    /// The most objects which may be live at once, collecting garbage when
+    /// an allocation would exceed it and failing if that doesn't help
+    pub max_heap: Option<usize>,
+    /// Count the instructions executed by every method, see [`Vm::profile`]
+    pub profile: bool,
+    /// Check that final fields are only written once, by an initializer of
+    /// the class declaring them, which the VM otherwise trusts the compiler
+    /// to ensure
+    pub checked: bool,
+    /// Make `System.nanoTime` and `System.currentTimeMillis` read a clock
+    /// which starts at 0 and advances by [`Vm::FAKE_CLOCK_STEP`] on every
+    /// read instead of the time, for reproducible output
+    pub fake_clock: bool,
+    /// Print every garbage collection which freed objects to `System.err`,
+    /// unless a log is set with [`Vm::set_gc_log`]
+    pub verbose_gc: bool,
+    /// Reject every class using instructions the VM doesn't support when it
+    /// is loaded, listing all of them, instead of running it until it
+    /// reaches the first
+    pub strict_verify: bool,
+    /// Check that private, protected and package-private fields and methods
+    /// are only used by the classes allowed to, which the VM otherwise
+    /// trusts the compiler to ensure
+    pub strict_access: bool,
+    /// Stop before every instruction to read a command of the step debugger,
+    /// see [`Vm::set_step_input`]
+    pub step: bool,
+}
+
+/// The class file of the class named `name`, like `com/example/App`, in the
+/// first directory of `class_path` which has one
+pub fn find_class_file(class_path: &[PathBuf], name: &str) -> Option<PathBuf> {
+    class_path
+        .iter()
+        .map(|dir| dir.join(format!("{}.class", name)))
+        .find(|path| path.is_file())
+}
+
+/// The callback given to [`Vm::set_gc_log`]
+type GcLog<'a> = Box<dyn FnMut(&GcEvent) + 'a>;
+
+/// The state of the step debugger
+struct Stepper<'a> {
+    /// Where the commands are read from
+    input: Box<dyn BufRead + 'a>,
+    /// The states of the top frame before the instructions stepped through
+    history: History,
+    /// The depth of the stack the history was recorded at, as its snapshots
+    /// are only of the frame on top then
+    depth: usize,
+    /// Whether the program was let run without stopping again
+    running: bool,
+}
+
+/// Run the `main` method of `class`, writing the output of the program to `out`
+pub fn execute(class: ClassFile, options: Options, out: impl Write) -> Result<(), RuntimeError> {
+    Vm::with_output(class, options, out).run_main()
+}
+
+/// Report that an instruction expecting a value of type `expected` got `value`
+///
+/// With the `strict-assertions` feature this panics instead, to catch bugs
+/// of the VM itself right where they happen.
+fn type_mismatch(expected: &'static str, value: &Value) -> RuntimeError {
+    if cfg!(feature = "strict-assertions") {
+        panic!("expected {}, found {:?}", expected, value);
+    }
+    RuntimeError::TypeMismatch {
+        expected,
+        found: format!("{:?}", value),
+    }
+}
+
+/// Report that the method `method` of the program, named like `Foo.bar`,
+/// which the VM called for a value, returned without one
+fn missing_return_value(method: &str) -> RuntimeError {
+    RuntimeError::TypeMismatch {
+        expected: "a returned value",
+        found: format!("none from {}", method),
+    }
+}
+
+/// The int operand `value`, promoting the types the JVM computes with as ints
+///
+/// The VM only produces `Value::Int` for them, but callers of the API may
+/// pass a `Value::Char` and the like.
+fn as_int(value: Value) -> Result<i32, RuntimeError> {
+    match value {
+        Value::Int(value) => Ok(value),
+        Value::Boolean(value) => Ok(value as i32),
+        Value::Byte(value) => Ok(value as i32),
+        Value::Char(value) => Ok(value as i32),
+        Value::Short(value) => Ok(value as i32),
+        _ => Err(type_mismatch("int", &value)),
+    }
+}
+
+fn as_float(value: Value) -> Result<f32, RuntimeError> {
+    match value {
+        Value::Float(value) => Ok(value),
+        _ => Err(type_mismatch("float", &value)),
+    }
+}
+
+fn as_long(value: Value) -> Result<i64, RuntimeError> {
+    match value {
+        Value::Long(value) => Ok(value),
+        _ => Err(type_mismatch("long", &value)),
+    }
+}
+
+fn as_double(value: Value) -> Result<f64, RuntimeError> {
+    match value {
+        Value::Double(value) => Ok(value),
+        _ => Err(type_mismatch("double", &value)),
+    }
+}
+
+/// Narrow an int stored into a boolean, byte, char or short field or array
+/// element, whose type starts with `c`, to the values of that type
+fn narrow_int(c: u8, value: i32) -> i32 {
+    match c {
+        // only the lowest bit is stored into booleans
+        b'Z' => value & 1,
+        b'B' => value as i8 as i32,
+        b'C' => value as u16 as i32,
+        b'S' => value as i16 as i32,
+        _ => value,
+    }
+}
+
+/// Check that `value` can be stored in a field of type `descriptor`, so that
+/// a long or double is never mixed up with an int of the same slot, and
+/// narrow ints to the type
+fn field_value(descriptor: &str, value: Value) -> Result<Value, RuntimeError> {
+    let c = descriptor.as_bytes()[0];
+    let fits = match c {
+        b'B' | b'C' | b'I' | b'S' | b'Z' => matches!(value, Value::Int(_)),
+        b'J' => matches!(value, Value::Long(_)),
+        b'F' => matches!(value, Value::Float(_)),
+        b'D' => matches!(value, Value::Double(_)),
+        _ => value.is_reference(),
+    };
+    if !fits {
+        return Err(type_mismatch("a value of the field type", &value));
+    }
+    match value {
+        Value::Int(value) => Ok(Value::Int(narrow_int(c, value))),
+        value => Ok(value),
+    }
+}
+
+pub struct Vm<'a> {
+    classes: Vec<Rc<ClassFile>>,
+    heap: Heap,
+    /// The frames of all methods currently being executed, innermost last
+    stack: Vec<Frame>,
+    /// Where the program output of `System.out` goes
+    out: Box<dyn Write + 'a>,
+    /// Where the program output of `System.err` goes
+    err: Box<dyn Write + 'a>,
+    /// Where `System.in` reads from
+    input: Box<dyn BufRead + 'a>,
+    /// The input read from `input` which no `Scanner` has consumed yet
+    pending_input: String,
+    /// The PrintStream instances of the fields of `System`, by field name
+    streams: Vec<(String, Reference)>,
+    /// The `Thread` of the main thread, the only one there is
+    thread: Option<Reference>,
+    /// The boxes of the values in `INTEGER_CACHE` created so far
+    integer_cache: HashMap<i32, Reference>,
+    class_path: Vec<PathBuf>,
+    /// The methods invoked by call sites executed before, keyed by the code
+    /// containing the site and its address
+    ///
+    /// Classes are never unloaded and their code never changes, so a site
+    /// keeps resolving to the same method; failed resolutions aren't cached.
+    call_sites: HashMap<(*const CodeAttribute, usize), (Rc<ClassFile>, usize)>,
+    /// The keys of the fields accessed by the field instructions executed
+    /// before, keyed like `call_sites`
+    field_sites: HashMap<(*const CodeAttribute, usize), Rc<str>>,
+    /// The values of the static fields of initialized classes, by field key
+    statics: HashMap<String, Value>,
+    /// The classes whose `<clinit>` has been started
+    initialized: HashSet<String>,
+    gc: GcMode,
+    /// Called with every garbage collection which freed objects
+    gc_log: Option<GcLog<'a>>,
+    verbose_gc: bool,
+    max_heap: Option<usize>,
+    trace: bool,
+    /// Where every executed instruction is recorded, see [`Vm::set_trace_file`]
+    trace_file: Option<Box<dyn Write + 'a>>,
+    enable_assertions: bool,
+    strict_verify: bool,
+    strict_access: bool,
+    /// In checked mode, the final fields written so far, by the index of the
+    /// object for instance fields, and by field key
+    final_writes: Option<HashSet<(Option<usize>, String)>>,
+    /// When the VM was created, which `System.nanoTime` counts from
+    started: Instant,
+    /// With a fake clock, the nanoseconds it has advanced so far
+    fake_clock: Option<i64>,
+    /// The number of instructions executed so far
+    instructions: u64,
+    /// When profiling, the qualified name of every method executed and the
+    /// number of instructions it executed, keyed by its code
+    profile: Option<HashMap<*const CodeAttribute, (String, u64)>>,
+    stepper: Option<Stepper<'a>>,
+}
+
+impl<'a> Vm<'a> {
+    /// The nanoseconds a fake clock advances by on every read, one millisecond
+    pub const FAKE_CLOCK_STEP: i64 = 1_000_000;
+
+    /// The number of instructions the step debugger can step back by
+    pub const STEP_HISTORY: usize = 256;
+
+    pub fn new(class: ClassFile, options: Options) -> Self {
+        Self::with_output(class, options, io::stdout())
+    }
+
+    pub fn with_output(class: ClassFile, options: Options, out: impl Write + 'a) -> Self {
+        Self::with_streams(class, options, out, io::stderr())
+    }
+
+    /// Create a VM whose `System.out` writes to `out` and `System.err` to `err`
+    pub fn with_streams(
+        class: ClassFile,
+        options: Options,
+        out: impl Write + 'a,
+        err: impl Write + 'a,
+    ) -> Self {
+        Self {
+            classes: vec![Rc::new(class)],
+            heap: Heap::with_deterministic_ids(options.deterministic_ids),
+            stack: Vec::new(),
+            out: Box::new(out),
+            err: Box::new(err),
+            // not locked until read, so that several VMs can exist at once
+            input: Box::new(io::BufReader::new(io::stdin())),
+            pending_input: String::new(),
+            streams: Vec::new(),
+            thread: None,
+            integer_cache: HashMap::new(),
+            class_path: options.class_path,
+            call_sites: HashMap::new(),
+            field_sites: HashMap::new(),
+            statics: HashMap::new(),
+            initialized: HashSet::new(),
+            gc: options.gc,
+            gc_log: None,
+            verbose_gc: options.verbose_gc,
+            max_heap: options.max_heap,
+            trace: options.trace,
+            trace_file: None,
+            enable_assertions: options.enable_assertions,
+            strict_verify: options.strict_verify,
+            strict_access: options.strict_access,
+            final_writes: options.checked.then(HashSet::new),
+            started: Instant::now(),
+            fake_clock: options.fake_clock.then_some(0),
+            instructions: 0,
+            profile: options.profile.then(HashMap::new),
+            stepper: options.step.then(|| Stepper {
+                input: Box::new(io::BufReader::new(io::stdin())),
+                history: History::new(Self::STEP_HISTORY),
+                depth: 0,
+                running: false,
+            }),
+        }
+    }
+
+    /// Make `System.in` read from `input` instead of the standard input
+    pub fn set_input(&mut self, input: impl BufRead + 'a) {
+        self.input = Box::new(input);
+    }
+
+    /// With [`Options::step`], read the commands of the step debugger from
+    /// `input` instead of the standard input
+    ///
+    /// Before every instruction the debugger prints it like `--trace` does
+    /// and reads a line: nothing or `s` executes the instruction, `b` steps
+    /// back by restoring the operand stack and locals from before the last
+    /// one, and `c` runs the rest of the program without stopping, as does
+    /// the end of the input. Stepping back doesn't undo changes to the heap
+    /// or the static fields, and only goes back to where the method last
+    /// called another or got back from one.
+    pub fn set_step_input(&mut self, input: impl BufRead + 'a) {
+        if let Some(stepper) = &mut self.stepper {
+            stepper.input = Box::new(input);
+        }
+    }
+
+    /// Call `log` with every garbage collection which freed objects instead
+    /// of printing them, whether or not `verbose_gc` is set
+    pub fn set_gc_log(&mut self, log: impl FnMut(&GcEvent) + 'a) {
+        self.gc_log = Some(Box::new(log));
+    }
+
+    /// Record every instruction executed to `out`, one line each with the
+    /// method, the address, the instruction and the top of the operand stack
+    /// afterwards, separated by tabs
+    ///
+    /// Unlike `--trace` this is meant to be compared between runs, so it
+    /// only has values which are the same every time, with `-` for an empty
+    /// operand stack. After an invocation that is the stack of the new frame,
+    /// and after a return that of the caller.
+    pub fn set_trace_file(&mut self, out: impl Write + 'a) {
+        self.trace_file = Some(Box::new(out));
+    }
+
+    /// The number of instructions executed so far
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// The methods executed so far, like `Fib.fib`, and the number of
+    /// instructions each executed, the most first, if profiling is enabled
+    ///
+    /// Overloads of a method are counted separately but listed by the same
+    /// name.
+    pub fn profile(&self) -> Option<Vec<(String, u64)>> {
+        let mut methods = self.profile.as_ref()?.values().cloned().collect::<Vec<_>>();
+        methods.sort_by(|(name1, count1), (name2, count2)| {
+            count2.cmp(count1).then_with(|| name1.cmp(name2))
+        });
+        Some(methods)
+    }
+
+    /// Find the class named `name`, loading it from the class path if
+    /// it hasn't been loaded yet
+    fn load_class(&mut self, name: &str) -> Result<Rc<ClassFile>, RuntimeError> {
+        if let Some(class) = self.classes.iter().find(|c| c.name() == name) {
+            return Ok(class.clone());
+        }
+        let path = find_class_file(&self.class_path, name)
+            .ok_or_else(|| RuntimeError::NoClassDefFound(name.to_string()))?;
+        let class =
+            ClassFile::parse(&path.to_string_lossy()).map_err(|err| RuntimeError::ClassFormat {
+                class: name.to_string(),
+                message: format!("{:#}", err),
+            })?;
+        if self.strict_verify {
+            verifier::check_supported(&class)?;
+        }
+        let class = Rc::new(class);
+        self.classes.push(class.clone());
+        Ok(class)
+    }
+
+    /// Initialize the static fields of `class` to their `ConstantValue` or
+    /// default and run its `<clinit>`, after its superclass, unless that has
+    /// been done before
+    ///
+    /// A class being initialized counts as initialized, so a `<clinit>`
+    /// touching its own class (or a cycle of classes) doesn't recurse.
+    fn initialize(&mut self, class: &Rc<ClassFile>) -> Result<(), RuntimeError> {
+        if self.initialized.contains(class.name()) {
+            return Ok(());
+        }
+        self.initialized.insert(class.name().to_string());
+        // the superclass is initialized first, those of the standard library
+        // are modelled by the VM itself
+        if let Some(super_class) = class.super_class_name() {
+            if !super_class.starts_with("java/") {
+                let super_class = self.load_class(super_class)?;
+                self.initialize(&super_class)?;
+            }
+        }
+        let cp = &class.constant_pool;
+        for field in class.fields.iter().filter(|field| field.is_static()) {
+            let key = field_key(class.name(), field.name(cp));
+            let value = match field.constant_value(cp) {
+                Some(index) => {
+                    let value = load_constant(cp, index as usize, &mut self.heap)?;
+                    field_value(field.descriptor(cp), value)?
+                }
+                None => Value::default_for(field.descriptor(cp)),
+            };
+            self.statics.insert(key, value);
+        }
+        if let Some(method) = class.find_method_with_descriptor("<clinit>", "()V") {
+            let frame = Frame::new(class.clone(), method);
+            self.execute(frame)?;
+        }
+        Ok(())
+    }
+
+    /// Run the `main` method of the first loaded class
+    pub fn run_main(&mut self) -> Result<(), RuntimeError> {
+        let class = self.classes[0].clone();
+        let main_method = class
+            .find_main_method()
+            .ok_or_else(|| RuntimeError::MainMethodNotFound(class.name().to_string()))?;
+        if self.strict_verify {
+            verifier::check_supported(&class)?;
+        }
+        self.initialize(&class)?;
+        let frame = Frame::new(class.clone(), main_method);
+        self.run(frame).map(|_| ())
+    }
+
+    /// Invoke the static method `name` of the first loaded class with `args`,
+    /// returning the value it returns
+    ///
+    /// The first overload taking as many arguments as given is invoked.
+    /// Booleans and chars, which are ints inside the VM, are returned as
+    /// such again.
+    pub fn invoke_static(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let class = self.classes[0].clone();
+        let cp = &class.constant_pool;
+        let candidates = || {
+            class
+                .methods
+                .iter()
+                .filter(|method| method.name(cp) == name && method.is_static())
+        };
+        // like javac, a method taking exactly the arguments is preferred over
+        // one taking them as varargs
+        let method = candidates()
+            .find(|method| !method.is_varargs() && args_size(method.descriptor(cp)) == args.len())
+            .or_else(|| {
+                candidates().find(|method| {
+                    method.is_varargs() && args_size(method.descriptor(cp)) <= args.len() + 1
+                })
+            })
+            .ok_or_else(|| RuntimeError::NoSuchMethod {
+                class: class.name().to_string(),
+                name: name.to_string(),
+            })?;
+        let args = match method.is_varargs() {
+            true => self.pack_varargs(method.descriptor(cp), args)?,
+            false => args,
+        };
+        if self.strict_verify {
+            verifier::check_supported(&class)?;
+        }
+        self.initialize(&class)?;
+        let mut frame = Frame::new(class.clone(), method);
+        pass_arguments(&mut frame, method.descriptor(cp), true, args.into_iter());
+        let result = self.run(frame)?;
+        let descriptor = method.descriptor(cp);
+        Ok(match (result, &descriptor[descriptor.len() - 2..]) {
+            (Some(value), ")Z") => Some(Value::Boolean(bool::try_from(value)?)),
+            (Some(value), ")C") => {
+                let code = u32::try_from(i32::try_from(value)?).unwrap_or(0);
+                Some(Value::Char(char::from_u32(code).unwrap_or('\u{fffd}')))
+            }
+            (result, _) => result,
+        })
+    }
+
+    /// Execute `frame` as the outermost one and flush the output streams,
+    /// reporting an exception it doesn't catch like the JVM does
+    fn run(&mut self, frame: Frame) -> Result<Option<Value>, RuntimeError> {
+        let result = match self.execute(frame) {
+            Err(RuntimeError::Throw(exception)) => {
+                // which the exception may override, running more code
+                let message = match self.throwable_message(&exception)? {
+                    Value::Null => None,
+                    message => Some(self.stringify(message)?),
+                };
+                let exception = self.heap.get(&exception)?;
+                Err(RuntimeError::Uncaught {
+                    class: exception.class.replace('/', "."),
+                    message,
+                    stack_trace: exception.stack_trace.clone(),
+                })
+            }
+            result => result,
+        };
+        self.out.flush()?;
+        self.err.flush()?;
+        result
+    }
+
+    /// Execute `frame` on top of the current stack until it returns
+    ///
+    /// Methods invoked by the frame are run by the same loop, so this only
+    /// needs to be re-entered when the VM itself calls back into bytecode.
+    fn execute(&mut self, frame: Frame) -> Result<Option<Value>, RuntimeError> {
+        let depth = self.stack.len();
+        self.stack.push(frame);
+        let result = loop {
+            let position = self.trace_file.is_some().then(|| {
+                let frame = self.stack.last().unwrap();
+                format!(
+                    "{}.{}\t{}\t{}",
+                    frame.class.name(),
+                    frame.name,
+                    frame.pc,
+                    traced_instruction(&frame.code.code, frame.pc)
+                )
+            });
+            let step = self.step(depth);
+            if let Some(position) = position {
+                if let Err(err) = self.record_trace(&position) {
+                    break Err(err);
+                }
+            }
+            let result = match step {
+                Ok(Outcome::Next | Outcome::Branch) => Ok(()),
+                Ok(Outcome::Return(value)) => break Ok(value),
+                // which neither step nor execute_instruction knows
+                Ok(Outcome::Unhandled) => {
+                    let frame = self.stack.last().unwrap();
+                    Err(frame.unsupported_instruction(frame.current_pc))
+                }
+                Err(RuntimeError::Throw(exception)) => self.unwind(exception, depth),
+                Err(err) => match err.exception() {
+                    // raised by the VM on behalf of an instruction
+                    Some((class, message)) => {
+                        let exception = self.new_exception(class, message);
+                        self.unwind(exception, depth)
+                    }
+                    None => Err(err),
+                },
+            };
+            if let Err(err) = result {
+                break Err(err);
+            }
+        };
+        // frames left by an error would be taken for those of the caller by
+        // the next call into the VM
+        self.stack.truncate(depth);
+        result
+    }
+
+    /// Write the line of the instruction at `position` to the trace file
+    fn record_trace(&mut self, position: &str) -> Result<(), RuntimeError> {
+        let top = self
+            .stack
+            .last()
+            .and_then(|frame| frame.operand_stack.last())
+            .map_or_else(|| "-".to_string(), |value| self.heap.render(value));
+        let out = self.trace_file.as_mut().unwrap();
+        writeln!(out, "{}\t{}", position, top)?;
+        Ok(())
+    }
+
+    /// Collect the objects not reachable from the frames, the static fields
+    /// or the objects the VM holds on to, reporting them as collected in the
+    /// method `name` of `class`
+    fn collect_garbage(&mut self, class: &ClassFile, name: &str) -> Result<(), RuntimeError> {
+        let streams = self.streams.iter().map(|(_, reference)| reference);
+        let roots = streams
+            .chain(self.thread.iter())
+            .chain(self.integer_cache.values())
+            .cloned()
+            .chain(self.statics.values().filter_map(Value::as_reference));
+        let collected = self.heap.gc(&self.stack, roots);
+        if collected.is_empty() || self.gc_log.is_none() && !self.verbose_gc {
+            return Ok(());
+        }
+        let event = GcEvent {
+            collected,
+            method: format!("{}.{}", class.name(), name),
+        };
+        match &mut self.gc_log {
+            Some(log) => log(&event),
+            None => writeln!(self.err, "{}", event)?,
+        }
+        Ok(())
+    }
+
+    /// Make room for allocating an object by the program if a maximum heap
+    /// size is set and reached, collecting garbage first
+    fn reserve_object(&mut self) -> Result<(), RuntimeError> {
+        let Some(max_heap) = self.max_heap else {
+            return Ok(());
+        };
+        // nothing can be collected before the program runs, e.g. while
+        // packing the arguments of an invocation
+        if let Some(frame) = self.stack.last().filter(|_| self.heap.live() >= max_heap) {
+            let (class, name) = (frame.class.clone(), frame.name.clone());
+            self.collect_garbage(&class, &name)?;
+        }
+        if self.heap.live() >= max_heap {
+            return Err(RuntimeError::OutOfMemory);
+        }
+        Ok(())
+    }
+
+    /// The PrintStream or InputStream of the field `name` of `System`, which
+    /// is the same instance every time
+    fn system_stream(&mut self, name: &str) -> Result<Reference, RuntimeError> {
+        if let Some((_, reference)) = self.streams.iter().find(|(field, _)| field == name) {
+            return Ok(reference.clone());
+        }
+        let class = match name {
+            "out" | "err" => "java/io/PrintStream",
+            "in" => "java/io/InputStream",
+            _ => {
+                return Err(RuntimeError::NoSuchField {
+                    class: "java/lang/System".to_string(),
+                    name: name.to_string(),
+                })
+            }
+        };
+        let reference = self.heap.malloc_instance(class);
+        self.streams.push((name.to_string(), reference.clone()));
+        Ok(reference)
+    }
+
+    /// The `Thread` returned by `Thread.currentThread`, which is the same
+    /// instance every time as the VM runs only the main thread
+    fn current_thread(&mut self) -> Reference {
+        if let Some(thread) = &self.thread {
+            return thread.clone();
+        }
+        let thread = self.heap.malloc_instance("java/lang/Thread");
+        let name = Value::from("main".to_string());
+        let instance = self.heap.get_mut(&thread).expect("a new object is live");
+        instance.put_field(THREAD_NAME, name);
+        self.thread = Some(thread.clone());
+        thread
+    }
+
+    /// `Integer.valueOf(value)`, which boxes small values into the same
+    /// instance every time
+    fn box_int(&mut self, value: i32) -> Result<Reference, RuntimeError> {
+        if let Some(reference) = self.integer_cache.get(&value) {
+            return Ok(reference.clone());
+        }
+        self.reserve_object()?;
+        let reference = self.heap.malloc_instance("java/lang/Integer");
+        self.heap
+            .get_mut(&reference)?
+            .put_field(INTEGER_VALUE, Value::Int(value));
+        if INTEGER_CACHE.contains(&value) {
+            self.integer_cache.insert(value, reference.clone());
+        }
+        Ok(reference)
+    }
+
+    /// One of the static `Character` methods classifying or converting a
+    /// `char`, or an `int` code point, if `name` and `descriptor` are one of
+    /// them, returning whether it was
+    ///
+    /// Apart from the decimal digits, these follow the Unicode properties of
+    /// Rust's `char`, which agree with Java's for ASCII but may not for every
+    /// other character.
+    fn character_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        if !matches!(descriptor, "(C)Z" | "(I)Z" | "(C)C" | "(I)I" | "(C)I") {
+            return Ok(false);
+        }
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let code = as_int(operand_stack.last().unwrap().clone())?;
+        // surrogates aren't characters of their own, so nothing applies
+        let c = u32::try_from(code).ok().and_then(char::from_u32);
+        let result = match name {
+            "isDigit" => c.and_then(decimal_digit).is_some().into(),
+            "isLetter" | "isAlphabetic" => c.is_some_and(char::is_alphabetic).into(),
+            "isLetterOrDigit" => c.is_some_and(char::is_alphanumeric).into(),
+            "isUpperCase" => c.is_some_and(char::is_uppercase).into(),
+            "isLowerCase" => c.is_some_and(char::is_lowercase).into(),
+            // unlike Rust, Java doesn't count non-breaking spaces but does
+            // count the separator controls
+            "isWhitespace" => c
+                .is_some_and(|c| {
+                    c.is_whitespace() && !matches!(c, '\u{85}' | '\u{a0}' | '\u{2007}' | '\u{202f}')
+                        || ('\u{1c}'..='\u{1f}').contains(&c)
+                })
+                .into(),
+            // characters which would change into several are kept
+            "toUpperCase" | "toLowerCase" => {
+                let mut converted = match (c, name) {
+                    (Some(c), "toUpperCase") => c.to_uppercase().collect::<Vec<_>>(),
+                    (Some(c), _) => c.to_lowercase().collect::<Vec<_>>(),
+                    (None, _) => vec![],
+                };
+                match converted.pop() {
+                    Some(c) if converted.is_empty() => Value::Int(c as i32),
+                    _ => Value::Int(code),
+                }
+            }
+            // letters count as the digits of radix 36 like in Java
+            "getNumericValue" => match c.and_then(|c| decimal_digit(c).or(c.to_digit(36))) {
+                Some(digit) => Value::Int(digit as i32),
+                None => Value::Int(-1),
+            },
+            _ => return Ok(false),
+        };
+        *operand_stack.last_mut().unwrap() = result;
+        Ok(true)
+    }
+
+    /// `Objects.requireNonNull` with or without a message,
+    /// `requireNonNullElse`, `isNull` or `nonNull` if `name` and `descriptor`
+    /// are one of them, returning whether it was
+    fn objects_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let result = match (name, descriptor) {
+            ("requireNonNull", "(Ljava/lang/Object;)Ljava/lang/Object;") => {
+                match operand_stack.pop().unwrap() {
+                    Value::Null => None,
+                    value => Some(value),
+                }
+            }
+            ("requireNonNull", "(Ljava/lang/Object;Ljava/lang/String;)Ljava/lang/Object;") => {
+                let message = operand_stack.pop().unwrap();
+                match operand_stack.pop().unwrap() {
+                    Value::Null => {
+                        let message = match message {
+                            Value::String(message) => Some(message.to_string()),
+                            _ => None,
+                        };
+                        let exception =
+                            self.new_exception("java/lang/NullPointerException", message);
+                        return Err(RuntimeError::Throw(exception));
+                    }
+                    value => Some(value),
+                }
+            }
+            ("requireNonNullElse", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;") => {
+                let default = operand_stack.pop().unwrap();
+                match (operand_stack.pop().unwrap(), default) {
+                    (Value::Null, Value::Null) => {
+                        let message = Some("defaultObj".to_string());
+                        let exception =
+                            self.new_exception("java/lang/NullPointerException", message);
+                        return Err(RuntimeError::Throw(exception));
+                    }
+                    (Value::Null, default) => Some(default),
+                    (value, _) => Some(value),
+                }
+            }
+            ("isNull" | "nonNull", "(Ljava/lang/Object;)Z") => {
+                let is_null = matches!(operand_stack.pop().unwrap(), Value::Null);
+                Some(Value::from(is_null == (name == "isNull")))
+            }
+            _ => return Ok(false),
+        };
+        // the exception of requireNonNull(null) has no message
+        let Some(result) = result else {
+            let exception = self.new_exception("java/lang/NullPointerException", None);
+            return Err(RuntimeError::Throw(exception));
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        current_frame.operand_stack.push(result);
+        Ok(true)
+    }
+
+    /// `valueOf` of the wrapper class `class`, boxing `value`
+    fn box_value(&mut self, class: &str, value: Value) -> Result<Reference, RuntimeError> {
+        if class == "java/lang/Integer" {
+            return self.box_int(as_int(value)?);
+        }
+        self.reserve_object()?;
+        let reference = self.heap.malloc_instance(class);
+        self.heap
+            .get_mut(&reference)?
+            .put_field(&format!("{}.value", class), value);
+        Ok(reference)
+    }
+
+    /// The wrapper class of `value` and the value it boxes, if it is one
+    /// boxed by `box_value`
+    fn unbox(&self, value: &Value) -> Option<(&str, Value)> {
+        let Value::Reference(reference @ Reference::Object(_)) = value else {
+            return None;
+        };
+        let instance = self.heap.get(reference).ok()?;
+        boxed_type(&instance.class)?;
+        let value = instance.get_field(&format!("{}.value", instance.class))?;
+        Some((&instance.class, value.clone()))
+    }
+
+    /// Pack the arguments passed for the last parameter of the varargs method
+    /// `descriptor` into an array, like javac does for a call in the code
+    ///
+    /// Primitives are boxed into an array of references, e.g. the ints of
+    /// `join(1, 2, 3)` for `join(Object...)`.
+    fn pack_varargs(
+        &mut self,
+        descriptor: &str,
+        mut args: Vec<Value>,
+    ) -> Result<Vec<Value>, RuntimeError> {
+        let parameters = parameters(descriptor);
+        let class = parameters.last().filter(|class| class.starts_with('['));
+        let Some(class) = class.map(|class| class.to_string()) else {
+            return Ok(args);
+        };
+        let trailing = args.split_off(parameters.len() - 1);
+        let element = &class[1..];
+        let mut elements = vec![];
+        for value in trailing {
+            let value = match (element.as_bytes()[0], value) {
+                (b'L' | b'[', Value::Int(value)) => Value::Reference(self.box_int(value)?),
+                (b'L' | b'[', Value::Char(value)) => {
+                    let value = Value::Int(value as i32);
+                    Value::Reference(self.box_value("java/lang/Character", value)?)
+                }
+                (b'L' | b'[', value @ (Value::Long(_) | Value::Float(_) | Value::Double(_))) => {
+                    let class = match value {
+                        Value::Long(_) => "java/lang/Long",
+                        Value::Float(_) => "java/lang/Float",
+                        _ => "java/lang/Double",
+                    };
+                    Value::Reference(self.box_value(class, value)?)
+                }
+                // booleans and chars are ints inside arrays
+                (b'B' | b'C' | b'I' | b'S' | b'Z', value) => Value::Int(as_int(value)?),
+                (_, value) => value,
+            };
+            elements.push(field_value(element, value)?);
+        }
+        self.reserve_object()?;
+        args.push(Value::Reference(self.heap.malloc_array(&class, elements)));
+        Ok(args)
+    }
+
+    /// The value boxed by `value` if it is a `java.lang.Integer`
+    fn boxed_int(&self, value: &Value) -> Option<i32> {
+        match value {
+            Value::Reference(reference @ Reference::Object(_)) => {
+                match self.heap.get(reference).ok()?.get_field(INTEGER_VALUE) {
+                    Some(Value::Int(value)) => Some(*value),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// `Arrays.toString`, `Arrays.fill(array, value)` or `Arrays.sort` of a
+    /// primitive array if `name` and `descriptor` are one of them, returning
+    /// whether it was
+    fn arrays_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        // the element type of the array, which is the first parameter
+        let element = descriptor.as_bytes()[2];
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        match name {
+            "toString" if descriptor.ends_with(")Ljava/lang/String;") => {
+                // the array stays on the operand stack until its elements are
+                // converted, so that it and they stay reachable while their
+                // toString methods run and may collect garbage
+                let array = match operand_stack.last().unwrap() {
+                    Value::Null => {
+                        operand_stack.pop();
+                        operand_stack.push(Value::from("null".to_string()));
+                        return Ok(true);
+                    }
+                    value => value
+                        .as_reference()
+                        .ok_or_else(|| type_mismatch("array", value))?,
+                };
+                let elements = self.heap.get_array(&array).elements.clone();
+                let mut strings = vec![];
+                for value in elements {
+                    // booleans and chars are ints inside arrays
+                    strings.push(match element {
+                        b'Z' => bool::try_from(value)?.to_string(),
+                        b'C' => {
+                            let code = u32::try_from(i32::try_from(value)?).unwrap_or(0);
+                            char::from_u32(code).unwrap_or('\u{fffd}').to_string()
+                        }
+                        _ => self.stringify(value)?,
+                    });
+                }
+                let string = format!("[{}]", strings.join(", "));
+                let current_frame = self.stack.last_mut().unwrap();
+                current_frame.operand_stack.pop();
+                current_frame.operand_stack.push(Value::from(string));
+            }
+            // only the overloads filling the whole array
+            "fill"
+                if descriptor.len() == 6
+                    || descriptor == "([Ljava/lang/Object;Ljava/lang/Object;)V" =>
+            {
+                let value = operand_stack.pop().unwrap();
+                let array = pop_array(operand_stack)?;
+                if value.is_reference() && !matches!(value, Value::Null) {
+                    let class = self.runtime_class(&value)?.unwrap();
+                    let array_class = self.heap.get_array(&array).class.clone();
+                    if !self.is_assignable(&class, element_class(&array_class))? {
+                        return Err(RuntimeError::ArrayStore(class.replace('/', ".")));
+                    }
+                }
+                let array = self.heap.get_array_mut(&array);
+                array.elements.fill(value);
+            }
+            "sort" if descriptor.len() == 5 && element != b'Z' => {
+                let array = pop_array(operand_stack)?;
+                let elements = &mut self.heap.get_array_mut(&array).elements;
+                // like Float.compare and Double.compare, -0.0 sorts before
+                // 0.0 and NaN after everything else
+                elements.sort_by(|value1, value2| match (value1, value2) {
+                    (Value::Int(value1), Value::Int(value2)) => value1.cmp(value2),
+                    (Value::Long(value1), Value::Long(value2)) => value1.cmp(value2),
+                    (Value::Float(value1), Value::Float(value2)) => value1.total_cmp(value2),
+                    (Value::Double(value1), Value::Double(value2)) => value1.total_cmp(value2),
+                    _ => std::cmp::Ordering::Equal,
+                });
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// `System.arraycopy(src, srcPos, dest, destPos, length)`, which copies
+    /// through a temporary so that overlapping ranges of one array work
+    fn arraycopy(&mut self) -> Result<(), RuntimeError> {
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let length = as_int(operand_stack.pop().unwrap())?;
+        let dest_pos = as_int(operand_stack.pop().unwrap())?;
+        let dest = operand_stack.pop().unwrap();
+        let src_pos = as_int(operand_stack.pop().unwrap())?;
+        let src = operand_stack.pop().unwrap();
+        let heap = &self.heap;
+        let array = |value: &Value, role: &str| match value {
+            Value::Reference(reference @ Reference::Array(_)) => Ok(reference.clone()),
+            Value::Null => Err(RuntimeError::NullPointer(format!(
+                "arraycopy: {} is null",
+                role
+            ))),
+            value => {
+                let class = match value {
+                    Value::Reference(reference) => heap.class_of(reference)?,
+                    Value::String(_) => "java/lang/String",
+                    _ => return Err(type_mismatch("array", value)),
+                };
+                Err(RuntimeError::ArrayStore(format!(
+                    "arraycopy: {} type {} is not an array",
+                    role,
+                    class.replace('/', ".")
+                )))
+            }
+        };
+        let (src, dest) = (array(&src, "source")?, array(&dest, "destination")?);
+        let (src, dest_array) = (self.heap.get_array(&src), self.heap.get_array(&dest));
+        // arrays of references of different classes are copied checking
+        // every element
+        let checked = src.class != dest_array.class;
+        if checked
+            && !(has_reference_elements(&src.class) && has_reference_elements(&dest_array.class))
+        {
+            return Err(RuntimeError::ArrayStore(format!(
+                "arraycopy: type mismatch: can not copy {}[] into {}[]",
+                src.element_name(),
+                dest_array.element_name()
+            )));
+        }
+        let out_of_bounds = |message: String| Err(RuntimeError::ArrayIndexOutOfBounds(message));
+        if src_pos < 0 {
+            return out_of_bounds(format!(
+                "arraycopy: source index {} out of bounds for {}",
+                src_pos,
+                src.describe()
+            ));
+        }
+        if dest_pos < 0 {
+            return out_of_bounds(format!(
+                "arraycopy: destination index {} out of bounds for {}",
+                dest_pos,
+                dest_array.describe()
+            ));
+        }
+        if length < 0 {
+            return out_of_bounds(format!("arraycopy: length {} is negative", length));
+        }
+        let (src_pos, dest_pos, length) = (src_pos as usize, dest_pos as usize, length as usize);
+        if src_pos + length > src.elements.len() {
+            return out_of_bounds(format!(
+                "arraycopy: last source index {} out of bounds for {}",
+                src_pos + length,
+                src.describe()
+            ));
+        }
+        if dest_pos + length > dest_array.elements.len() {
+            return out_of_bounds(format!(
+                "arraycopy: last destination index {} out of bounds for {}",
+                dest_pos + length,
+                dest_array.describe()
+            ));
+        }
+        let elements = src.elements[src_pos..src_pos + length].to_vec();
+        if !checked {
+            self.heap.get_array_mut(&dest).elements[dest_pos..dest_pos + length]
+                .clone_from_slice(&elements);
+            return Ok(());
+        }
+        let (src_class, dest_class) = (src.class.clone(), dest_array.class.clone());
+        let target = element_class(&dest_class);
+        // the elements before the first one which can't be stored are copied
+        for (offset, element) in elements.into_iter().enumerate() {
+            if let Some(class) = self.runtime_class(&element)? {
+                if !self.is_assignable(&class, target)? {
+                    return Err(RuntimeError::ArrayStore(format!(
+                        "arraycopy: element type mismatch: can not cast one of the elements \
+                         of {} to the type of the destination array, {}",
+                        java_type_name(&src_class),
+                        java_type_name(target)
+                    )));
+                }
+            }
+            self.heap.get_array_mut(&dest).elements[dest_pos + offset] = element;
+        }
+        Ok(())
+    }
+
+    /// Resume at the innermost handler of `exception`, discarding the frames
+    /// above it but none below `depth`
+    fn unwind(&mut self, exception: Reference, depth: usize) -> Result<(), RuntimeError> {
+        let exception_class = self.heap.get(&exception)?.class.clone();
+        while self.stack.len() > depth {
+            if let Some(handler_pc) = self.find_handler(&exception_class)? {
+                let frame = self.stack.last_mut().unwrap();
+                frame.operand_stack.clear();
+                frame.operand_stack.push(Value::Reference(exception));
+                frame.pc = handler_pc;
+                return Ok(());
+            }
+            let frame = self.stack.pop().unwrap();
+            if let Some(reference) = &frame.monitor {
+                self.heap.monitor_exit(reference)?;
+            }
+        }
+        Err(RuntimeError::Throw(exception))
+    }
+
+    /// The address of the handler of the top frame for an exception of class
+    /// `exception_class`, thrown by the instruction being executed
+    ///
+    /// The first matching entry of the exception table is used. Compilers
+    /// list the handlers of nested try blocks before the enclosing ones, so
+    /// this is the innermost handler.
+    fn find_handler(&mut self, exception_class: &str) -> Result<Option<usize>, RuntimeError> {
+        let frame = self.stack.last().unwrap();
+        let (class, code, pc) = (frame.class.clone(), frame.code.clone(), frame.current_pc);
+        for entry in &code.exception_table {
+            if !(entry.start_pc as usize..entry.end_pc as usize).contains(&pc) {
+                continue;
+            }
+            // a catch type of 0 is used by finally blocks and catches anything
+            let catches = entry.catch_type == 0 || {
+                let cp = &class.constant_pool;
+                let name_index = cp[entry.catch_type as usize].as_class().unwrap();
+                let catch_type = cp[name_index as usize].as_utf8().unwrap();
+                self.is_subclass_of(exception_class, catch_type)?
+            };
+            if catches {
+                return Ok(Some(entry.handler_pc as usize));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The key of the field `field` refers to, which is either declared by
+    /// the class it is referenced through or inherited from a superclass
+    fn resolve_field(
+        &mut self,
+        field: &MemberRef,
+        is_static: bool,
+    ) -> Result<String, RuntimeError> {
+        let mut current = field.class.clone();
+        while !current.starts_with("java/") {
+            let declaring = self.load_class(&current)?;
+            let access = declaring
+                .find_field_with_descriptor(&field.name, &field.descriptor)
+                .filter(|field| field.is_static() == is_static)
+                .map(FieldInfo::access);
+            if let Some(access) = access {
+                self.check_access(&declaring, access, "field", &field.name)?;
+                return Ok(field_key(&current, &field.name));
+            }
+            match declaring.super_class_name() {
+                Some(super_class) => current = super_class.to_string(),
+                None => break,
+            }
+        }
+        Err(RuntimeError::NoSuchField {
+            class: field.class.clone(),
+            name: field.name.clone(),
+        })
+    }
+
+    /// The key of the field accessed by the instruction being executed, which
+    /// refers to `field`, resolving it only the first time the site executes
+    fn resolve_field_site(
+        &mut self,
+        field: &MemberRef,
+        is_static: bool,
+    ) -> Result<Rc<str>, RuntimeError> {
+        let frame = self.stack.last().unwrap();
+        let site = (Rc::as_ptr(&frame.code), frame.current_pc);
+        if let Some(key) = self.field_sites.get(&site) {
+            return Ok(key.clone());
+        }
+        let key = Rc::<str>::from(self.resolve_field(field, is_static)?);
+        self.field_sites.insert(site, key.clone());
+        Ok(key)
+    }
+
+    /// In strict access mode, check that the method being executed may use
+    /// the `kind` ("field" or "method") `member` of the class `declaring`,
+    /// which has the `access`
+    ///
+    /// Private members may only be used by the classes of the same nest,
+    /// package-private ones by those of the same package, and protected ones
+    /// also by subclasses.
+    fn check_access(
+        &mut self,
+        declaring: &ClassFile,
+        access: Access,
+        kind: &str,
+        member: &str,
+    ) -> Result<(), RuntimeError> {
+        if !self.strict_access {
+            return Ok(());
+        }
+        let accessor = self.stack.last().unwrap().class.clone();
+        let same_package = package_name(accessor.name()) == package_name(declaring.name());
+        let allowed = match access {
+            Access::Public => true,
+            Access::Protected => {
+                same_package || self.is_subclass_of(accessor.name(), declaring.name())?
+            }
+            Access::Package => same_package,
+            Access::Private => accessor.nest_host() == declaring.nest_host(),
+        };
+        if allowed {
+            return Ok(());
+        }
+        Err(RuntimeError::IllegalAccess(format!(
+            "class {} tried to access {} {} {}.{}",
+            accessor.name().replace('/', "."),
+            access.name(),
+            kind,
+            declaring.name().replace('/', "."),
+            member
+        )))
+    }
+
+    /// The nanoseconds since some fixed point for `System.nanoTime`, or since
+    /// the epoch if `wall_clock` for `System.currentTimeMillis`, which a fake
+    /// clock counts from its start for both
+    fn time(&mut self, wall_clock: bool) -> i64 {
+        if let Some(clock) = &mut self.fake_clock {
+            *clock += Self::FAKE_CLOCK_STEP;
+            return *clock;
+        }
+        let elapsed = if wall_clock {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+        } else {
+            self.started.elapsed()
+        };
+        elapsed.as_nanos() as i64
+    }
+
+    /// In checked mode, record the write of `field` of the object at
+    /// `instance`, or of a static field, whose key is `key`
+    ///
+    /// A final field may only be written once, by `<init>` or `<clinit>` of
+    /// the class declaring it.
+    fn check_final_write(
+        &mut self,
+        field: &MemberRef,
+        key: &str,
+        instance: Option<usize>,
+    ) -> Result<(), RuntimeError> {
+        if self.final_writes.is_none() {
+            return Ok(());
+        }
+        let (declaring, name) = key.rsplit_once('.').unwrap();
+        let declaring_class = self.load_class(declaring)?;
+        let is_final = declaring_class
+            .find_field_with_descriptor(name, &field.descriptor)
+            .is_some_and(FieldInfo::is_final);
+        if !is_final {
+            return Ok(());
+        }
+        let (kind, initializer) = match instance {
+            Some(_) => ("non-static", "<init>"),
+            None => ("static", "<clinit>"),
+        };
+        let frame = self.stack.last().unwrap();
+        if frame.class.name() != declaring || frame.name != initializer {
+            return Err(RuntimeError::IllegalAccess(format!(
+                "Update to {} final field {} attempted from a different method ({}) than the \
+                 initializer method {}",
+                kind,
+                key.replace('/', "."),
+                frame.name,
+                initializer
+            )));
+        }
+        let final_writes = self.final_writes.as_mut().unwrap();
+        if !final_writes.insert((instance, key.to_string())) {
+            return Err(RuntimeError::IllegalAccess(format!(
+                "Update to {} final field {} attempted twice",
+                kind,
+                key.replace('/', ".")
+            )));
+        }
+        Ok(())
+    }
+
+    /// The key of the static field `field` refers to, after initializing the
+    /// class declaring it
+    fn static_field(&mut self, field: &MemberRef) -> Result<Rc<str>, RuntimeError> {
+        let key = self.resolve_field_site(field, true)?;
+        let (declaring, _) = key.rsplit_once('.').unwrap();
+        let declaring = self.load_class(declaring)?;
+        self.initialize(&declaring)?;
+        Ok(key)
+    }
+
+    /// Whether the class `name` is `target` or a subclass of it
+    fn is_subclass_of(&mut self, name: &str, target: &str) -> Result<bool, RuntimeError> {
+        let mut name = name.to_string();
+        loop {
+            if name == target {
+                return Ok(true);
+            }
+            let super_class = if name.starts_with("java/") {
+                java_super_class(&name).map(str::to_string)
+            } else {
+                let class = self.load_class(&name)?;
+                class.super_class_name().map(str::to_string)
+            };
+            match super_class {
+                Some(super_class) => name = super_class,
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Whether a value of the class or array class `class` can be assigned
+    /// to a variable of type `target`
+    fn is_assignable(&mut self, class: &str, target: &str) -> Result<bool, RuntimeError> {
+        if class == target || target == "java/lang/Object" {
+            return Ok(true);
+        }
+        match (class.starts_with('['), target.starts_with('[')) {
+            // arrays of references are covariant, those of primitives aren't
+            (true, true) => {
+                if !has_reference_elements(class) || !has_reference_elements(target) {
+                    return Ok(false);
+                }
+                self.is_assignable(element_class(class), element_class(target))
+            }
+            (true, false) => Ok(matches!(
+                target,
+                "java/lang/Cloneable" | "java/io/Serializable"
+            )),
+            (false, true) => Ok(false),
+            (false, false) => {
+                Ok(self.is_subclass_of(class, target)? || self.implements(class, target)?)
+            }
+        }
+    }
+
+    /// Whether the class `name` or one of its superclasses implements the
+    /// `interface`, directly or through the interfaces it implements
+    ///
+    /// The interfaces of the standard library aren't known, so they are only
+    /// found when a user class names them.
+    fn implements(&mut self, name: &str, interface: &str) -> Result<bool, RuntimeError> {
+        let mut pending = vec![name.to_string()];
+        while let Some(name) = pending.pop() {
+            if name.starts_with("java/") {
+                continue;
+            }
+            let class = self.load_class(&name)?;
+            for implemented in class.interface_names() {
+                if implemented == interface {
+                    return Ok(true);
+                }
+                pending.push(implemented.to_string());
+            }
+            // an interface has java/lang/Object as its superclass
+            if let Some(super_class) = class.super_class_name() {
+                pending.push(super_class.to_string());
+            }
+        }
+        Ok(false)
+    }
+
+    /// The name of the class of the object `value` refers to, `None` for null
+    /// and primitives
+    fn runtime_class(&self, value: &Value) -> Result<Option<String>, RuntimeError> {
+        Ok(match value {
+            Value::String(_) => Some("java/lang/String".to_string()),
+            Value::Reference(reference) => Some(self.heap.class_of(reference)?.to_string()),
+            _ => None,
+        })
+    }
+
+    /// `String.equals` or `intern` if `name` and `descriptor` are one of them,
+    /// returning whether it was
+    ///
+    /// Unlike `==`, which compares the identity of strings, `equals` compares
+    /// their contents, and `intern` gives the string of the same contents
+    /// which literals are.
+    fn string_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        if !matches!(
+            (name, descriptor),
+            ("equals", "(Ljava/lang/Object;)Z") | ("intern", "()Ljava/lang/String;")
+        ) {
+            return Ok(false);
+        }
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let other = match name {
+            "equals" => operand_stack.pop(),
+            _ => None,
+        };
+        let Value::String(string) = operand_stack.pop().unwrap() else {
+            return Err(RuntimeError::NullPointer(format!(
+                "cannot invoke {} on null",
+                name
+            )));
+        };
+        let result = match other {
+            Some(Value::String(other)) => Value::from(*other == *string),
+            Some(_) => Value::from(false),
+            None => Value::String(self.heap.intern(&string)),
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        current_frame.operand_stack.push(result);
+        Ok(true)
+    }
+
+    /// Run `new String()`, `new String(String)` or `new String(char[])` if
+    /// the constructor has the `descriptor` of one of them, returning whether
+    /// it has
+    ///
+    /// Strings aren't objects on the heap, so the copies of the uninitialized
+    /// object on the operand stack are replaced by the string, which is never
+    /// the same as another string, like the interned one of a literal.
+    fn init_string(&mut self, descriptor: &str) -> Result<bool, RuntimeError> {
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let contents = match descriptor {
+            "()V" => String::new(),
+            "(Ljava/lang/String;)V" => match operand_stack.pop().unwrap() {
+                Value::String(string) => string.to_string(),
+                _ => {
+                    return Err(RuntimeError::NullPointer(
+                        "cannot copy the contents of null".to_string(),
+                    ))
+                }
+            },
+            "([C)V" => {
+                let chars = operand_stack.pop().unwrap();
+                self.string_value_of("[C", chars)?
+            }
+            _ => return Ok(false),
+        };
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let uninitialized = operand_stack.pop().unwrap();
+        let string = Value::String(Rc::from(contents));
+        for value in operand_stack.iter_mut() {
+            if let (Value::Reference(value_ref), Value::Reference(uninitialized)) =
+                (&*value, &uninitialized)
+            {
+                if value_ref == uninitialized {
+                    *value = string.clone();
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Run the constructor of a Throwable of the standard library, which
+    /// records its message and the methods currently active
+    fn init_throwable(&mut self, class: &str, descriptor: &str) -> Result<(), RuntimeError> {
+        let current_frame = self.stack.last_mut().unwrap();
+        let (message, cause) = match descriptor {
+            "()V" => (Value::Null, Value::Null),
+            "(Ljava/lang/String;)V" => (current_frame.operand_stack.pop().unwrap(), Value::Null),
+            "(Ljava/lang/String;Ljava/lang/Throwable;)V" => {
+                let cause = current_frame.operand_stack.pop().unwrap();
+                (current_frame.operand_stack.pop().unwrap(), cause)
+            }
+            // the message is that of the cause, like `Exception(Throwable)`
+            "(Ljava/lang/Throwable;)V" => match current_frame.operand_stack.pop().unwrap() {
+                Value::Null => (Value::Null, Value::Null),
+                cause => (Value::from(self.stringify(cause.clone())?), cause),
+            },
+            // AssertionError(Object), the detail message of `assert x : value`
+            "(Ljava/lang/Object;)V" => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                (Value::from(self.stringify(value)?), Value::Null)
+            }
+            // the protected constructor also taking whether the exception
+            // can be suppressed and has a writable stack trace, which don't
+            // matter to the VM
+            "(Ljava/lang/String;Ljava/lang/Throwable;ZZ)V" => {
+                pop_slots(&mut current_frame.operand_stack, 2);
+                let cause = current_frame.operand_stack.pop().unwrap();
+                (current_frame.operand_stack.pop().unwrap(), cause)
+            }
+            _ => {
+                return Err(RuntimeError::UnsupportedMethod {
+                    class: class.to_string(),
+                    name: "<init>".to_string(),
+                    descriptor: descriptor.to_string(),
+                })
+            }
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        let value = current_frame.operand_stack.pop().unwrap();
+        let reference = value
+            .as_reference()
+            .ok_or_else(|| type_mismatch("reference", &value))?;
+        let stack_trace = self.stack_trace(&reference);
+        let exception = self.heap.get_mut(&reference)?;
+        exception.put_field(THROWABLE_MESSAGE, message);
+        exception.put_field(THROWABLE_CAUSE, cause);
+        exception.stack_trace = stack_trace;
+        Ok(())
+    }
+
+    /// Invoke `getMessage`, `getLocalizedMessage`, `getCause` or `toString`
+    /// of `Throwable` if `name` and `descriptor` are one of them, returning
+    /// whether it was
+    ///
+    /// The class of the exception itself may override the method, which is
+    /// invoked then, so exceptions of the program only have to declare
+    /// their constructors.
+    fn throwable_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        if !matches!(
+            (name, descriptor),
+            ("getMessage" | "getLocalizedMessage", "()Ljava/lang/String;")
+                | ("getCause", "()Ljava/lang/Throwable;")
+                | ("toString", "()Ljava/lang/String;")
+        ) {
+            return Ok(false);
+        }
+        let current_frame = self.stack.last_mut().unwrap();
+        let value = current_frame.operand_stack.pop().unwrap();
+        let reference = value
+            .as_reference()
+            .ok_or_else(|| RuntimeError::NullPointer(format!("cannot invoke {} on null", name)))?;
+        let class_name = self.heap.get(&reference)?.class.clone();
+        // getLocalizedMessage returns getMessage unless overridden itself
+        let mut names = vec![name];
+        if name == "getLocalizedMessage" {
+            names.push("getMessage");
+        }
+        for name in names {
+            if let Some((class, index)) = self.find_override(&class_name, name, descriptor)? {
+                let method = &class.methods[index];
+                let mut frame = Frame::new(class.clone(), method);
+                frame.locals[0] = value;
+                self.enter_method_monitor(&mut frame, method)?;
+                self.stack.push(frame);
+                return Ok(true);
+            }
+        }
+        let result = match name {
+            "getCause" => {
+                let exception = self.heap.get(&reference)?;
+                exception.get_field(THROWABLE_CAUSE).cloned()
+            }
+            "toString" => Some(Value::from(self.stringify(value)?)),
+            _ => {
+                let exception = self.heap.get(&reference)?;
+                exception.get_field(THROWABLE_MESSAGE).cloned()
+            }
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        current_frame
+            .operand_stack
+            .push(result.unwrap_or(Value::Null));
+        Ok(true)
+    }
+
+    /// The message `Throwable.toString` shows for `exception`, which is
+    /// `getLocalizedMessage` and so `getMessage`, unless the class of the
+    /// exception overrides them
+    fn throwable_message(&mut self, exception: &Reference) -> Result<Value, RuntimeError> {
+        let class_name = self.heap.get(exception)?.class.clone();
+        for name in ["getLocalizedMessage", "getMessage"] {
+            let descriptor = "()Ljava/lang/String;";
+            if let Some((class, index)) = self.find_override(&class_name, name, descriptor)? {
+                let method = &class.methods[index];
+                let mut frame = Frame::new(class.clone(), method);
+                frame.locals[0] = Value::Reference(exception.clone());
+                self.enter_method_monitor(&mut frame, method)?;
+                return Ok(self.execute(frame)?.unwrap_or(Value::Null));
+            }
+        }
+        let instance = self.heap.get(exception)?;
+        Ok(instance
+            .get_field(THROWABLE_MESSAGE)
+            .cloned()
+            .unwrap_or(Value::Null))
+    }
+
+    /// The class and index of the method `name` of the `descriptor` declared
+    /// by the class `class_name` or the closest of its superclasses, up to
+    /// those of the standard library which are modelled by the VM itself
+    fn find_override(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<(Rc<ClassFile>, usize)>, RuntimeError> {
+        let mut next = Some(class_name.to_string());
+        while let Some(class_name) = next.filter(|name| !name.starts_with("java/")) {
+            let class = self.load_class(&class_name)?;
+            let cp = &class.constant_pool;
+            let index = class
+                .methods
+                .iter()
+                .position(|method| method.name(cp) == name && method.descriptor(cp) == descriptor);
+            if let Some(index) = index {
+                return Ok(Some((class, index)));
+            }
+            next = class.super_class_name().map(str::to_string);
+        }
+        Ok(None)
+    }
+
+    /// The interface and index of the `default` method `name` of the
+    /// `descriptor` which the class `class_name` inherits, when neither it nor
+    /// its superclasses declare it
+    ///
+    /// The interfaces of the class and its superclasses are searched before
+    /// their superinterfaces, so the first one found is the most specific.
+    fn find_default_method(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<(Rc<ClassFile>, usize)>, RuntimeError> {
+        let mut pending = VecDeque::new();
+        let mut next = Some(class_name.to_string());
+        while let Some(class_name) = next.filter(|name| !name.starts_with("java/")) {
+            let class = self.load_class(&class_name)?;
+            pending.extend(class.interface_names().into_iter().map(str::to_string));
+            next = class.super_class_name().map(str::to_string);
+        }
+        let mut visited = HashSet::new();
+        while let Some(interface) = pending.pop_front() {
+            if interface.starts_with("java/") || !visited.insert(interface.clone()) {
+                continue;
+            }
+            let class = self.load_class(&interface)?;
+            let cp = &class.constant_pool;
+            // abstract methods have no code, and static ones aren't inherited
+            let index = class.methods.iter().position(|method| {
+                method.name(cp) == name
+                    && method.descriptor(cp) == descriptor
+                    && !method.is_static()
+                    && method.has_code(cp)
+            });
+            if let Some(index) = index {
+                return Ok(Some((class, index)));
+            }
+            pending.extend(class.interface_names().into_iter().map(str::to_string));
+        }
+        Ok(None)
+    }
+
+    /// Create an exception like the JVM does when an instruction fails, with
+    /// the stack trace of the current frames
+    fn new_exception(&mut self, class: &str, message: Option<String>) -> Reference {
+        let reference = self.heap.malloc_instance(class);
+        let stack_trace = self.stack_trace(&reference);
+        let exception = self.heap.get_mut(&reference).expect("a new object is live");
+        exception.put_field(THROWABLE_MESSAGE, message.map_or(Value::Null, Value::from));
+        exception.stack_trace = stack_trace;
+        reference
+    }
+
+    /// The active methods, innermost first, leaving out the constructors of
+    /// the Throwable `exception` like `Throwable.fillInStackTrace` does
+    fn stack_trace(&self, exception: &Reference) -> Vec<String> {
+        self.stack
+            .iter()
+            .rev()
+            .skip_while(|frame| {
+                frame.name == "<init>"
+                    && frame.locals.first().and_then(Value::as_reference).as_ref()
+                        == Some(exception)
+            })
+            .map(|frame| {
+                let class = &frame.class;
+                let line = frame
+                    .code
+                    .line_number(frame.current_pc, &class.constant_pool);
+                let source = match (class.source_file(), line) {
+                    (Some(file), Some(line)) => format!("{}:{}", file, line),
+                    (Some(file), None) => file.to_string(),
+                    (None, _) => "Unknown Source".to_string(),
+                };
+                format!(
+                    "{}.{}({})",
+                    class.name().replace('/', "."),
+                    frame.name,
+                    source
+                )
+            })
+            .collect()
+    }
+
+    /// Execute the next instruction of the top frame
+    ///
+    /// The outcome is `Return` only once the frame `execute` was called with
+    /// has returned and been popped, and `Next` for the returns of the frames
+    /// above it.
+    fn step(&mut self, depth: usize) -> Result<Outcome, RuntimeError> {
+        if self
+            .stepper
+            .as_ref()
+            .is_some_and(|stepper| !stepper.running)
+        {
+            self.read_step_commands()?;
+        }
+        if self.trace {
+            let line = self.trace_line();
+            writeln!(self.err, "{}", line)?;
+        }
+        let current_frame = self.stack.last_mut().unwrap();
+        let class = current_frame.class.clone();
+        let constant_pool = &class.constant_pool;
+        let bc = current_frame.fetch()?;
+        self.instructions += 1;
+        if let Some(profile) = &mut self.profile {
+            let (_, count) = profile
+                .entry(Rc::as_ptr(&current_frame.code))
+                .or_insert_with(|| (format!("{}.{}", class.name(), current_frame.name), 0));
+            *count += 1;
+        }
+        match execute_instruction(bc, current_frame, &mut self.heap)? {
+            Outcome::Next | Outcome::Branch => return Ok(Outcome::Next),
+            Outcome::Return(value) => return self.return_from_frame(value, depth),
+            Outcome::Unhandled => {}
+        }
+        match bc {
+            ByteCode::New(index) => {
+                let name_index = constant_pool[index as usize].as_class().unwrap();
+                let class_name = constant_pool[name_index as usize].as_utf8().unwrap();
+                // before allocating, as `<clinit>` may collect garbage
+                if !class_name.starts_with("java/") {
+                    let new_class = self.load_class(class_name)?;
+                    self.initialize(&new_class)?;
+                }
+                self.reserve_object()?;
+                let instance = self.heap.malloc_instance(class_name);
+                // the fields declared by the class and its superclasses, up to
+                // those of the standard library which are modelled by the VM itself
+                let mut next = Some(class_name.to_string());
+                while let Some(name) = next.filter(|name| !name.starts_with("java/")) {
+                    let new_class = self.load_class(&name)?;
+                    let object = self.heap.get_mut(&instance)?;
+                    for field in new_class.fields.iter().filter(|f| !f.is_static()) {
+                        let cp = &new_class.constant_pool;
+                        let value = Value::default_for(field.descriptor(cp));
+                        object.put_field(&field_key(&name, field.name(cp)), value);
+                    }
+                    next = new_class.super_class_name().map(str::to_string);
+                }
+                let current_frame = self.stack.last_mut().unwrap();
+                current_frame.operand_stack.push(Value::Reference(instance));
+            }
+            ByteCode::GetField(index) => {
+                let field = class.member_ref(index as usize);
+                let reference = &pop_object(&mut current_frame.operand_stack, "read", &field.name)?;
+                let key = self.resolve_field_site(field, false)?;
+                let instance = self.heap.get(reference)?;
+                let value = instance
+                    .get_field(&key)
+                    .ok_or_else(|| RuntimeError::NoSuchField {
+                        class: instance.class.clone(),
+                        name: field.name.clone(),
+                    })?
+                    .clone();
+                let current_frame = self.stack.last_mut().unwrap();
+                current_frame.operand_stack.push(value);
+            }
+            ByteCode::PutField(index) => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                let field = class.member_ref(index as usize);
+                let reference =
+                    &pop_object(&mut current_frame.operand_stack, "assign", &field.name)?;
+                let value = field_value(&field.descriptor, value)?;
+                let key = self.resolve_field_site(field, false)?;
+                if let Reference::Object(index) = reference {
+                    self.check_final_write(field, &key, Some(*index))?;
+                }
+                let instance = self.heap.get_mut(reference)?;
+                instance.put_field(&key, value);
+            }
+            ByteCode::GetStatic(index) => {
+                let field = class.member_ref(index as usize);
+                if field.class == "java/lang/System"
+                    && (field.descriptor == "Ljava/io/PrintStream;"
+                        || field.descriptor == "Ljava/io/InputStream;")
+                {
+                    let stream = self.system_stream(&field.name)?;
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(Value::Reference(stream));
+                } else if let Some(value) = library_constant(&field.class, &field.name) {
+                    current_frame.operand_stack.push(value);
+                } else {
+                    let key = self.static_field(field)?;
+                    let value = self.statics[&*key].clone();
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(value);
+                }
+            }
+            ByteCode::PutStatic(index) => {
+                let field = class.member_ref(index as usize);
+                let value = current_frame.operand_stack.pop().unwrap();
+                let value = field_value(&field.descriptor, value)?;
+                let key = self.static_field(field)?;
+                self.check_final_write(field, &key, None)?;
+                match self.statics.get_mut(&*key) {
+                    Some(slot) => *slot = value,
+                    None => {
+                        self.statics.insert(key.to_string(), value);
+                    }
+                }
+            }
+            ByteCode::NewArray(atype) => {
+                let class = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => {
+                        return Err(RuntimeError::Verify {
+                            class: class.name().to_string(),
+                            message: format!("invalid newarray type {}", atype),
+                        })
+                    }
+                };
+                let length = as_int(current_frame.operand_stack.pop().unwrap())?;
+                if length < 0 {
+                    return Err(RuntimeError::NegativeArraySize(length));
+                }
+                self.reserve_object()?;
+                let elements = vec![Value::default_for(&class[1..]); length as usize];
+                let array = self.heap.malloc_array(class, elements);
+                let current_frame = self.stack.last_mut().unwrap();
+                current_frame.operand_stack.push(Value::Reference(array));
+            }
+            ByteCode::ArrayLength => {
+                let array = pop_array(&mut current_frame.operand_stack)?;
+                let length = self.heap.get_array(&array).elements.len();
+                current_frame.operand_stack.push(Value::Int(length as i32));
+            }
+            ByteCode::ANewArray(index) => {
+                let name_index = constant_pool[index as usize].as_class().unwrap();
+                let element = constant_pool[name_index as usize].as_utf8().unwrap();
+                let class = match element.starts_with('[') {
+                    true => format!("[{}", element),
+                    false => format!("[L{};", element),
+                };
+                let length = as_int(current_frame.operand_stack.pop().unwrap())?;
+                if length < 0 {
+                    return Err(RuntimeError::NegativeArraySize(length));
+                }
+                self.reserve_object()?;
+                let array = self
+                    .heap
+                    .malloc_array(&class, vec![Value::Null; length as usize]);
+                let current_frame = self.stack.last_mut().unwrap();
+                current_frame.operand_stack.push(Value::Reference(array));
+            }
+            // the elements of arrays smaller than int are stored as ints
+            // narrowed to the element type already
+            ByteCode::IALoad
+            | ByteCode::AALoad
+            | ByteCode::BALoad
+            | ByteCode::CALoad
+            | ByteCode::SALoad
+            | ByteCode::LALoad
+            | ByteCode::FALoad
+            | ByteCode::DALoad => {
+                let index = as_int(current_frame.operand_stack.pop().unwrap())?;
+                let array = pop_array(&mut current_frame.operand_stack)?;
+                let value = self.heap.get_array(&array).get(index)?.clone();
+                current_frame.operand_stack.push(value);
+            }
+            ByteCode::IAStore | ByteCode::BAStore | ByteCode::CAStore | ByteCode::SAStore => {
+                let value = as_int(current_frame.operand_stack.pop().unwrap())?;
+                let index = as_int(current_frame.operand_stack.pop().unwrap())?;
+                let array = pop_array(&mut current_frame.operand_stack)?;
+                let array = self.heap.get_array_mut(&array);
+                let value = narrow_int(array.class.as_bytes()[1], value);
+                array.set(index, Value::Int(value))?;
+            }
+            ByteCode::LAStore | ByteCode::FAStore | ByteCode::DAStore => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                let value = match bc {
+                    ByteCode::LAStore => Value::Long(as_long(value)?),
+                    ByteCode::FAStore => Value::Float(as_float(value)?),
+                    _ => Value::Double(as_double(value)?),
+                };
+                let index = as_int(current_frame.operand_stack.pop().unwrap())?;
+                let array = pop_array(&mut current_frame.operand_stack)?;
+                self.heap.get_array_mut(&array).set(index, value)?;
+            }
+            ByteCode::AAStore => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                let index = as_int(current_frame.operand_stack.pop().unwrap())?;
+                let array = pop_array(&mut current_frame.operand_stack)?;
+                // the index is checked before the type of the value
+                self.heap.get_array(&array).get(index)?;
+                if let Some(class) = self.runtime_class(&value)? {
+                    let target = element_class(&self.heap.get_array(&array).class).to_string();
+                    if !self.is_assignable(&class, &target)? {
+                        return Err(RuntimeError::ArrayStore(class.replace('/', ".")));
+                    }
+                }
+                self.heap.get_array_mut(&array).set(index, value)?;
+            }
+            ByteCode::CheckCast(index) | ByteCode::InstanceOf(index) => {
+                let name_index = constant_pool[index as usize].as_class().unwrap();
+                let target = constant_pool[name_index as usize].as_utf8().unwrap();
+                let value = current_frame.operand_stack.pop().unwrap();
+                let class = self.runtime_class(&value)?;
+                // null is an instance of nothing, but can be cast to anything
+                let is_instance = match &class {
+                    Some(class) => self.is_assignable(class, target)?,
+                    None => false,
+                };
+                let current_frame = self.stack.last_mut().unwrap();
+                match bc {
+                    ByteCode::InstanceOf(_) => {
+                        current_frame.operand_stack.push(Value::from(is_instance));
+                    }
+                    _ => match class {
+                        Some(class) if !is_instance => {
+                            return Err(RuntimeError::ClassCast(format!(
+                                "class {} cannot be cast to class {}",
+                                class.replace('/', "."),
+                                target.replace('/', ".")
+                            )));
+                        }
+                        _ => current_frame.operand_stack.push(value),
+                    },
+                }
+            }
+            ByteCode::AThrow => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                let reference = value
+                    .as_reference()
+                    .ok_or_else(|| RuntimeError::NullPointer("cannot throw null".to_string()))?;
+                return Err(RuntimeError::Throw(reference));
+            }
+            ByteCode::MonitorEnter | ByteCode::MonitorExit => {
+                let value = current_frame.operand_stack.pop().unwrap();
+                let reference = value.as_reference().ok_or_else(|| {
+                    RuntimeError::NullPointer("cannot synchronize on null".to_string())
+                })?;
+                match bc {
+                    ByteCode::MonitorEnter => self.heap.monitor_enter(&reference)?,
+                    _ => self.heap.monitor_exit(&reference)?,
+                }
+            }
+            ByteCode::InvokeDynamic(index) => {
+                // invokedynamic was introduced by Java 7
+                if class.version() < (51, 0) {
+                    return Err(RuntimeError::Verify {
+                        class: class.name().to_string(),
+                        message: format!(
+                            "invokedynamic requires class file version 51, found {}",
+                            class.major_version
+                        ),
+                    });
+                }
+                let constant = &constant_pool[index as usize];
+                return Err(RuntimeError::UnsupportedConstant(constant.tag_name()));
+            }
+            ByteCode::InvokeSpecial(index) => {
+                let method = class.member_ref(index as usize);
+                if method.class == "java/lang/Object" && method.name == "<init>" {
+                    // consume the reference, do nothing
+                    current_frame.operand_stack.pop().unwrap();
+                    return Ok(Outcome::Next);
+                }
+                // every scanner reads from `System.in`, the only input stream
+                if method.class == "java/util/Scanner"
+                    && method.name == "<init>"
+                    && method.descriptor == "(Ljava/io/InputStream;)V"
+                {
+                    current_frame.operand_stack.pop().unwrap();
+                    current_frame.operand_stack.pop().unwrap();
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/StringBuilder"
+                    && self.string_builder_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/util/HashMap"
+                    && self.hash_map_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/String"
+                    && method.name == "<init>"
+                    && self.init_string(&method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class.starts_with("java/")
+                    && method.name == "<init>"
+                    && self.is_subclass_of(&method.class, "java/lang/Throwable")?
+                {
+                    self.init_throwable(&method.class, &method.descriptor)?;
+                    return Ok(Outcome::Next);
+                }
+                let frame = self.invoke_frame(method)?;
+                self.stack.push(frame);
+            }
+            ByteCode::InvokeStatic(index) => {
+                let method = class.member_ref(index as usize);
+                if method.class == "java/lang/System" && method.name == "arraycopy" {
+                    self.arraycopy()?;
+                    return Ok(Outcome::Next);
+                }
+                if method.name == "valueOf"
+                    && boxed_type(&method.class).is_some_and(|boxed| {
+                        method.descriptor == format!("({})L{};", boxed, method.class)
+                    })
+                {
+                    let value = current_frame.operand_stack.pop().unwrap();
+                    let reference = self.box_value(&method.class, value)?;
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame
+                        .operand_stack
+                        .push(Value::Reference(reference));
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/Thread" && method.name == "currentThread" {
+                    let thread = self.current_thread();
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(Value::Reference(thread));
+                    return Ok(Outcome::Next);
+                }
+                // there are no other threads to run meanwhile, and a fake clock
+                // only needs to be advanced
+                if method.class == "java/lang/Thread"
+                    && method.name == "sleep"
+                    && method.descriptor == "(J)V"
+                {
+                    let millis = as_long(current_frame.operand_stack.pop().unwrap())?.max(0);
+                    match &mut self.fake_clock {
+                        Some(clock) => {
+                            *clock = clock.saturating_add(millis.saturating_mul(1_000_000))
+                        }
+                        None => std::thread::sleep(Duration::from_millis(millis as u64)),
+                    }
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/System"
+                    && (method.name == "nanoTime" || method.name == "currentTimeMillis")
+                    && method.descriptor == "()J"
+                {
+                    let time = match method.name.as_str() {
+                        "nanoTime" => self.time(false),
+                        _ => self.time(true) / 1_000_000,
+                    };
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(Value::Long(time));
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/util/Arrays"
+                    && self.arrays_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/Character"
+                    && self.character_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/util/Objects"
+                    && self.objects_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/String"
+                    && method.name == "format"
+                    && method.descriptor
+                        == "(Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/String;"
+                {
+                    let string = self.format_from_stack()?;
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(Value::from(string));
+                    return Ok(Outcome::Next);
+                }
+                // before the arguments are popped, as `<clinit>` may collect garbage
+                let (declaring, _) = self.resolve_call_site(method)?;
+                self.initialize(&declaring)?;
+                let frame = self.invoke_frame(method)?;
+                self.stack.push(frame);
+            }
+            ByteCode::InvokeVirtual(index) => {
+                let method = class.member_ref(index as usize);
+                // special case for println
+                if method.class == "java/io/PrintStream" && method.name == "println" {
+                    let value = match method.descriptor.as_str() {
+                        "()V" => None,
+                        _ => current_frame.operand_stack.pop(),
+                    };
+                    let stream = current_frame.operand_stack.pop().unwrap();
+                    let output = match value {
+                        Some(value) => {
+                            let parameter = &method.descriptor[1..method.descriptor.len() - 2];
+                            self.string_value_of(parameter, value)?
+                        }
+                        None => String::new(),
+                    };
+                    self.print(&stream, &format!("{}\n", output))?;
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/io/PrintStream"
+                    && (method.name == "printf" || method.name == "format")
+                    && method.descriptor
+                        == "(Ljava/lang/String;[Ljava/lang/Object;)Ljava/io/PrintStream;"
+                {
+                    let output = self.format_from_stack()?;
+                    let current_frame = self.stack.last_mut().unwrap();
+                    let stream = current_frame.operand_stack.pop().unwrap();
+                    self.print(&stream, &output)?;
+                    // which returns the stream itself
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(stream);
+                    return Ok(Outcome::Next);
+                }
+                // Object.getClass is final, so it is never implemented by the class
+                if method.name == "getClass" && method.descriptor == "()Ljava/lang/Class;" {
+                    let value = current_frame.operand_stack.pop().unwrap();
+                    let class_name = match &value {
+                        Value::Reference(reference @ Reference::Object(_)) => {
+                            self.heap.get(reference)?.class.clone()
+                        }
+                        Value::Reference(reference @ Reference::Array(_)) => {
+                            self.heap.get_array(reference).class.clone()
+                        }
+                        Value::Reference(Reference::Class(_)) => "java/lang/Class".to_string(),
+                        Value::String(_) => "java/lang/String".to_string(),
+                        Value::Null => {
+                            return Err(RuntimeError::NullPointer(
+                                "cannot invoke getClass on null".to_string(),
+                            ))
+                        }
+                        value => return Err(type_mismatch("reference", value)),
+                    };
+                    let class_object = self.heap.class_object(&class_name);
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame
+                        .operand_stack
+                        .push(Value::Reference(class_object));
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/Class" && method.name == "getName" {
+                    let value = current_frame.operand_stack.pop().unwrap();
+                    let reference = value.as_reference().ok_or_else(|| {
+                        RuntimeError::NullPointer("cannot invoke getName on null".to_string())
+                    })?;
+                    let name = self.heap.class_name(&reference).replace('/', ".");
+                    current_frame.operand_stack.push(Value::from(name));
+                    return Ok(Outcome::Next);
+                }
+                // a boxed int compares and hashes by its value
+                if method.class == "java/lang/Integer"
+                    && matches!(
+                        (method.name.as_str(), method.descriptor.as_str()),
+                        ("intValue", "()I")
+                            | ("hashCode", "()I")
+                            | ("equals", "(Ljava/lang/Object;)Z")
+                            | ("toString", "()Ljava/lang/String;")
+                    )
+                {
+                    let other = match method.name.as_str() {
+                        "equals" => current_frame.operand_stack.pop(),
+                        _ => None,
+                    };
+                    let value = current_frame.operand_stack.pop().unwrap();
+                    let int = match value {
+                        Value::Null => {
+                            return Err(RuntimeError::NullPointer(format!(
+                                "cannot invoke {} on null",
+                                method.name
+                            )))
+                        }
+                        _ => self
+                            .boxed_int(&value)
+                            .ok_or_else(|| type_mismatch("Integer", &value))?,
+                    };
+                    let result = match method.name.as_str() {
+                        "equals" => {
+                            Value::from(other.and_then(|other| self.boxed_int(&other)) == Some(int))
+                        }
+                        "toString" => Value::from(int.to_string()),
+                        _ => Value::Int(int),
+                    };
+                    let current_frame = self.stack.last_mut().unwrap();
+                    current_frame.operand_stack.push(result);
+                    return Ok(Outcome::Next);
+                }
+                // decides whether the `assert` statements of the class are enabled
+                if method.class == "java/lang/Class" && method.name == "desiredAssertionStatus" {
+                    current_frame.operand_stack.pop().unwrap();
+                    let enabled = Value::from(self.enable_assertions);
+                    current_frame.operand_stack.push(enabled);
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/Thread"
+                    && (method.name == "getName" || method.name == "setName")
+                {
+                    let name = match method.name.as_str() {
+                        "setName" => Some(current_frame.operand_stack.pop().unwrap()),
+                        _ => None,
+                    };
+                    let value = current_frame.operand_stack.pop().unwrap();
+                    let reference = value.as_reference().ok_or_else(|| {
+                        RuntimeError::NullPointer(format!("cannot invoke {} on null", method.name))
+                    })?;
+                    let thread = self.heap.get_mut(&reference)?;
+                    match name {
+                        Some(name) => thread.put_field(THREAD_NAME, name),
+                        None => {
+                            let name = thread.get_field(THREAD_NAME).cloned();
+                            let current_frame = self.stack.last_mut().unwrap();
+                            current_frame
+                                .operand_stack
+                                .push(name.unwrap_or(Value::Null));
+                        }
+                    }
+                    return Ok(Outcome::Next);
+                }
+                if matches!(
+                    method.name.as_str(),
+                    "getMessage" | "getLocalizedMessage" | "getCause" | "toString"
+                ) && self.is_subclass_of(&method.class, "java/lang/Throwable")?
+                    && self.throwable_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/util/Scanner"
+                    && self.scanner_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/String"
+                    && self.string_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/lang/StringBuilder"
+                    && self.string_builder_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                if method.class == "java/util/HashMap"
+                    && self.hash_map_helper(&method.name, &method.descriptor)?
+                {
+                    return Ok(Outcome::Next);
+                }
+                // common case
+                let (class, index) = self.select_override(method)?;
+                let frame = self.method_frame(class, index)?;
+                self.stack.push(frame);
+            }
+            ByteCode::InvokeInterface(index) => {
+                let method = class.member_ref(index as usize);
+                let receiver = current_frame.operand_stack
+                    [current_frame.operand_stack.len() - args_size(&method.descriptor) - 1]
+                    .clone();
+                let class_name = self.runtime_class(&receiver)?.ok_or_else(|| {
+                    RuntimeError::NullPointer(format!("cannot invoke {} on null", method.name))
+                })?;
+                let (name, descriptor) = (&method.name, &method.descriptor);
+                // e.g. `Map.put` on a HashMap
+                if class_name == "java/util/HashMap" && self.hash_map_helper(name, descriptor)? {
+                    return Ok(Outcome::Next);
+                }
+                // the receivers of a call site differ, so it is never cached
+                let target = match self.find_override(&class_name, name, descriptor)? {
+                    Some(target) => Some(target),
+                    None => self.find_default_method(&class_name, name, descriptor)?,
+                };
+                let (class, index) = target.ok_or_else(|| RuntimeError::NoSuchMethod {
+                    class: class_name,
+                    name: method.name.clone(),
+                })?;
+                let frame = self.method_frame(class, index)?;
+                self.stack.push(frame);
+            }
+            _ => return Ok(Outcome::Unhandled),
+        }
+        Ok(Outcome::Next)
+    }
+
+    /// The next instruction of the top frame with its operand stack and
+    /// locals, as `--trace` prints it
+    fn trace_line(&self) -> String {
+        let frame = self.stack.last().unwrap();
+        format!(
+            "{}.{}:{} {} {} {}",
+            frame.class.name(),
+            frame.name,
+            frame.pc,
+            traced_instruction(&frame.code.code, frame.pc),
+            frame.dump_operand_stack(&self.heap),
+            frame.dump_locals(&self.heap)
+        )
+    }
+
+    /// Show the next instruction and read the commands of the step debugger
+    /// until one lets it execute, see [`Vm::set_step_input`]
+    fn read_step_commands(&mut self) -> Result<(), RuntimeError> {
+        let stepper = self.stepper.as_mut().unwrap();
+        // the snapshots are of the frame which called or returned
+        if stepper.depth != self.stack.len() {
+            stepper.history.clear();
+            stepper.depth = self.stack.len();
+        }
+        loop {
+            let line = self.trace_line();
+            writeln!(self.err, "{}", line)?;
+            let stepper = self.stepper.as_mut().unwrap();
+            let frame = self.stack.last_mut().unwrap();
+            let mut command = String::new();
+            if stepper.input.read_line(&mut command)? == 0 {
+                stepper.running = true;
+                return Ok(());
+            }
+            match command.trim() {
+                "" | "s" => {
+                    stepper.history.record(frame);
+                    return Ok(());
+                }
+                "b" => {
+                    if !stepper.history.step_back(frame) {
+                        writeln!(self.err, "nothing to step back to in this method")?;
+                    }
+                }
+                "c" => {
+                    stepper.running = true;
+                    return Ok(());
+                }
+                _ => writeln!(
+                    self.err,
+                    "commands: s or nothing to step, b to step back, c to continue"
+                )?,
+            }
+        }
+    }
+
+    /// Pop the top frame, which returned `value`, passing the value to the
+    /// frame below unless it is the one `execute` was called with
+    fn return_from_frame(
+        &mut self,
+        value: Option<Value>,
+        depth: usize,
+    ) -> Result<Outcome, RuntimeError> {
+        let frame = self.stack.pop().unwrap();
+        if let Some(reference) = &frame.monitor {
+            self.heap.monitor_exit(reference)?;
+        }
+        if self.stack.len() > depth {
+            let current_frame = self.stack.last_mut().unwrap();
+            if let Some(value) = value.clone() {
+                current_frame.operand_stack.push(value);
+            }
+        }
+        // garbage collection when returning from a method
+        let collect = match self.gc {
+            GcMode::None => false,
+            GcMode::MarkSweep => self.heap.allocated() >= GcMode::THRESHOLD,
+            GcMode::OnReturn => true,
+        };
+        if collect && !self.stack.is_empty() {
+            self.collect_garbage(&frame.class, &frame.name)?;
+        }
+        if self.stack.len() == depth {
+            return Ok(Outcome::Return(value));
+        }
+        Ok(Outcome::Next)
+    }
+
+    /// Build the frame of a method, moving `this` (for instance methods) and
+    /// the arguments from the operand stack of the current frame into its locals
+    fn invoke_frame(&mut self, method: &MemberRef) -> Result<Frame, RuntimeError> {
+        let (class, index) = self.resolve_call_site(method)?;
+        self.method_frame(class, index)
+    }
+
+    /// Build the frame of the method at `index` of `class` like
+    /// [`Vm::invoke_frame`], once it has been resolved
+    fn method_frame(&mut self, class: Rc<ClassFile>, index: usize) -> Result<Frame, RuntimeError> {
+        let method = &class.methods[index];
+        let descriptor = method.descriptor(&class.constant_pool);
+        let mut frame = Frame::new(class.clone(), method);
+        let current_frame = self.stack.last_mut().unwrap();
+        // + 1 for `this`
+        let size = args_size(descriptor) + if method.is_static() { 0 } else { 1 };
+        let start = current_frame.operand_stack.len() - size;
+        let args = current_frame.operand_stack.drain(start..);
+        pass_arguments(&mut frame, descriptor, method.is_static(), args);
+        self.enter_method_monitor(&mut frame, method)?;
+        Ok(frame)
+    }
+
+    /// The class and index of the method invoked by the instruction being
+    /// executed, which refers to `method`
+    fn resolve_call_site(
+        &mut self,
+        method: &MemberRef,
+    ) -> Result<(Rc<ClassFile>, usize), RuntimeError> {
+        let frame = self.stack.last().unwrap();
+        let site = (Rc::as_ptr(&frame.code), frame.current_pc);
+        if let Some(target) = self.call_sites.get(&site) {
+            return Ok(target.clone());
+        }
+        // the class referred to may inherit the method from a superclass, or
+        // a default method from an interface
+        let (name, descriptor) = (&method.name, &method.descriptor);
+        let target = match self.find_override(&method.class, name, descriptor)? {
+            Some(target) => Some(target),
+            None => self.find_default_method(&method.class, name, descriptor)?,
+        };
+        let Some((class, index)) = target else {
+            // which fails if it is a class of the standard library
+            self.load_class(&method.class)?;
+            return Err(RuntimeError::NoSuchMethod {
+                class: method.class.clone(),
+                name: method.name.clone(),
+            });
+        };
+        // the site is only resolved once, and always executed by the same class
+        let access = class.methods[index].access();
+        let member = format!("{}{}", name, descriptor);
+        self.check_access(&class, access, "method", &member)?;
+        self.call_sites.insert(site, (class.clone(), index));
+        Ok((class, index))
+    }
+
+    /// The class and index of the method an `invokevirtual` of `method` runs,
+    /// which is the one the call site resolves to or an override of it by
+    /// the class of the receiver
+    ///
+    /// So a method inherited by the class, like one calling `this.sound()`,
+    /// reaches the overrides of that class rather than those of the class
+    /// declaring it. The receivers of a site differ, so only the method it
+    /// resolves to is cached.
+    fn select_override(
+        &mut self,
+        method: &MemberRef,
+    ) -> Result<(Rc<ClassFile>, usize), RuntimeError> {
+        let (declaring, index) = self.resolve_call_site(method)?;
+        // private methods are never overridden
+        if declaring.methods[index].is_private() {
+            return Ok((declaring, index));
+        }
+        let operand_stack = &self.stack.last().unwrap().operand_stack;
+        let receiver = &operand_stack[operand_stack.len() - args_size(&method.descriptor) - 1];
+        let class_name = self.runtime_class(receiver)?.ok_or_else(|| {
+            RuntimeError::NullPointer(format!("cannot invoke {} on null", method.name))
+        })?;
+        if class_name == declaring.name() {
+            return Ok((declaring, index));
+        }
+        Ok(self
+            .find_override(&class_name, &method.name, &method.descriptor)?
+            .unwrap_or((declaring, index)))
+    }
+
+    /// Acquire the monitor of `this` if `method` is synchronized, which
+    /// is released again when the frame returns
+    fn enter_method_monitor(
+        &mut self,
+        frame: &mut Frame,
+        method: &MethodInfo,
+    ) -> Result<(), RuntimeError> {
+        // static methods would lock the class, which isn't modelled as an object
+        if method.is_synchronized() && !method.is_static() {
+            let reference = frame.locals[0].as_reference().ok_or_else(|| {
+                RuntimeError::NullPointer("cannot synchronize on null".to_string())
+            })?;
+            self.heap.monitor_enter(&reference)?;
+            frame.monitor = Some(reference);
+        }
+        Ok(())
+    }
+
+    /// The start and end of the next token of `System.in` within the pending
+    /// input, reading more lines as needed, or `None` at the end of the input
+    fn peek_token(&mut self) -> Result<Option<(usize, usize)>, RuntimeError> {
+        loop {
+            let pending = &self.pending_input;
+            if let Some(start) = pending.find(|c: char| !c.is_whitespace()) {
+                let end = pending[start..]
+                    .find(char::is_whitespace)
+                    .map_or(pending.len(), |end| start + end);
+                return Ok(Some((start, end)));
+            }
+            if self.input.read_line(&mut self.pending_input)? == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// `Scanner.nextInt`, `next`, `nextLine`, `hasNextInt`, `hasNext`,
+    /// `hasNextLine` or `close` if `name` and `descriptor` are one of them,
+    /// returning whether it was
+    ///
+    /// Like Java, looking ahead doesn't consume any input, and tokens are
+    /// separated by whitespace.
+    fn scanner_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        if !matches!(
+            (name, descriptor),
+            ("nextInt", "()I")
+                | ("next", "()Ljava/lang/String;")
+                | ("nextLine", "()Ljava/lang/String;")
+                | ("hasNextInt", "()Z")
+                | ("hasNext", "()Z")
+                | ("hasNextLine", "()Z")
+                | ("close", "()V")
+        ) {
+            return Ok(false);
+        }
+        let current_frame = self.stack.last_mut().unwrap();
+        if let Value::Null = current_frame.operand_stack.pop().unwrap() {
+            return Err(RuntimeError::NullPointer(format!(
+                "cannot invoke {} on null",
+                name
+            )));
+        }
+        let result = match name {
+            "nextInt" | "next" | "hasNextInt" | "hasNext" => {
+                let token = self.peek_token()?;
+                let pending = &mut self.pending_input;
+                let text = token.map(|(start, end)| pending[start..end].to_string());
+                let int = text.as_deref().and_then(|text| text.parse::<i32>().ok());
+                match (name, token) {
+                    ("hasNext", _) => Value::from(token.is_some()),
+                    ("hasNextInt", _) => Value::from(int.is_some()),
+                    // the whitespace before the end of the input is skipped
+                    (_, None) => {
+                        pending.clear();
+                        return Err(RuntimeError::NoSuchElement(None));
+                    }
+                    ("nextInt", Some(_)) if int.is_none() => {
+                        return Err(RuntimeError::InputMismatch);
+                    }
+                    (_, Some((_, end))) => {
+                        pending.drain(..end);
+                        match int.filter(|_| name == "nextInt") {
+                            Some(int) => Value::Int(int),
+                            None => Value::from(text.unwrap()),
+                        }
+                    }
+                }
+            }
+            "nextLine" | "hasNextLine" => {
+                if self.pending_input.is_empty() {
+                    self.input.read_line(&mut self.pending_input)?;
+                }
+                let pending = &mut self.pending_input;
+                match (name, pending.is_empty()) {
+                    ("hasNextLine", empty) => Value::from(!empty),
+                    (_, true) => {
+                        return Err(RuntimeError::NoSuchElement(Some(
+                            "No line found".to_string(),
+                        )))
+                    }
+                    (_, false) => {
+                        let end = pending.find('\n').map_or(pending.len(), |end| end + 1);
+                        let line = pending.drain(..end).collect::<String>();
+                        let line = line.strip_suffix('\n').unwrap_or(&line);
+                        Value::from(line.strip_suffix('\r').unwrap_or(line).to_string())
+                    }
+                }
+            }
+            _ => return Ok(true),
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        current_frame.operand_stack.push(result);
+        Ok(true)
+    }
+
+    /// The `HashMap` constructor and the methods `put`, `get`, `containsKey`,
+    /// `size` and `isEmpty` if `name` and `descriptor` are one of them,
+    /// returning whether it was
+    ///
+    /// Keys are compared like Java does, by value for strings and boxed
+    /// primitives and with `hashCode` and `equals` for the objects of the
+    /// program. The arguments stay on the operand stack until the entry is
+    /// found, as those methods may collect garbage.
+    fn hash_map_helper(&mut self, name: &str, descriptor: &str) -> Result<bool, RuntimeError> {
+        let arguments = match (name, descriptor) {
+            ("<init>", "()V") | ("size", "()I") | ("isEmpty", "()Z") => 0,
+            ("get", "(Ljava/lang/Object;)Ljava/lang/Object;")
+            | ("containsKey", "(Ljava/lang/Object;)Z") => 1,
+            ("put", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;") => 2,
+            _ => return Ok(false),
+        };
+        let operand_stack = &self.stack.last().unwrap().operand_stack;
+        let values = operand_stack[operand_stack.len() - arguments - 1..].to_vec();
+        let map = values[0]
+            .as_reference()
+            .ok_or_else(|| RuntimeError::NullPointer(format!("cannot invoke {} on null", name)))?;
+        // the key, and the index and value of its entry if there is one
+        let entry = match values.get(1) {
+            Some(key) => {
+                let map_key = self.map_key(key)?;
+                let instance = self.heap.get(&map)?;
+                let entries = instance.entries.get(&map_key).cloned().unwrap_or_default();
+                let index = self.find_entry(&entries, &map_key, key)?;
+                let value = index.map(|index| (index, entries[index].1.clone()));
+                Some((map_key, value))
+            }
+            None => None,
+        };
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        operand_stack.truncate(operand_stack.len() - arguments - 1);
+        let instance = self.heap.get_mut(&map)?;
+        let result = match (name, entry) {
+            ("size", _) => {
+                Value::Int(instance.entries.values().map(Vec::len).sum::<usize>() as i32)
+            }
+            ("isEmpty", _) => Value::from(instance.entries.is_empty()),
+            ("containsKey", Some((_, entry))) => Value::from(entry.is_some()),
+            ("get", Some((_, entry))) => entry.map_or(Value::Null, |(_, value)| value),
+            // which keeps the key it has, and returns the value replaced
+            ("put", Some((map_key, entry))) => {
+                let entries = instance.entries.entry(map_key).or_default();
+                let value = values[2].clone();
+                match entry {
+                    Some((index, previous)) => {
+                        entries[index].1 = value;
+                        previous
+                    }
+                    None => {
+                        entries.push((values[1].clone(), value));
+                        Value::Null
+                    }
+                }
+            }
+            // a new map has no entries yet
+            _ => return Ok(true),
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        current_frame.operand_stack.push(result);
+        Ok(true)
+    }
+
+    /// What the key `key` of a `HashMap` is looked up by, calling its
+    /// `hashCode` if it is an object of the program overriding it
+    fn map_key(&mut self, key: &Value) -> Result<MapKey, RuntimeError> {
+        if let Some((class, value)) = self.unbox(key) {
+            let class = class.to_string();
+            let bits = match value {
+                Value::Long(value) => value,
+                Value::Float(value) => value.to_bits().into(),
+                Value::Double(value) => value.to_bits() as i64,
+                value => as_int(value)?.into(),
+            };
+            return Ok(MapKey::Boxed(class, bits));
+        }
+        let hash = match key {
+            Value::Null => return Ok(MapKey::Null),
+            Value::String(contents) => return Ok(MapKey::String(contents.clone())),
+            Value::Reference(reference @ Reference::Object(_)) => {
+                match self.call_override(key.clone(), "hashCode", "()I", vec![])? {
+                    Some(hash) => as_int(hash)?,
+                    None => self.heap.get(reference)?.hash,
+                }
+            }
+            Value::Reference(reference @ Reference::Array(_)) => {
+                self.heap.get_array(reference).hash
+            }
+            // there is a single class object for every class
+            Value::Reference(Reference::Class(index)) => *index as i32,
+            value => return Err(type_mismatch("reference", value)),
+        };
+        Ok(MapKey::Hash(hash))
+    }
+
+    /// The index of the entry of `key` among `entries`, those of a map with
+    /// the same `map_key`, calling `equals` of the keys of the program
+    fn find_entry(
+        &mut self,
+        entries: &[(Value, Value)],
+        map_key: &MapKey,
+        key: &Value,
+    ) -> Result<Option<usize>, RuntimeError> {
+        // strings and boxed primitives are their own map key
+        if !matches!(map_key, MapKey::Hash(_)) {
+            return Ok((!entries.is_empty()).then_some(0));
+        }
+        for (index, (candidate, _)) in entries.iter().enumerate() {
+            if candidate.as_reference() == key.as_reference() {
+                return Ok(Some(index));
+            }
+            let descriptor = "(Ljava/lang/Object;)Z";
+            let arguments = vec![candidate.clone()];
+            if let Some(equal) = self.call_override(key.clone(), "equals", descriptor, arguments)? {
+                if as_int(equal)? != 0 {
+                    return Ok(Some(index));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Run the method `name` of the `descriptor` of the object `receiver`
+    /// with `args` and return its result, if the class of the object or one
+    /// of its superclasses in the program declares the method
+    fn call_override(
+        &mut self,
+        receiver: Value,
+        name: &str,
+        descriptor: &str,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let Value::Reference(reference @ Reference::Object(_)) = &receiver else {
+            return Ok(None);
+        };
+        let class_name = self.heap.get(reference)?.class.clone();
+        let Some((class, index)) = self.find_override(&class_name, name, descriptor)? else {
+            return Ok(None);
+        };
+        let method = &class.methods[index];
+        let mut frame = Frame::new(class.clone(), method);
+        let args = std::iter::once(receiver).chain(args);
+        pass_arguments(&mut frame, descriptor, false, args);
+        self.enter_method_monitor(&mut frame, method)?;
+        // hand-written bytecode may return nothing from a method which
+        // should return a value
+        let result = self.execute(frame)?;
+        let method = format!("{}.{}", class.name(), name);
+        result
+            .map(Some)
+            .ok_or_else(|| missing_return_value(&method))
+    }
+
+    /// The `StringBuilder` constructors and the methods `append`, `insert`,
+    /// `reverse`, `length`, `charAt`, `deleteCharAt` and `toString` if `name`
+    /// and `descriptor` are one of them, returning whether it was
+    ///
+    /// The contents are kept as a string in a field of the builder and
+    /// indexed by characters rather than UTF-16 units, which only differ for
+    /// those outside of the Basic Multilingual Plane.
+    fn string_builder_helper(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<bool, RuntimeError> {
+        // the types which append and insert convert like String.valueOf
+        let convertible = |parameter: &str| {
+            matches!(
+                parameter,
+                "Z" | "C"
+                    | "I"
+                    | "J"
+                    | "F"
+                    | "D"
+                    | "[C"
+                    | "Ljava/lang/String;"
+                    | "Ljava/lang/Object;"
+                    | "Ljava/lang/CharSequence;"
+            )
+        };
+        let (parameters, returns) = descriptor[1..].split_once(')').unwrap();
+        let returns_builder = returns == "Ljava/lang/StringBuilder;";
+        let known = match name {
+            "<init>" => matches!(
+                parameters,
+                "" | "I" | "Ljava/lang/String;" | "Ljava/lang/CharSequence;"
+            ),
+            "append" => returns_builder && convertible(parameters),
+            "insert" => returns_builder && parameters.strip_prefix('I').is_some_and(convertible),
+            "reverse" => descriptor == "()Ljava/lang/StringBuilder;",
+            "deleteCharAt" => descriptor == "(I)Ljava/lang/StringBuilder;",
+            "length" => descriptor == "()I",
+            "charAt" => descriptor == "(I)C",
+            "toString" => descriptor == "()Ljava/lang/String;",
+            _ => false,
+        };
+        if !known {
+            return Ok(false);
+        }
+        let operand_stack = &mut self.stack.last_mut().unwrap().operand_stack;
+        let argument = match (name, parameters) {
+            ("charAt" | "deleteCharAt", _) | (_, "") => None,
+            _ => operand_stack.pop(),
+        };
+        // the offset of insert, or the index of the other methods taking one
+        let index = match name {
+            "insert" | "charAt" | "deleteCharAt" => Some(as_int(operand_stack.pop().unwrap())?),
+            _ => None,
+        };
+        let builder = operand_stack.pop().unwrap();
+        let reference = builder
+            .as_reference()
+            .ok_or_else(|| RuntimeError::NullPointer(format!("cannot invoke {} on null", name)))?;
+        if name == "<init>" {
+            let contents = match (parameters, argument) {
+                ("I", Some(capacity)) => match as_int(capacity)? {
+                    capacity if capacity < 0 => {
+                        return Err(RuntimeError::NegativeArraySize(capacity))
+                    }
+                    _ => String::new(),
+                },
+                (_, Some(Value::Null)) => {
+                    return Err(RuntimeError::NullPointer(
+                        "cannot invoke length on null".to_string(),
+                    ))
+                }
+                (_, Some(value)) => self.stringify(value)?,
+                (_, None) => String::new(),
+            };
+            let builder = self.heap.get_mut(&reference)?;
+            builder.put_field(STRING_BUILDER_VALUE, Value::from(contents));
+            return Ok(true);
+        }
+        // the characters of the argument, which are converted first as that
+        // may call its toString
+        let string = match (name, argument) {
+            ("append", Some(value)) => Some(self.string_value_of(parameters, value)?),
+            // after the offset
+            ("insert", Some(value)) => Some(self.string_value_of(&parameters[1..], value)?),
+            _ => None,
+        };
+        let mut contents = match self.heap.get(&reference)?.get_field(STRING_BUILDER_VALUE) {
+            Some(Value::String(contents)) => contents.chars().collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        let length = contents.len();
+        let index = match (name, index) {
+            // inserting at the end appends
+            ("insert", Some(offset)) if offset < 0 || offset as usize > length => {
+                return Err(RuntimeError::StringIndexOutOfBounds(format!(
+                    "offset {}, length {}",
+                    offset, length
+                )));
+            }
+            ("charAt" | "deleteCharAt", Some(index)) if index < 0 || index as usize >= length => {
+                return Err(RuntimeError::StringIndexOutOfBounds(format!(
+                    "index {}, length {}",
+                    index, length
+                )));
+            }
+            (_, index) => index.unwrap_or(0) as usize,
+        };
+        let result = match name {
+            "append" => {
+                contents.extend(string.unwrap().chars());
+                None
+            }
+            "insert" => {
+                contents.splice(index..index, string.unwrap().chars());
+                None
+            }
+            "reverse" => {
+                contents.reverse();
+                None
+            }
+            "deleteCharAt" => {
+                contents.remove(index);
+                None
+            }
+            "length" => Some(Value::Int(length as i32)),
+            "charAt" => Some(Value::Int(contents[index] as i32)),
+            _ => Some(Value::from(contents.iter().collect::<String>())),
+        };
+        let current_frame = self.stack.last_mut().unwrap();
+        match result {
+            Some(result) => current_frame.operand_stack.push(result),
+            // the methods changing the builder return it for chaining
+            None => {
+                current_frame.operand_stack.push(builder);
+                let contents = contents.into_iter().collect::<String>();
+                let builder = self.heap.get_mut(&reference)?;
+                builder.put_field(STRING_BUILDER_VALUE, Value::from(contents));
+            }
+        }
+        Ok(true)
+    }
+
+    /// The string of a `value` of the field type `descriptor` like
+    /// `String.valueOf` gives
+    ///
+    /// Booleans and chars are ints on the operand stack, so they are told
+    /// apart by the type, and so are char arrays which give their characters.
+    fn string_value_of(&mut self, descriptor: &str, value: Value) -> Result<String, RuntimeError> {
+        match (descriptor, value) {
+            ("Z", value) => Ok(bool::try_from(value)?.to_string()),
+            ("C", value) => {
+                let code = u32::try_from(i32::try_from(value)?).unwrap_or(0);
+                Ok(char::from_u32(code).unwrap_or('\u{fffd}').to_string())
+            }
+            ("[C", Value::Reference(array)) => {
+                let elements = &self.heap.get_array(&array).elements;
+                let mut string = String::new();
+                for value in elements.clone() {
+                    let code = u32::try_from(i32::try_from(value)?).unwrap_or(0);
+                    string.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                }
+                Ok(string)
+            }
+            ("[C", Value::Null) => Err(RuntimeError::NullPointer(
+                "cannot read the length of null".to_string(),
+            )),
+            (_, value) => self.stringify(value),
+        }
+    }
+
+    /// Write `output` to the `System` stream `stream`
+    fn print(&mut self, stream: &Value, output: &str) -> Result<(), RuntimeError> {
+        let name = self
+            .streams
+            .iter()
+            .find(|(_, reference)| Some(reference) == stream.as_reference().as_ref())
+            .map(|(name, _)| name.as_str());
+        match name {
+            Some("err") => write!(self.err, "{}", output)?,
+            _ => write!(self.out, "{}", output)?,
+        }
+        Ok(())
+    }
+
+    /// Pop the format string and the `Object[]` of the arguments passed to
+    /// `String.format` or `printf` and format them
+    fn format_from_stack(&mut self) -> Result<String, RuntimeError> {
+        let current_frame = self.stack.last_mut().unwrap();
+        let args = pop_array(&mut current_frame.operand_stack)?;
+        let format = match current_frame.operand_stack.pop().unwrap() {
+            Value::String(format) => format,
+            Value::Null => {
+                return Err(RuntimeError::NullPointer(
+                    "cannot format with a null format string".to_string(),
+                ))
+            }
+            value => return Err(type_mismatch("String", &value)),
+        };
+        let args = self.heap.get_array(&args).elements.clone();
+        self.format(&format, args)
+    }
+
+    /// `String.format(format, args)` for the conversions `d`, `x`, `c`, `s`,
+    /// `f`, `n` and `%`, with the flags `-`, `0` and `+`, a width and a
+    /// precision
+    fn format(&mut self, format: &str, args: Vec<Value>) -> Result<String, RuntimeError> {
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let mut output = String::new();
+        let mut args = args.into_iter();
+        let mut rest = format;
+        while let Some(start) = rest.find('%') {
+            output.push_str(&rest[..start]);
+            let specifier = &rest[start..];
+            let flags = specifier[1..]
+                .find(|c| !"-0+".contains(c))
+                .map_or(specifier.len(), |end| end + 1);
+            let width = flags + digits(&specifier[flags..]);
+            let (precision, end) = match specifier[width..].strip_prefix('.') {
+                Some(after) => (
+                    after[..digits(after)].parse().ok(),
+                    width + 1 + digits(after),
+                ),
+                None => (None, width),
+            };
+            let Some(conversion) = specifier[end..].chars().next() else {
+                return Err(RuntimeError::IllegalFormat(
+                    "java/util/UnknownFormatConversionException",
+                    "Conversion = '%'".to_string(),
+                ));
+            };
+            rest = &specifier[end + conversion.len_utf8()..];
+            let flags = &specifier[1..flags];
+            let text = match conversion {
+                'n' => "\n".to_string(),
+                '%' => "%".to_string(),
+                _ => {
+                    let arg = args.next().ok_or_else(|| {
+                        RuntimeError::IllegalFormat(
+                            "java/util/MissingFormatArgumentException",
+                            format!("Format specifier '{}'", &specifier[..end + 1]),
+                        )
+                    })?;
+                    self.convert(conversion, arg, flags, precision)?
+                }
+            };
+            let width = specifier[flags.len() + 1..width]
+                .parse::<usize>()
+                .unwrap_or(0);
+            let padding = width.saturating_sub(text.chars().count());
+            if flags.contains('-') {
+                output.push_str(&text);
+                output.push_str(&" ".repeat(padding));
+            } else if flags.contains('0') && "dxXf".contains(conversion) {
+                // the zeros go between the sign and the digits
+                let (sign, digits) = match text.strip_prefix(['-', '+']) {
+                    Some(digits) => text.split_at(text.len() - digits.len()),
+                    None => ("", text.as_str()),
+                };
+                output.push_str(sign);
+                output.push_str(&"0".repeat(padding));
+                output.push_str(digits);
+            } else {
+                output.push_str(&" ".repeat(padding));
+                output.push_str(&text);
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Format a single argument of `String.format` by its `conversion`
+    fn convert(
+        &mut self,
+        conversion: char,
+        arg: Value,
+        flags: &str,
+        precision: Option<usize>,
+    ) -> Result<String, RuntimeError> {
+        if matches!(arg, Value::Null) {
+            return Ok("null".to_string());
+        }
+        let mismatch = |vm: &Self, arg: &Value| {
+            let class = vm.runtime_class(arg).ok().flatten().unwrap_or_default();
+            RuntimeError::IllegalFormat(
+                "java/util/IllegalFormatConversionException",
+                format!("{} != {}", conversion, class.replace('/', ".")),
+            )
+        };
+        let sign = |negative: bool| match negative || !flags.contains('+') {
+            true => "",
+            false => "+",
+        };
+        // `%S` and `%X` print in upper case
+        let lower = match conversion {
+            'S' => 's',
+            'X' => 'x',
+            conversion => conversion,
+        };
+        let text = match (lower, self.unbox(&arg)) {
+            ('d', Some(("java/lang/Integer", Value::Int(value)))) => {
+                format!("{}{}", sign(value < 0), value)
+            }
+            ('d', Some(("java/lang/Long", Value::Long(value)))) => {
+                format!("{}{}", sign(value < 0), value)
+            }
+            // negative values are printed as their two's complement
+            ('x', Some(("java/lang/Integer", Value::Int(value)))) => format!("{:x}", value),
+            ('x', Some(("java/lang/Long", Value::Long(value)))) => format!("{:x}", value),
+            ('c', Some(("java/lang/Character" | "java/lang/Integer", Value::Int(code)))) => {
+                char::from_u32(code as u32)
+                    .unwrap_or('\u{fffd}')
+                    .to_string()
+            }
+            ('f', Some(("java/lang/Float", Value::Float(value)))) => {
+                let value = value as f64;
+                let text = java_fixed(value, precision.unwrap_or(6));
+                format!("{}{}", sign(value.is_sign_negative()), text)
+            }
+            ('f', Some(("java/lang/Double", Value::Double(value)))) => {
+                let text = java_fixed(value, precision.unwrap_or(6));
+                format!("{}{}", sign(value.is_sign_negative()), text)
+            }
+            ('s', _) => {
+                let text = self.stringify(arg)?;
+                match precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                }
+            }
+            ('d' | 'x' | 'c' | 'f', _) => return Err(mismatch(self, &arg)),
+            _ => {
+                return Err(RuntimeError::IllegalFormat(
+                    "java/util/UnknownFormatConversionException",
+                    format!("Conversion = '{}'", conversion),
+                ))
+            }
+        };
+        Ok(match lower == conversion {
+            true => text,
+            false => text.to_uppercase(),
+        })
+    }
+
+    /// Convert `value` to the string Java would print for it, calling the
+    /// `toString` method of the object's class if it declares one
+    fn stringify(&mut self, value: Value) -> Result<String, RuntimeError> {
+        if let Value::Reference(reference @ Reference::Object(_)) = &value {
+            let class_name = self.heap.get(reference)?.class.clone();
+            // which may be inherited from a superclass, like invokevirtual
+            // finds it, but not an abstract one
+            let target = self
+                .find_override(&class_name, "toString", "()Ljava/lang/String;")?
+                .filter(|(class, index)| class.methods[*index].has_code(&class.constant_pool));
+            if let Some((class, index)) = target {
+                let method = &class.methods[index];
+                let mut frame = Frame::new(class.clone(), method);
+                frame.locals[0] = value;
+                self.enter_method_monitor(&mut frame, method)?;
+                let result = self
+                    .execute(frame)?
+                    .ok_or_else(|| missing_return_value(&format!("{}.toString", class.name())))?;
+                return Ok(result.to_string());
+            }
+            match self.unbox(&value) {
+                Some(("java/lang/Character", value)) => {
+                    let code = u32::try_from(i32::try_from(value)?).unwrap_or(0);
+                    return Ok(char::from_u32(code).unwrap_or('\u{fffd}').to_string());
+                }
+                Some((_, value)) => return Ok(value.to_string()),
+                None => {}
+            }
+            if let Some(Value::String(contents)) =
+                self.heap.get(reference)?.get_field(STRING_BUILDER_VALUE)
+            {
+                return Ok(contents.to_string());
+            }
+            // the default of java.lang.Throwable
+            if self.is_subclass_of(&class_name, "java/lang/Throwable")? {
+                let class = class_name.replace('/', ".");
+                return Ok(match self.throwable_message(reference)? {
+                    Value::Null => class,
+                    message => format!("{}: {}", class, self.stringify(message)?),
+                });
+            }
+            // the default of java.lang.Object
+            let instance = self.heap.get(reference)?;
+            return Ok(format!(
+                "{}@{:x}",
+                instance.class.replace('/', "."),
+                instance.hash
+            ));
+        }
+        if let Value::Reference(reference @ Reference::Class(_)) = &value {
+            let name = self.heap.class_name(reference).replace('/', ".");
+            return Ok(format!("class {}", name));
+        }
+        // arrays don't override toString, and their class names already
+        // look like `[I` or `[Ljava.lang.String;`
+        if let Value::Reference(reference @ Reference::Array(_)) = &value {
+            let array = self.heap.get_array(reference);
+            return Ok(format!(
+                "{}@{:x}",
+                array.class.replace('/', "."),
+                array.hash
+            ));
+        }
+        Ok(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::Comparison;
+
+    /// The class file of the test program `name` of `tests/java`
+    fn test_class(name: &str) -> Rc<ClassFile> {
+        let path = format!("{}/tests/java/{}.class", env!("CARGO_MANIFEST_DIR"), name);
+        Rc::new(ClassFile::parse(&path).unwrap())
+    }
+
+    /// A frame of the `main` method of the test program `name`
+    fn main_frame(name: &str) -> Frame {
+        let class = test_class(name);
+        let main = class.find_main_method().unwrap();
+        Frame::new(class.clone(), main)
+    }
+
+    #[test]
+    fn ldc_of_a_method_handle_is_unsupported() {
+        let mut frame = main_frame("Lambdas");
+        let index = frame
+            .class
+            .constant_pool
+            .iter()
+            .position(|constant| matches!(constant, ConstantInfo::MethodHandle { .. }))
+            .unwrap();
+        let result = execute_instruction(ByteCode::Ldc(index as u8), &mut frame, &mut Heap::new());
+        assert!(matches!(
+            result,
+            Err(RuntimeError::UnsupportedConstant("MethodHandle"))
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "strict-assertions"))]
+    fn adding_a_string_is_a_type_mismatch() {
+        let mut frame = main_frame("Hello");
+        frame.operand_stack.push(Value::Int(1));
+        frame.operand_stack.push(Value::String("x".into()));
+        let result = execute_instruction(ByteCode::IAdd, &mut frame, &mut Heap::new());
+        assert!(matches!(
+            result,
+            Err(RuntimeError::TypeMismatch {
+                expected: "int",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn stepping_back_restores_the_pc_and_locals() {
+        let mut frame = main_frame("Steps");
+        let mut heap = Heap::new();
+        let mut history = History::new(8);
+        // iconst_1, istore_1
+        for _ in 0..2 {
+            history.record(&frame);
+            let bc = frame.fetch().unwrap();
+            let outcome = execute_instruction(bc, &mut frame, &mut heap).unwrap();
+            assert!(matches!(outcome, Outcome::Next));
+        }
+        assert_eq!(frame.pc, 2);
+        assert!(matches!(frame.locals[1], Value::Int(1)));
+        assert!(history.step_back(&mut frame));
+        assert_eq!(frame.pc, 1);
+        assert!(matches!(frame.locals[1], Value::Int(0)));
+        assert!(matches!(frame.operand_stack[..], [Value::Int(1)]));
+        assert_eq!(history.len(), 1);
+    }
+
+    /// Execute `bc` on `operands`, giving the value it leaves on the stack
+    fn compute(bc: ByteCode, operands: &[Value]) -> Value {
+        let mut frame = main_frame("Steps");
+        frame.operand_stack.extend_from_slice(operands);
+        execute_instruction(bc, &mut frame, &mut Heap::new()).unwrap();
+        assert_eq!(frame.operand_stack.len(), 1);
+        frame.operand_stack.pop().unwrap()
+    }
+
+    #[test]
+    fn add_dup_and_store_act_on_the_frame_alone() {
+        let mut frame = main_frame("Steps");
+        let mut heap = Heap::new();
+        frame.operand_stack.extend([Value::Int(40), Value::Int(2)]);
+        for bc in [ByteCode::IAdd, ByteCode::Dup, ByteCode::IStore(2)] {
+            let outcome = execute_instruction(bc, &mut frame, &mut heap).unwrap();
+            assert!(matches!(outcome, Outcome::Next), "{:?}", bc);
+        }
+        assert_eq!(format!("{:?}", frame.operand_stack), "[Int(42)]");
+        assert_eq!(format!("{:?}", frame.locals[2]), "Int(42)");
+        // and an instruction needing the rest of the VM is left undone
+        let bc = ByteCode::InvokeStatic(1);
+        let outcome = execute_instruction(bc, &mut frame, &mut heap).unwrap();
+        assert!(matches!(outcome, Outcome::Unhandled));
+        assert_eq!(frame.operand_stack.len(), 1);
+    }
+
+    #[test]
+    fn traces_show_undecodable_instructions_by_mnemonic() {
+        // iload_0, ifnull +3, then a byte which isn't an instruction
+        let code = [0x1a, 0xc6, 0, 3, 0xff];
+        assert_eq!(traced_instruction(&code, 0), "ILoad(0)");
+        assert_eq!(traced_instruction(&code, 1), "ifnull");
+        assert_eq!(traced_instruction(&code, 4), "<undecodable>");
+        assert_eq!(traced_instruction(&code, 5), "<undecodable>");
+    }
+
+    #[test]
+    fn integer_arithmetic_wraps_on_overflow() {
+        use Value::{Int, Long};
+        let cases = [
+            (ByteCode::IAdd, vec![Int(i32::MAX), Int(1)], Int(i32::MIN)),
+            (ByteCode::ISub, vec![Int(i32::MIN), Int(1)], Int(i32::MAX)),
+            (ByteCode::IMul, vec![Int(i32::MAX), Int(2)], Int(-2)),
+            (ByteCode::INeg, vec![Int(i32::MIN)], Int(i32::MIN)),
+            (ByteCode::IShl, vec![Int(1), Int(33)], Int(2)),
+            (ByteCode::IDiv, vec![Int(i32::MIN), Int(-1)], Int(i32::MIN)),
+            (
+                ByteCode::LAdd,
+                vec![Long(i64::MAX), Long(1)],
+                Long(i64::MIN),
+            ),
+            (
+                ByteCode::LSub,
+                vec![Long(i64::MIN), Long(1)],
+                Long(i64::MAX),
+            ),
+            (ByteCode::LMul, vec![Long(i64::MAX), Long(2)], Long(-2)),
+            (ByteCode::LNeg, vec![Long(i64::MIN)], Long(i64::MIN)),
+            (ByteCode::LShl, vec![Long(1), Int(65)], Long(2)),
+        ];
+        for (bc, operands, expected) in cases {
+            let value = compute(bc, &operands);
+            assert_eq!(
+                format!("{:?}", value),
+                format!("{:?}", expected),
+                "{:?}",
+                bc
+            );
+        }
+    }
+
+    #[test]
+    fn hot_call_sites_are_resolved_once() {
+        let mut out = vec![];
+        let class = Rc::try_unwrap(test_class("HotCalls")).unwrap();
+        let mut vm = Vm::with_output(class, Options::default(), &mut out);
+        vm.run_main().unwrap();
+        // one entry for each site, however often it was executed
+        let mut targets = vm
+            .call_sites
+            .values()
+            .map(|(class, index)| class.methods[*index].name(&class.constant_pool))
+            .collect::<Vec<_>>();
+        targets.sort();
+        assert_eq!(targets, ["<init>", "add", "square"]);
+        drop(vm);
+        assert_eq!(out, b"28500\n");
+    }
+
+    #[test]
+    fn field_sites_are_resolved_once() {
+        let mut out = vec![];
+        let class = Rc::try_unwrap(test_class("FieldLoop")).unwrap();
+        let options = Options {
+            class_path: vec![PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/java"
+            ))],
+            ..Options::default()
+        };
+        let mut vm = Vm::with_output(class, options, &mut out);
+        vm.run_main().unwrap();
+        // one entry for each site, however often it was executed
+        let mut keys = vm
+            .field_sites
+            .values()
+            .map(|key| &**key)
+            .collect::<Vec<_>>();
+        keys.sort();
+        // the fields of this are read and written by separate sites, and
+        // both fields named value are used
+        assert_eq!(
+            keys,
+            [
+                "FieldLoop$Base.total",
+                "FieldLoop$Base.total",
+                "FieldLoop$Base.total",
+                "FieldLoop$Base.value",
+                "FieldLoop$Base.value",
+                "FieldLoop$Counter.count",
+                "FieldLoop$Counter.count",
+                "FieldLoop$Counter.count",
+                "FieldLoop$Counter.value",
+                "FieldLoop$Counter.value",
+                "FieldLoop.runs",
+                "FieldLoop.runs",
+                "FieldLoop.runs",
+            ]
+        );
+        drop(vm);
+        assert_eq!(out, b"1010 3030 2\n");
+    }
+
+    #[test]
+    fn failed_invocations_leave_no_frames_behind() {
+        let class = Rc::try_unwrap(test_class("Calc")).unwrap();
+        let mut vm = Vm::with_output(class, Options::default(), io::sink());
+        let result = vm.invoke_static("boom", vec![]);
+        assert!(matches!(result, Err(RuntimeError::Uncaught { .. })));
+        assert!(vm.stack.is_empty());
+        let sum = vm.invoke_static("add", vec![Value::Int(3), Value::Int(4)]);
+        assert!(matches!(sum, Ok(Some(Value::Int(7)))));
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn trailing_arguments_of_varargs_are_packed_into_an_array() {
+        let class = Rc::try_unwrap(test_class("Varargs")).unwrap();
+        let mut vm = Vm::with_output(class, Options::default(), io::sink());
+        let args = vec![Value::Int(7), Value::Int(1), Value::Int(2), Value::Int(3)];
+        let args = vm.pack_varargs("(I[Ljava/lang/Object;)V", args).unwrap();
+        assert_eq!(args.len(), 2);
+        assert!(matches!(args[0], Value::Int(7)));
+        let Value::Reference(array) = &args[1] else {
+            panic!("{:?}", args[1]);
+        };
+        let array = vm.heap.get_array(array);
+        assert_eq!(array.class, "[Ljava/lang/Object;");
+        let boxed = array
+            .elements
+            .iter()
+            .map(|element| vm.boxed_int(element))
+            .collect::<Vec<_>>();
+        assert_eq!(boxed, [Some(1), Some(2), Some(3)]);
+        // primitive arrays hold the values themselves
+        let args = vm.pack_varargs("([I)I", vec![]).unwrap();
+        let Value::Reference(array) = &args[0] else {
+            panic!("{:?}", args[0]);
+        };
+        assert!(vm.heap.get_array(array).elements.is_empty());
+    }
+
+    #[test]
+    fn loading_a_string_constant_twice_gives_the_same_instance() {
+        let mut frame = main_frame("Interning");
+        let mut heap = Heap::new();
+        let cp = &frame.class.constant_pool;
+        let index = cp
+            .iter()
+            .position(|constant| match constant {
+                ConstantInfo::String(string) => cp[*string as usize].as_utf8() == Some("x"),
+                _ => false,
+            })
+            .unwrap();
+        for _ in 0..2 {
+            execute_instruction(ByteCode::Ldc(index as u8), &mut frame, &mut heap).unwrap();
+        }
+        let bc = ByteCode::IfACmp(Comparison::Eq, 3);
+        let outcome = execute_instruction(bc, &mut frame, &mut heap).unwrap();
+        assert!(matches!(outcome, Outcome::Branch));
+    }
+
+    #[test]
+    fn bitwise_operators_combine_booleans_and_ints() {
+        use Value::{Boolean, Int};
+        let cases = [
+            (ByteCode::IAnd, vec![Int(1), Int(0)], 0),
+            (ByteCode::IOr, vec![Int(0), Int(1)], 1),
+            (ByteCode::IXor, vec![Int(1), Int(1)], 0),
+            (ByteCode::IAnd, vec![Boolean(true), Int(1)], 1),
+            (ByteCode::IAnd, vec![Int(0b1100), Int(0b1010)], 0b1000),
+            (ByteCode::IOr, vec![Int(0b1100), Int(0b1010)], 0b1110),
+            (ByteCode::IXor, vec![Int(-1), Int(0b1010)], !0b1010),
+        ];
+        for (bc, operands, expected) in cases {
+            let value = compute(bc, &operands);
+            assert!(matches!(value, Int(v) if v == expected), "{:?}", bc);
+        }
+    }
+
+    #[test]
+    fn only_decimal_digits_are_digits() {
+        assert_eq!(decimal_digit('0'), Some(0));
+        assert_eq!(decimal_digit('9'), Some(9));
+        // arabic-indic, devanagari, fullwidth and mathematical bold digits
+        for (c, value) in [
+            ('\u{663}', 3),
+            ('\u{96f}', 9),
+            ('\u{ff15}', 5),
+            ('\u{1d7ce}', 0),
+        ] {
+            assert_eq!(decimal_digit(c), Some(value), "{:?}", c);
+        }
+        // numbers which aren't decimal digits, and the neighbours of digits
+        for c in [
+            '\u{bd}', '\u{216b}', '\u{b2}', '/', ':', 'a', '\u{65f}', '\u{66a}',
+        ] {
+            assert_eq!(decimal_digit(c), None, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn pop2_and_dup2_act_on_the_category_of_the_top() {
+        use Value::{Double, Int, Long};
+        let cases = [
+            (ByteCode::Pop2, vec![Int(1), Long(5)], vec![Int(1)]),
+            (ByteCode::Pop2, vec![Int(1), Int(2), Int(3)], vec![Int(1)]),
+            (ByteCode::Pop2, vec![Int(1), Double(0.5)], vec![Int(1)]),
+            (ByteCode::Dup2, vec![Long(5)], vec![Long(5), Long(5)]),
+            (
+                ByteCode::Dup2,
+                vec![Int(1), Int(2)],
+                vec![Int(1), Int(2), Int(1), Int(2)],
+            ),
+            // a long under an object, and under an array and index
+            (
+                ByteCode::Dup2X1,
+                vec![Int(9), Long(5)],
+                vec![Long(5), Int(9), Long(5)],
+            ),
+            (
+                ByteCode::Dup2X2,
+                vec![Int(9), Int(0), Long(5)],
+                vec![Long(5), Int(9), Int(0), Long(5)],
+            ),
+            (
+                ByteCode::Dup2X2,
+                vec![Double(0.5), Long(5)],
+                vec![Long(5), Double(0.5), Long(5)],
+            ),
+        ];
+        for (bc, operands, expected) in cases {
+            let mut frame = main_frame("Steps");
+            frame.operand_stack.extend_from_slice(&operands);
+            execute_instruction(bc, &mut frame, &mut Heap::new()).unwrap();
+            assert_eq!(
+                format!("{:?}", frame.operand_stack),
+                format!("{:?}", expected),
+                "{:?} of {:?}",
+                bc,
+                operands
+            );
+        }
+    }
+
+    #[test]
+    fn int_comparisons_promote_narrower_operands() {
+        let operands = [
+            (Value::Char('A'), Value::Int(65)),
+            (Value::Byte(-1), Value::Short(-1)),
+            (Value::Boolean(true), Value::Int(1)),
+        ];
+        for (value1, value2) in operands {
+            let mut frame = main_frame("Steps");
+            frame.operand_stack.push(value1.clone());
+            frame.operand_stack.push(value2);
+            let bc = ByteCode::IfICmp(Comparison::Eq, 3);
+            let outcome = execute_instruction(bc, &mut frame, &mut Heap::new()).unwrap();
+            assert!(matches!(outcome, Outcome::Branch), "{:?}", value1);
+        }
+    }
+
+    #[test]
+    fn library_constants_have_their_java_values() {
+        let constant = |class, name| format!("{:?}", library_constant(class, name));
+        assert_eq!(
+            constant("java/lang/Integer", "MAX_VALUE"),
+            "Some(Int(2147483647))"
+        );
+        assert_eq!(
+            constant("java/lang/Character", "MAX_VALUE"),
+            "Some(Int(65535))"
+        );
+        assert_eq!(
+            constant("java/lang/Double", "MIN_VALUE"),
+            "Some(Double(5e-324))"
+        );
+        assert_eq!(constant("java/lang/Double", "NaN"), "Some(Double(NaN))");
+        assert_eq!(constant("java/lang/Integer", "MAX_VALUES"), "None");
+    }
+
+    #[test]
+    fn comparisons_with_nan_push_the_value_of_the_instruction() {
+        use Value::{Double, Float, Int};
+        let cases = [
+            (ByteCode::DCmp(1), vec![Double(1.0), Double(2.0)], -1),
+            (ByteCode::DCmp(-1), vec![Double(2.0), Double(2.0)], 0),
+            (
+                ByteCode::DCmp(-1),
+                vec![Double(f64::NAN), Double(f64::NAN)],
+                -1,
+            ),
+            (ByteCode::DCmp(1), vec![Double(f64::NAN), Double(0.0)], 1),
+            (ByteCode::FCmp(-1), vec![Float(3.0), Float(2.0)], 1),
+            (ByteCode::FCmp(1), vec![Float(0.0), Float(f32::NAN)], 1),
+        ];
+        for (bc, operands, expected) in cases {
+            let value = compute(bc, &operands);
+            assert_eq!(
+                format!("{:?}", value),
+                format!("{:?}", Int(expected)),
+                "{:?}",
+                bc
+            );
+        }
+    }
+
+    #[test]
+    fn ldc_of_a_large_int_takes_the_equal_branch() {
+        let mut frame = main_frame("LargeConstants");
+        let mut heap = Heap::new();
+        let index = frame
+            .class
+            .constant_pool
+            .iter()
+            .position(|constant| matches!(constant, ConstantInfo::Integer(1000000)))
+            .unwrap();
+        frame.operand_stack.push(Value::Int(1000000));
+        execute_instruction(ByteCode::Ldc(index as u8), &mut frame, &mut heap).unwrap();
+        let bc = ByteCode::IfICmp(Comparison::Eq, 3);
+        let outcome = execute_instruction(bc, &mut frame, &mut heap).unwrap();
+        assert!(matches!(outcome, Outcome::Branch));
+    }
+}
@@ -0,0 +1,76 @@
+//! Helpers to run the programs of `tests/java`.
+//!
+//! The class files are compiled from the sources next to them with
+//! `javac -g -XDstringConcat=inline`, unless a source says otherwise, and
+//! committed so that the tests don't need a JDK.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use jrm::class_file::ClassFile;
+use jrm::runtime::RuntimeError;
+use jrm::vm::{Options, Vm};
+
+/// The directory of the test programs
+pub fn java_dir() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/java"))
+}
+
+/// The path of the class file of the test program `name`
+pub fn class_path(name: &str) -> String {
+    java_dir()
+        .join(format!("{}.class", name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Parse the class file of the test program `name`
+pub fn class_file(name: &str) -> ClassFile {
+    ClassFile::parse(&class_path(name)).unwrap()
+}
+
+/// What a program printed, and how it ended
+pub struct Run {
+    pub out: String,
+    pub err: String,
+    pub result: Result<(), RuntimeError>,
+}
+
+/// Run the `main` method of the test program `name` with `options`, whose
+/// other classes are looked up next to it
+pub fn run_with(name: &str, mut options: Options) -> Run {
+    options.class_path.push(java_dir());
+    let (mut out, mut err) = (vec![], vec![]);
+    let result = Vm::with_streams(class_file(name), options, &mut out, &mut err).run_main();
+    Run {
+        out: String::from_utf8(out).unwrap(),
+        err: String::from_utf8(err).unwrap(),
+        result,
+    }
+}
+
+/// Run the `main` method of the test program `name`, which must succeed,
+/// returning what it printed to `System.out`
+pub fn run(name: &str) -> String {
+    let run = run_with(name, Options::default());
+    if let Err(err) = run.result {
+        panic!("{} failed: {}\n{}", name, err, run.out);
+    }
+    run.out
+}
+
+/// Run the `jrm` binary with `args`, returning its exit code, standard
+/// output and standard error
+pub fn jrm(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jrm"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap_or(-1),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
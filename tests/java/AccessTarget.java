@@ -0,0 +1,8 @@
+// The members were public when PrivateAccess was compiled against it.
+public class AccessTarget {
+    private int hidden = 42;
+
+    private int secret() {
+        return hidden + 1;
+    }
+}
@@ -0,0 +1,24 @@
+public class ArrayCopy {
+    public static void main(String[] args) {
+        int[] a = new int[5];
+        for (int i = 0; i < 5; i++) {
+            a[i] = i + 10;
+        }
+        int[] b = new int[4];
+        System.arraycopy(a, 1, b, 0, 3);
+        for (int i = 0; i < 4; i++) {
+            System.out.println(b[i]);
+        }
+        // overlapping ranges of the same array
+        System.arraycopy(a, 0, a, 1, 4);
+        for (int i = 0; i < 5; i++) {
+            System.out.println(a[i]);
+        }
+        try {
+            System.arraycopy(a, 3, b, 0, 3);
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        System.out.println(b[0]);
+    }
+}
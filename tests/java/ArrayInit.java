@@ -0,0 +1,14 @@
+public class ArrayInit {
+    public static void main(String[] args) {
+        int[] a = {1, 2, 3};
+        System.out.println(a.length);
+        for (int i = 0; i < a.length; i++) {
+            System.out.println(a[i]);
+        }
+        try {
+            a[3] = 4;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+    }
+}
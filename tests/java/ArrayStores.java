@@ -0,0 +1,17 @@
+public class ArrayStores {
+    public static void main(String[] args) {
+        Object[] objects = new Integer[2];
+        objects[0] = Integer.valueOf(1);
+        objects[1] = null;
+        System.out.println(objects[0]);
+        try {
+            objects[1] = "two";
+            System.out.println("stored");
+        } catch (ArrayStoreException e) {
+            System.out.println(e.getMessage());
+        }
+        CharSequence[] sequences = new String[1];
+        sequences[0] = "ok";
+        System.out.println(sequences[0]);
+    }
+}
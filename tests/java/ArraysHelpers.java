@@ -0,0 +1,30 @@
+import java.util.Arrays;
+
+public class ArraysHelpers {
+    static class P {
+        int v;
+
+        P(int v) {
+            this.v = v;
+        }
+
+        public String toString() {
+            return new StringBuilder().append("P").append(v).toString();
+        }
+    }
+
+    public static void main(String[] args) {
+        int[] a = {5, -3, 9, 0, -3};
+        System.out.println(Arrays.toString(a));
+        Arrays.sort(a);
+        System.out.println(Arrays.toString(a));
+        P[] ps = new P[3];
+        for (int i = 0; i < 3; i++) {
+            ps[i] = new P(i);
+        }
+        System.out.println(Arrays.toString(new P[] {new P(7), new P(8)}));
+        System.out.println(Arrays.toString(ps));
+        int[] none = null;
+        System.out.println(Arrays.toString(none));
+    }
+}
@@ -0,0 +1,17 @@
+public class Assertions {
+    static int check(int x) {
+        assert x > 0 : "not positive";
+        return x;
+    }
+    public static void main(String[] args) {
+        System.out.println(check(5));
+        try {
+            check(-1);
+            System.out.println("no assertion");
+        } catch (AssertionError e) {
+            System.out.println(e.getMessage());
+        }
+        assert false;
+        System.out.println("done");
+    }
+}
@@ -0,0 +1,45 @@
+public class Booleans {
+    static boolean positive(int x) {
+        return x > 0;
+    }
+
+    static int compare(int a, int b) {
+        boolean lt = a < b;
+        boolean eq = a == b;
+        int r = lt ? 1 : eq ? 0 : -1;
+        if (lt) {
+            r += 10;
+        }
+        if (!eq && !lt) {
+            r -= 10;
+        }
+        return r;
+    }
+
+    public static void main(String[] args) {
+        for (int i = -2; i <= 2; i++) {
+            boolean b = i > 0;
+            boolean c = i != 0 && i % 2 == 0;
+            boolean d = b || c;
+            boolean e = b ^ c;
+            System.out.println((b & c) | (i > 1 - 3 & i < 0));
+            System.out.println(b);
+            System.out.println(c ? "even" : "other");
+            if (d) {
+                System.out.println("d");
+            } else {
+                System.out.println("!d");
+            }
+            System.out.println(e);
+            System.out.println(positive(i) == b);
+            System.out.println(compare(i, 0));
+            int asInt = b ? 1 : 0;
+            System.out.println(asInt);
+        }
+        boolean flag = false;
+        for (int i = 0; i < 3; i++) {
+            flag = !flag;
+        }
+        System.out.println(flag);
+    }
+}
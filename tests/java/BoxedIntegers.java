@@ -0,0 +1,29 @@
+public class BoxedIntegers {
+    static int unbox(Integer i) { return i; }
+    public static void main(String[] args) {
+        Integer a = Integer.valueOf(5);
+        Integer b = Integer.valueOf(5);
+        System.out.println(a.equals(b));
+        System.out.println(a == b);
+        System.out.println(a.intValue() == 5);
+        System.out.println(a.hashCode());
+        Integer big1 = 1000;
+        Integer big2 = 1000;
+        System.out.println(big1.equals(big2));
+        System.out.println(big1 == big2);
+        System.out.println(big1.equals(a));
+        System.out.println(a.equals(null));
+        System.out.println(a.equals("5"));
+        System.out.println(unbox(-7) + 1);
+        System.out.println(big1);
+        Object o = a;
+        System.out.println(o);
+        System.out.println(a.toString());
+        Integer n = null;
+        try {
+            int x = n;
+        } catch (NullPointerException e) {
+            System.out.println("npe");
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// compareTo(Object) is a synthetic bridge to compareTo(Bridges)
+public class Bridges implements Comparable<Bridges> {
+    int value;
+
+    @Deprecated
+    int old;
+
+    public int compareTo(Bridges other) {
+        return value - other.value;
+    }
+
+    public static void main(String[] args) {
+        Bridges one = new Bridges();
+        one.value = 1;
+        Bridges two = new Bridges();
+        two.value = 2;
+        System.out.println(one.compareTo(two));
+    }
+}
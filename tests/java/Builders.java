@@ -0,0 +1,49 @@
+public class Builders {
+    public String toString() {
+        return "<sb>";
+    }
+
+    public static void main(String[] args) {
+        StringBuilder sb = new StringBuilder();
+        sb.append("hello").append(' ').append(42).append(true);
+        sb.append(2.5).append(7L).append(1.5f);
+        System.out.println(sb);
+        System.out.println(sb.length());
+        System.out.println(sb.charAt(1));
+        sb.reverse();
+        System.out.println(sb.toString());
+        sb.deleteCharAt(0).deleteCharAt(sb.length() - 1);
+        System.out.println(sb);
+        StringBuilder b = new StringBuilder("abc");
+        b.insert(0, "x").insert(4, 'y').insert(2, 9);
+        b.insert(1, new Builders()).insert(0, (Object) null);
+        System.out.println(b);
+        b.append(new char[] {'!', '?'}).append((String) null).append(new Builders());
+        System.out.println(b);
+        char[] cs = {'o', 'k'};
+        System.out.println(cs);
+        System.out.println(new StringBuilder(16).append("cap").length());
+        System.out.println(new StringBuilder("racecar").reverse());
+        try {
+            new StringBuilder("abc").deleteCharAt(3);
+        } catch (StringIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            new StringBuilder("abc").charAt(-1);
+        } catch (IndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            new StringBuilder("abc").insert(4, "z");
+        } catch (RuntimeException e) {
+            System.out.println(e.getMessage());
+        }
+        int sum = 0;
+        StringBuilder digits = new StringBuilder("12345");
+        for (int i = 0; i < digits.length(); i++) {
+            sum += digits.charAt(i) - '0';
+        }
+        System.out.println(sum);
+    }
+}
@@ -0,0 +1,17 @@
+public class Calc {
+    static int add(int a, int b) {
+        return a + b;
+    }
+    static String greet(String name, boolean loud) {
+        if (loud) {
+            System.out.println("HEY");
+        }
+        return name;
+    }
+    static boolean positive(int a) {
+        return a > 0;
+    }
+    static void boom() {
+        throw new IllegalStateException("no");
+    }
+}
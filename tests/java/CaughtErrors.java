@@ -0,0 +1,30 @@
+public class CaughtErrors {
+    int x;
+
+    public static void main(String[] args) {
+        CaughtErrors f = null;
+        try {
+            System.out.println(f.x);
+        } catch (NullPointerException e) {
+            System.out.println("caught read");
+        }
+        try {
+            f.x = 1;
+        } catch (NullPointerException e) {
+            System.out.println("caught write");
+        }
+        int zero = 0;
+        try {
+            System.out.println(1 / zero);
+        } catch (ArithmeticException e) {
+            System.out.println(e.getMessage());
+        }
+        int[] a = new int[2];
+        try {
+            a[2] = 1;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        System.out.println("done");
+    }
+}
@@ -0,0 +1,13 @@
+public class CharCompare {
+    char c = 'A';
+    static boolean isA(char c) { return c == 'A'; }
+    static int code(char c) { return c + 1; }
+    static boolean small(byte b, short s) { return b < s; }
+    public static void main(String[] args) {
+        CharCompare o = new CharCompare();
+        if (o.c == 'A') { System.out.println("equal"); } else { System.out.println("not equal"); }
+        o.c = 'B';
+        System.out.println(o.c > 'A');
+        System.out.println(isA(o.c));
+    }
+}
@@ -0,0 +1,15 @@
+public class Characters {
+    public static void main(String[] args) {
+        System.out.println(Character.isDigit('5'));
+        System.out.println(Character.toUpperCase('a'));
+        System.out.println(Character.getNumericValue('7'));
+        // neither vulgar fractions nor roman numerals are digits
+        System.out.println(Character.isDigit('\u00bd') + " " + Character.isDigit('\u216b'));
+        // but the arabic-indic and fullwidth digits are
+        System.out.println(Character.isDigit('\u0663') + " " + Character.getNumericValue('\u0663'));
+        System.out.println(Character.isDigit('\uff15') + " " + Character.getNumericValue('\uff15'));
+        System.out.println(Character.isDigit(0x1d7d9) + " " + Character.isDigit('x'));
+        System.out.println(Character.getNumericValue('z') + " " + Character.getNumericValue('-'));
+        System.out.println(Character.isLetter('\u00e9') + " " + Character.toLowerCase('Q'));
+    }
+}
@@ -0,0 +1,12 @@
+public class ClassLiterals {
+    public static void main(String[] args) {
+        System.out.println(Foo.class.getName());
+        Foo foo = new Foo();
+        System.out.println(foo.getClass().getName());
+        System.out.println(ClassLiterals.class);
+        System.out.println("s".getClass().getName());
+    }
+}
+
+class Foo {
+}
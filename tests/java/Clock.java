@@ -0,0 +1,12 @@
+public class Clock {
+    public static void main(String[] args) throws InterruptedException {
+        long first = System.nanoTime();
+        long second = System.nanoTime();
+        System.out.println(first);
+        System.out.println(second - first);
+        System.out.println(System.currentTimeMillis());
+        Thread.sleep(5);
+        System.out.println(System.nanoTime() - second);
+        System.out.println(second >= first);
+    }
+}
@@ -0,0 +1,13 @@
+public class CompoundField {
+    int f;
+
+    public static void main(String[] args) {
+        CompoundField x = new CompoundField();
+        x.f += 5;
+        x.f += 5;
+        System.out.println(x.f);
+        // the value of the assignment is the new one, which dup_x1 keeps
+        int y = (x.f += 1);
+        System.out.println(y);
+    }
+}
@@ -0,0 +1,15 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then the goto back to the
+// head of the loop in `sum` patched to jump to 80, past the end of the method.
+public class CorruptBranch {
+    static int sum(int n) {
+        int sum = 0;
+        for (int i = 0; i < n; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(sum(4));
+    }
+}
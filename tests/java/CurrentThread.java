@@ -0,0 +1,8 @@
+public class CurrentThread {
+    public static void main(String[] args) throws InterruptedException {
+        Thread thread = Thread.currentThread();
+        System.out.println(thread.getName());
+        Thread.sleep(1);
+        System.out.println(Thread.currentThread() == thread);
+    }
+}
@@ -0,0 +1,13 @@
+public class DecoyMain {
+    public static void main(int x) {
+        System.out.println("decoy");
+    }
+
+    public void main() {
+        System.out.println("instance");
+    }
+
+    public static void main(String[] args) {
+        System.out.println("real");
+    }
+}
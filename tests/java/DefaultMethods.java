@@ -0,0 +1,58 @@
+public class DefaultMethods {
+    interface Greeter {
+        String name();
+
+        default String greet() {
+            return "hello " + name();
+        }
+    }
+
+    interface Loud extends Greeter {
+        default String shout() {
+            return greet() + "!";
+        }
+    }
+
+    static class Plain implements Greeter {
+        int calls;
+
+        public String name() {
+            calls = calls + 1;
+            return "plain";
+        }
+    }
+
+    static class Child extends Plain implements Loud {
+        public String name() {
+            return "child";
+        }
+    }
+
+    static class Rude implements Greeter {
+        public String name() {
+            return "rude";
+        }
+
+        public String greet() {
+            return "go away";
+        }
+    }
+
+    public static void main(String[] args) {
+        Plain plain = new Plain();
+        System.out.println(plain.greet());
+        // `this` in the default body is the object it was called on
+        System.out.println(plain.calls);
+        Greeter g = new Child();
+        System.out.println(g.greet());
+        System.out.println(((Loud) g).shout());
+        Greeter rude = new Rude();
+        System.out.println(rude.greet());
+        try {
+            Greeter nobody = null;
+            nobody.greet();
+        } catch (NullPointerException e) {
+            System.out.println("null");
+        }
+    }
+}
@@ -0,0 +1,30 @@
+public class Dispatch {
+    static class Animal {
+        String describe() {
+            return getClass().getName() + " says " + sound() + (this instanceof Dog ? " and is a dog" : "");
+        }
+        String sound() { return "..."; }
+        String name() { return this.getClass().getName(); }
+    }
+    static class Dog extends Animal {
+        String sound() { return "woof"; }
+    }
+    static class Puppy extends Dog {
+        String sound() { return "yip"; }
+    }
+    static class Cat extends Animal {
+        String sound() { return "meow"; }
+    }
+    public static void main(String[] args) {
+        Animal[] animals = { new Animal(), new Dog(), new Cat(), new Puppy() };
+        for (int i = 0; i < animals.length; i++) {
+            Animal a = animals[i];
+            System.out.println(a.name());
+            System.out.println(a.describe());
+            System.out.println(a.sound());
+        }
+        Dog d = new Puppy();
+        System.out.println(d.sound());
+        System.out.println(d.name());
+    }
+}
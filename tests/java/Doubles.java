@@ -0,0 +1,22 @@
+public class Doubles {
+    public static void main(String[] args) {
+        System.out.println(0.1);
+        System.out.println(0.1f);
+        System.out.println(100.0);
+        System.out.println(1.5e7);
+        System.out.println(123456.789);
+        System.out.println(0.001);
+        System.out.println(0.0001234);
+        System.out.println(1e20);
+        System.out.println(-2.5e-10);
+        System.out.println(3.4028235e38f);
+        System.out.println(1.4e-45f);
+        System.out.println(4.9e-324);
+        System.out.println(1.7976931348623157e308);
+        System.out.println(9999999.0);
+        System.out.println(-0.5f);
+        System.out.println(2.0 / 3.0 * 0 + 0.6666666666666666);
+        System.out.println(1234567.0f);
+        System.out.println(12345678L);
+    }
+}
@@ -0,0 +1,31 @@
+public class Factorials {
+    static long factorial(int n, long acc) {
+        if (n <= 1) {
+            return acc;
+        }
+        return factorial(n - 1, acc * n);
+    }
+
+    static double pass(double value, int depth, double other) {
+        if (depth == 0) {
+            return value;
+        }
+        return pass(value, depth - 1, other);
+    }
+
+    long total;
+
+    long add(long a, int b, long c) {
+        total = total + a + b + c;
+        return total;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(factorial(20, 1));
+        System.out.println(factorial(25, 1));
+        System.out.println(pass(0.25, 1000, -1.5));
+        Factorials f = new Factorials();
+        f.add(1L << 33, 2, 3);
+        System.out.println(f.add(-1, 1, 5000000000L));
+    }
+}
@@ -0,0 +1,29 @@
+public class FieldLoop {
+    static int runs;
+
+    static class Base {
+        int total;
+        int value = 1;
+    }
+
+    static class Counter extends Base {
+        int count;
+        // hides the field of Base
+        int value = 2;
+
+        void run(int times) {
+            for (int i = 0; i < times; i++) {
+                total += value + ((Base) this).value;
+                count++;
+            }
+            runs++;
+        }
+    }
+
+    public static void main(String[] args) {
+        Counter counter = new Counter();
+        counter.run(1000);
+        counter.run(10);
+        System.out.println(counter.count + " " + counter.total + " " + runs);
+    }
+}
@@ -0,0 +1,28 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then the fields `value`
+// and `Limits.LIMIT` patched to be final, which javac doesn't allow to be
+// written twice
+public class FinalWrites {
+    int value;
+
+    FinalWrites() {
+        value = 1;
+        value = 2;
+    }
+
+    static class Limits {
+        static int LIMIT;
+
+        static {
+            LIMIT = 1;
+            LIMIT = 2;
+        }
+
+        public static void main(String[] args) {
+            System.out.println(LIMIT);
+        }
+    }
+
+    public static void main(String[] args) {
+        System.out.println(new FinalWrites().value);
+    }
+}
@@ -0,0 +1,21 @@
+public class FloatFields {
+    float f;
+    static float twice(float x) { return x * 2; }
+    public static void main(String[] args) {
+        FloatFields o = new FloatFields();
+        o.f = 1.5f;
+        float g = o.f + 0.5f;
+        System.out.println(g);
+        System.out.println(o.f);
+        System.out.println(twice(g));
+        float zero = 0;
+        float one = 1;
+        System.out.println(one / zero);
+        System.out.println(zero / zero);
+        System.out.println(-g);
+        System.out.println(7.5f % 2);
+        System.out.println(0.1f + 0.2f);
+        System.out.println(one / 3);
+        System.out.println(new FloatFields().f);
+    }
+}
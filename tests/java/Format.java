@@ -0,0 +1,35 @@
+public class Format {
+    public String toString() { return "Format!"; }
+    public static void main(String[] args) {
+        System.out.println(String.format("%d-%s", 5, "x"));
+        System.out.printf("%5d|%-5d|%05d|%+d%n", 42, 42, -42, 7);
+        System.out.printf("%x %X %x%n", 255, 255, -1);
+        System.out.printf("%c%c %s%n", 'h', 105, new Format());
+        System.out.printf("%.2f %f %8.3f %.0f%n", 3.14159, 2.5, -1.0005, 2.5);
+        System.out.printf("%.2f %.2f %.1f%n", 2.675, 0.125, 0.05);
+        System.out.printf("%.3f %s%n", 1.5f, 1.5f);
+        System.out.printf("%s %S %.3s|%-6s|%6s%n", null, "abc", "abcdef", "ab", "ab");
+        System.out.printf("100%% %d %s%n", 123456789012L, 'q');
+        System.out.printf("%010.3f|%n", -3.5);
+        String s = String.format("%s=%d", "n", Integer.valueOf(3));
+        System.out.println(s);
+        System.out.println(String.format("none"));
+        System.out.printf("%.2f %.3f%n", 999.999, 0.0005);
+        System.out.printf("%.2f%n", -0.001);
+        try {
+            String.format("%d", "str");
+        } catch (IllegalArgumentException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            String.format("%d %d", 1);
+        } catch (IllegalArgumentException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            String.format("%q", 1);
+        } catch (IllegalArgumentException e) {
+            System.out.println(e.getMessage());
+        }
+    }
+}
@@ -0,0 +1,26 @@
+public class GcModes {
+    int value;
+    GcModes next;
+
+    static GcModes push(GcModes list, int value) {
+        GcModes garbage = new GcModes();
+        garbage.value = -value;
+        GcModes node = new GcModes();
+        node.value = value;
+        node.next = list;
+        return node;
+    }
+
+    public static void main(String[] args) {
+        GcModes list = null;
+        for (int i = 1; i <= 200; i++) {
+            list = push(list, i);
+        }
+        int sum = 0;
+        for (int i = 0; i < 200; i++) {
+            sum += list.value;
+            list = list.next;
+        }
+        System.out.println(sum);
+    }
+}
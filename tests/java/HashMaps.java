@@ -0,0 +1,44 @@
+import java.util.HashMap;
+
+public class HashMaps {
+    static class Key {
+        int id;
+
+        Key(int id) {
+            this.id = id;
+        }
+
+        public boolean equals(Object other) {
+            return other instanceof Key && ((Key) other).id == id;
+        }
+
+        public int hashCode() {
+            return id % 3;
+        }
+    }
+
+    public static void main(String[] args) {
+        HashMap<String, Integer> ages = new HashMap<>();
+        System.out.println(ages.isEmpty());
+        ages.put("alice", 31);
+        ages.put("bob", 27);
+        String name = "car";
+        ages.put(name + "ol", 45);
+        System.out.println(ages.size());
+        System.out.println(ages.get("alice"));
+        System.out.println(ages.get("carol") + 1);
+        System.out.println(ages.get("dave"));
+        System.out.println(ages.put("bob", 28));
+        System.out.println(ages.get("bob"));
+        System.out.println(ages.size());
+        System.out.println(ages.containsKey("carol"));
+        System.out.println(ages.containsKey("dave"));
+        HashMap<Key, String> keys = new HashMap<>();
+        keys.put(new Key(1), "one");
+        keys.put(new Key(4), "four");
+        keys.put(new Key(1), "uno");
+        System.out.println(keys.size());
+        System.out.println(keys.get(new Key(1)));
+        System.out.println(keys.get(new Key(4)));
+    }
+}
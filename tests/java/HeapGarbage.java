@@ -0,0 +1,13 @@
+public class HeapGarbage {
+    int value;
+    HeapGarbage(int value) { this.value = value; }
+    public static void main(String[] args) {
+        int sum = 0;
+        int one = 1;
+        for (int i = 0; i < 200; i = i + one) {
+            HeapGarbage g = new HeapGarbage(i);
+            sum = sum + g.value;
+        }
+        System.out.println(sum);
+    }
+}
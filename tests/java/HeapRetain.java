@@ -0,0 +1,15 @@
+public class HeapRetain {
+    public static void main(String[] args) {
+        Object[] all = new Object[200];
+        try {
+            int one = 1;
+            for (int i = 0; i < all.length; i = i + one) {
+                all[i] = new HeapRetain();
+            }
+            System.out.println("all allocated");
+        } catch (OutOfMemoryError e) {
+            System.out.println(e.getMessage());
+        }
+        all[0] = new HeapRetain();
+    }
+}
@@ -0,0 +1,19 @@
+public class HotCalls {
+    int total;
+
+    static int square(int x) {
+        return x * x;
+    }
+
+    void add(int x) {
+        total += x;
+    }
+
+    public static void main(String[] args) {
+        HotCalls calls = new HotCalls();
+        for (int i = 0; i < 1000; i++) {
+            calls.add(square(i % 10));
+        }
+        System.out.println(calls.total);
+    }
+}
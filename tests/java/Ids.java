@@ -0,0 +1,8 @@
+public class Ids {
+    public static void main(String[] args) {
+        Ids first = new Ids();
+        Ids second = new Ids();
+        System.out.println(first);
+        System.out.println(second);
+    }
+}
@@ -0,0 +1,30 @@
+public class InheritedField {
+    static class Base {
+        int x = 1;
+        int y = 10;
+
+        int base() {
+            return x + y;
+        }
+    }
+
+    static class Sub extends Base {
+        int y = 20;
+
+        int own() {
+            return x + y;
+        }
+    }
+
+    public static void main(String[] args) {
+        Sub s = new Sub();
+        System.out.println(s.x);
+        s.x = 5;
+        System.out.println(s.x);
+        System.out.println(((Base) s).base());
+        System.out.println(s.own());
+        Base b = s;
+        System.out.println(b.y);
+        System.out.println(s.y);
+    }
+}
@@ -0,0 +1,28 @@
+public class InheritedThis {
+    static class Animal {
+        String describe() {
+            return getClass().getName() + " " + (this instanceof Dog) + " " + sound();
+        }
+
+        String sound() {
+            return "...";
+        }
+    }
+
+    static class Dog extends Animal {
+        String sound() {
+            return "woof";
+        }
+    }
+
+    static class Puppy extends Dog {
+    }
+
+    public static void main(String[] args) {
+        System.out.println(new Animal().describe());
+        System.out.println(new Dog().describe());
+        Animal puppy = new Puppy();
+        System.out.println(puppy.describe());
+        System.out.println(puppy.getClass() == Puppy.class);
+    }
+}
@@ -0,0 +1,33 @@
+public class InitOrder {
+    int value = 1;
+    int copy = value + 10;
+    String label = "field";
+    {
+        label = "block";
+    }
+    InitOrder() {
+        System.out.println(value);
+        System.out.println(copy);
+        System.out.println(label);
+        value = 2;
+    }
+    InitOrder(int value) {
+        this();
+        this.value = value;
+    }
+    public static void main(String[] args) {
+        InitOrder first = new InitOrder();
+        System.out.println(first.value);
+        InitOrder second = new InitOrder(5);
+        System.out.println(second.value);
+        InitOrderSub sub = new InitOrderSub();
+        System.out.println(sub.extra);
+        System.out.println(((InitOrder) sub).value);
+    }
+}
+class InitOrderSub extends InitOrder {
+    int extra = value + 100;
+    InitOrderSub() {
+        super(7);
+    }
+}
@@ -0,0 +1,44 @@
+public class Interfaces {
+    interface Shape {
+        int sides();
+    }
+
+    interface Polygon extends Shape {
+    }
+
+    static class Base implements Polygon {
+        public int sides() {
+            return 3;
+        }
+    }
+
+    static class Mid extends Base {
+    }
+
+    static class Leaf extends Mid {
+    }
+
+    static class Other {
+    }
+
+    public static void main(String[] args) {
+        Object o = new Leaf();
+        System.out.println(o instanceof Shape);
+        System.out.println(o instanceof Polygon);
+        System.out.println(o instanceof Base);
+        Object p = new Other();
+        System.out.println(p instanceof Shape);
+        Object n = null;
+        System.out.println(n instanceof Shape);
+        Shape s = (Shape) o;
+        System.out.println(s == o);
+        Object[] arr = new Leaf[2];
+        System.out.println(arr instanceof Shape[]);
+        try {
+            Shape bad = (Shape) p;
+            System.out.println(bad == null);
+        } catch (ClassCastException e) {
+            System.out.println("caught");
+        }
+    }
+}
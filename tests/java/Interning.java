@@ -0,0 +1,16 @@
+public class Interning {
+    static String x() { return "x"; }
+    public static void main(String[] args) {
+        String a = "x";
+        String b = "x";
+        if (a == b) { System.out.println("same"); } else { System.out.println("different"); }
+        System.out.println(a == x());
+        Integer i = 3;
+        String s = i.toString();
+        String t = i.toString();
+        System.out.println(s == t);
+        System.out.println(s == "3");
+        System.out.println(Thread.currentThread().getName() == "main");
+        System.out.println(a != "y");
+    }
+}
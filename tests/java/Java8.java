@@ -0,0 +1,6 @@
+// Compiled with `javac -g --release 8`, for class file version 52
+public class Java8 {
+    public static void main(String[] args) {
+        System.out.println(8);
+    }
+}
@@ -0,0 +1,8 @@
+// The bootstrap method of the lambda leaves MethodHandle constants in the
+// constant pool, which javac never loads with ldc.
+public class Lambdas {
+    public static void main(String[] args) {
+        Runnable hello = () -> System.out.println("hello");
+        hello.run();
+    }
+}
@@ -0,0 +1,27 @@
+public class LargeConstants {
+    static int check(int x) {
+        if (x == 1000000) {
+            return 1;
+        }
+        if (x > 2000000000) {
+            return 2;
+        }
+        if (x < -1000000) {
+            return 3;
+        }
+        return 0;
+    }
+    public static void main(String[] a) {
+        int x = 999999;
+        int one = 1;
+        x = x + one;
+        System.out.println(check(x));
+        System.out.println(check(2147483647));
+        System.out.println(check(-1000001));
+        System.out.println(check(1000001));
+        int y = x * 3000;
+        System.out.println(y);
+        System.out.println(y == -1294967296);
+        System.out.println(x - 1000000 == 0);
+    }
+}
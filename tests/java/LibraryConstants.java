@@ -0,0 +1,28 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then the names of the
+// nested stand-ins patched to `java/lang/Integer` and `java/lang/Double` in
+// the constant pool. javac inlines the real constants, while the fields of
+// the stand-ins aren't constants, so they are read with getstatic.
+public class LibraryConstants {
+    static class Integer {
+        static int MAX_VALUE;
+        static int MIN_VALUE;
+    }
+
+    static class Double {
+        static double NaN;
+        static double POSITIVE_INFINITY;
+    }
+
+    public static void main(String[] args) {
+        int max = Integer.MAX_VALUE;
+        System.out.println(max);
+        System.out.println(max + 1 == Integer.MIN_VALUE);
+        double nan = Double.NaN;
+        System.out.println(nan == nan);
+        System.out.println(nan != nan);
+        System.out.println(Double.POSITIVE_INFINITY);
+        // the constants javac inlines
+        System.out.println(java.lang.Integer.MAX_VALUE - 1);
+        System.out.println(java.lang.Double.NaN == java.lang.Double.NaN);
+    }
+}
@@ -0,0 +1,6 @@
+public class LongTrace {
+    public static void main(String[] args) {
+        long big = 5000000000L;
+        System.out.println(big);
+    }
+}
@@ -0,0 +1,9 @@
+// Compiled against a MissingFieldHolder declaring `int gone`, which was
+// then removed and the holder recompiled on its own.
+public class MissingField {
+    public static void main(String[] args) {
+        MissingFieldHolder holder = new MissingFieldHolder();
+        System.out.println(holder.kept);
+        System.out.println(holder.gone);
+    }
+}
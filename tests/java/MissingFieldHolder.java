@@ -0,0 +1,3 @@
+public class MissingFieldHolder {
+    int kept = 1;
+}
@@ -0,0 +1,42 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then patched so that the
+// ints are stored into the fields without being narrowed first: `truth`,
+// `bite` and `shorten` return their argument as it is, with the code after
+// the first `iload_0; ireturn` replaced by copies of it
+public class Narrowing {
+    byte small;
+    boolean flag;
+    static short counter;
+
+    static boolean truth(int value) {
+        return value != 0;
+    }
+
+    static byte bite(int value) {
+        return (byte) value;
+    }
+
+    static short shorten(int value) {
+        return (short) value;
+    }
+
+    void store(int value) {
+        small = bite(value);
+        flag = truth(value);
+        counter = shorten(value);
+    }
+
+    public static void main(String[] args) {
+        Narrowing narrowing = new Narrowing();
+        narrowing.store(300);
+        System.out.println(narrowing.small);
+        System.out.println(narrowing.flag);
+        System.out.println(counter);
+        narrowing.store(2);
+        System.out.println(narrowing.flag);
+        narrowing.store(3);
+        System.out.println(narrowing.flag);
+        narrowing.store(70001);
+        System.out.println(narrowing.small);
+        System.out.println(counter);
+    }
+}
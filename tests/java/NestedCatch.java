@@ -0,0 +1,23 @@
+public class NestedCatch {
+    static void fail(int kind) {
+        if (kind == 0) {
+            throw new IllegalArgumentException("inner");
+        }
+        throw new RuntimeException("outer");
+    }
+
+    public static void main(String[] args) {
+        for (int kind = 0; kind < 2; kind++) {
+            try {
+                try {
+                    fail(kind);
+                } catch (IllegalArgumentException e) {
+                    System.out.println("inner caught " + e.getMessage());
+                }
+                System.out.println("after inner");
+            } catch (RuntimeException e) {
+                System.out.println("outer caught " + e.getMessage());
+            }
+        }
+    }
+}
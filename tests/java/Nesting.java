@@ -0,0 +1,8 @@
+public class Nesting {
+    static class Nested {}
+    class Inner {}
+    Object make() {
+        class Local {}
+        return new Object() {};
+    }
+}
@@ -0,0 +1,16 @@
+public class NewInstance {
+    static class Foo {
+        int unset;
+        long wide;
+        Object reference;
+    }
+
+    public static void main(String[] args) {
+        Foo foo = new Foo();
+        System.out.println(foo.getClass().getName());
+        System.out.println(foo instanceof Foo);
+        System.out.println(foo.unset);
+        System.out.println(foo.wide);
+        System.out.println(foo.reference);
+    }
+}
@@ -0,0 +1,22 @@
+import java.util.Objects;
+
+public class NullChecks {
+    public static void main(String[] args) {
+        String kept = "kept";
+        System.out.println(Objects.requireNonNull(kept) == kept);
+        System.out.println(Objects.requireNonNull(kept, "unused") == kept);
+        System.out.println(Objects.requireNonNullElse(null, "else"));
+        System.out.println(Objects.isNull(null) + " " + Objects.nonNull(kept));
+        try {
+            Objects.requireNonNull(null, "oops");
+        } catch (NullPointerException e) {
+            System.out.println("caught " + e.getMessage());
+        }
+        try {
+            Objects.requireNonNull(null);
+        } catch (NullPointerException e) {
+            System.out.println("caught " + e.getMessage());
+        }
+        Objects.requireNonNull(null, "uncaught");
+    }
+}
@@ -0,0 +1,9 @@
+// Compiled with plain `javac -g`, so that the concatenation uses
+// invokedynamic, then the major version of the class file (bytes 6 and 7)
+// patched to 50, which predates invokedynamic
+public class OldIndy {
+    public static void main(String[] args) {
+        int n = 3;
+        System.out.println("n: " + n);
+    }
+}
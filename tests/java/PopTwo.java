@@ -0,0 +1,32 @@
+public class PopTwo {
+    static long counter = 40;
+    static int calls;
+
+    static long tick() {
+        calls++;
+        return counter * 3;
+    }
+
+    static int seven() {
+        return 7;
+    }
+
+    public static void main(String[] args) {
+        tick();
+        tick();
+        System.out.println(calls);
+        long y = counter++;
+        System.out.println(y);
+        System.out.println(counter);
+        long a, b;
+        a = b = tick();
+        System.out.println(a + b);
+        int[] arr = {1, 2, 3};
+        arr[1] += 40;
+        arr[seven() - 7] *= 5;
+        System.out.println(arr[0] + arr[1] + arr[2]);
+        int i = 2;
+        arr[i] <<= seven();
+        System.out.println(arr[2]);
+    }
+}
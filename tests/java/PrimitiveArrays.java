@@ -0,0 +1,23 @@
+import java.util.Arrays;
+
+public class PrimitiveArrays {
+    public static void main(String[] args) {
+        boolean[] flags = new boolean[3];
+        char[] chars = new char[2];
+        double[] doubles = new double[2];
+        long[] longs = new long[4];
+        System.out.println(flags[1]);
+        System.out.println(chars[0] == 0);
+        System.out.println(Arrays.toString(doubles));
+        System.out.println(Arrays.toString(longs));
+        System.out.println(flags.length + chars.length + doubles.length + longs.length);
+        flags[2] = true;
+        chars[1] = 'x';
+        doubles[0] = 0.5;
+        longs[3] = 1L << 40;
+        System.out.println(Arrays.toString(flags));
+        System.out.println(chars[1]);
+        System.out.println(doubles[0]);
+        System.out.println(longs[3]);
+    }
+}
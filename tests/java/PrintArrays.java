@@ -0,0 +1,9 @@
+public class PrintArrays {
+    public static void main(String[] args) {
+        int[] ints = new int[3];
+        System.out.println(ints);
+        System.out.println(new String[1]);
+        System.out.println(new double[0][]);
+        System.out.println(ints);
+    }
+}
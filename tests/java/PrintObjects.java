@@ -0,0 +1,43 @@
+public class PrintObjects {
+    static class Point {
+        int x;
+        int y;
+
+        Point(int x, int y) {
+            this.x = x;
+            this.y = y;
+        }
+
+        public String toString() {
+            return "(" + x + ", " + y + ")";
+        }
+    }
+
+    static class A {
+        public String toString() {
+            return "A!";
+        }
+    }
+
+    static class B extends A {
+    }
+
+    abstract static class Shape {
+        public abstract String toString();
+    }
+
+    static class Square extends Shape {
+        public String toString() {
+            return "square";
+        }
+    }
+
+    public static void main(String[] args) {
+        System.out.println(new Point(1, 2));
+        Object b = new B();
+        System.out.println(b);
+        System.out.println("x" + b);
+        Shape shape = new Square();
+        System.out.println(shape);
+    }
+}
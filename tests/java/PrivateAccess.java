@@ -0,0 +1,10 @@
+// Compiled with `javac -g -XDstringConcat=inline` against an AccessTarget
+// whose members were public. AccessTarget was then recompiled with them
+// private, as it is now, which javac itself never allows.
+public class PrivateAccess {
+    public static void main(String[] args) {
+        AccessTarget target = new AccessTarget();
+        System.out.println(target.secret());
+        System.out.println(target.hidden);
+    }
+}
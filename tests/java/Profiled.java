@@ -0,0 +1,25 @@
+public class Profiled {
+    static int collatz(int n) {
+        int steps = 0;
+        while (n != 1) {
+            n = n % 2 == 0 ? n / 2 : 3 * n + 1;
+            steps++;
+        }
+        return steps;
+    }
+
+    static int once() {
+        return 1;
+    }
+
+    public static void main(String[] args) {
+        int longest = 0;
+        for (int i = 1; i < 100; i++) {
+            int steps = collatz(i);
+            if (steps > longest) {
+                longest = steps;
+            }
+        }
+        System.out.println(longest + once());
+    }
+}
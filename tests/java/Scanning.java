@@ -0,0 +1,24 @@
+import java.util.InputMismatchException;
+import java.util.Scanner;
+
+public class Scanning {
+    public static void main(String[] args) {
+        Scanner scanner = new Scanner(System.in);
+        int count = scanner.nextInt();
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += scanner.nextInt();
+        }
+        System.out.println(sum);
+        System.out.println("[" + scanner.nextLine() + "]");
+        System.out.println(scanner.nextLine());
+        try {
+            scanner.nextInt();
+        } catch (InputMismatchException e) {
+            System.out.println("mismatch " + scanner.next());
+        }
+        System.out.println(scanner.hasNextInt());
+        System.out.println(scanner.hasNext());
+        scanner.close();
+    }
+}
@@ -0,0 +1,64 @@
+public class StateMachine {
+    static int run(int input) {
+        int state = 0;
+        int steps = 0;
+        int acc = input;
+        while (state != 4) {
+            switch (state) {
+                case 0:
+                    acc += 3;
+                    state = acc % 2 == 0 ? 1 : 2;
+                    break;
+                case 1:
+                    acc /= 2;
+                    state = 3;
+                    break;
+                case 2:
+                    acc = acc * 3 + 1;
+                    state = 1;
+                    break;
+                case 3:
+                    if (acc > 10) { state = 0; } else { state = 4; }
+                    break;
+                default:
+                    state = 4;
+            }
+            steps++;
+        }
+        return acc * 100 + steps;
+    }
+    static String name(int code) {
+        switch (code) {
+            case -1000: return "low";
+            case 7: return "seven";
+            case 100000: return "big";
+            default: return "other";
+        }
+    }
+    static int fallthrough(int x) {
+        int r = 0;
+        switch (x) {
+            case 1: r += 1;
+            case 2: r += 10;
+            case 3: r += 100; break;
+            case 5: r = -5;
+        }
+        return r;
+    }
+    public static void main(String[] args) {
+        for (int i = -3; i < 40; i += 7) {
+            System.out.println(run(i));
+        }
+        System.out.println(name(-1000));
+        System.out.println(name(7));
+        System.out.println(name(100000));
+        System.out.println(name(8));
+        for (int i = 0; i <= 6; i++) {
+            System.out.println(fallthrough(i));
+        }
+        int countdown = 200;
+        countdown -= 150;
+        countdown += -1;
+        System.out.println(countdown);
+    }
+}
@@ -0,0 +1,37 @@
+public class StaticRoots {
+    static class Node {
+        int value;
+
+        Node(int value) {
+            this.value = value;
+        }
+    }
+
+    static class Registry {
+        static int size = 1;
+        static Node head;
+
+        static {
+            System.out.println("Registry init");
+            head = new Node(42);
+        }
+    }
+
+    static Node garbage(int i) {
+        return new Node(i);
+    }
+
+    public static void main(String[] args) {
+        System.out.println("main");
+        System.out.println(Registry.size);
+        for (int i = 0; i < 100; i++) {
+            garbage(i);
+        }
+        System.out.println(Registry.head.value);
+        Registry.head = new Node(7);
+        for (int i = 0; i < 100; i++) {
+            garbage(i);
+        }
+        System.out.println(Registry.head.value);
+    }
+}
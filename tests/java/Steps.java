@@ -0,0 +1,7 @@
+public class Steps {
+    public static void main(String[] args) {
+        int a = 1;
+        int b = a + 2;
+        System.out.println(b);
+    }
+}
@@ -0,0 +1,12 @@
+import java.io.PrintStream;
+
+public class Streams {
+    public static void main(String[] args) {
+        PrintStream out = System.out;
+        PrintStream err = System.err;
+        out.println("to out");
+        err.println("to err");
+        System.out.println(out == err);
+        System.out.println(out == System.out);
+    }
+}
@@ -0,0 +1,33 @@
+public class StringIdentity {
+    static String twice(String s) {
+        return s + s;
+    }
+
+    public static void main(String[] args) {
+        String a = "hello";
+        String b = "hello";
+        String c = new String("hello");
+        String d = new StringBuilder("hel").append("lo").toString();
+        String e = "hellohello";
+        String f = twice(a);
+        String g = f.intern();
+        String h = new String(new char[] {'h', 'e', 'l', 'l', 'o'});
+        Object o = c;
+        System.out.println(a == b);
+        System.out.println(a == c);
+        System.out.println(a.equals(c));
+        System.out.println(a == d);
+        System.out.println(d.equals(a));
+        System.out.println(e == f);
+        System.out.println(e.equals(f));
+        System.out.println(e == g);
+        System.out.println(a == h);
+        System.out.println(a.equals(h));
+        System.out.println(o == c);
+        System.out.println(a.equals(o));
+        System.out.println(a.equals(null));
+        System.out.println("" == new String());
+        System.out.println(c == c.intern());
+        System.out.println(a == c.intern());
+    }
+}
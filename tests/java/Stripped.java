@@ -0,0 +1,9 @@
+// Compiled with `javac -g:none -XDstringConcat=inline`, so that the class has
+// no LocalVariableTable. Otherwise it is the same as Steps.
+public class Stripped {
+    public static void main(String[] args) {
+        int a = 1;
+        int b = a + 2;
+        System.out.println(b);
+    }
+}
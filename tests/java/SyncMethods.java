@@ -0,0 +1,26 @@
+public class SyncMethods {
+    private int n;
+
+    synchronized int inc() {
+        n = n + 1;
+        return n;
+    }
+
+    synchronized void fail() {
+        n = n + 10;
+        throw new IllegalStateException();
+    }
+
+    public static void main(String[] args) {
+        SyncMethods s = new SyncMethods();
+        System.out.println(s.inc());
+        try {
+            s.fail();
+        } catch (IllegalStateException e) {
+            System.out.println("caught");
+        }
+        synchronized (s) {
+            System.out.println(s.inc());
+        }
+    }
+}
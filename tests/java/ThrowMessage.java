@@ -0,0 +1,32 @@
+public class ThrowMessage {
+    static class Oops extends RuntimeException {
+        Oops(String message) {
+            super(message);
+        }
+    }
+
+    static void check(int x) {
+        if (x > 1) {
+            throw new IllegalStateException("bad");
+        }
+    }
+
+    public static void main(String[] args) {
+        try {
+            check(2);
+            System.out.println("unreachable");
+        } catch (IllegalStateException e) {
+            System.out.println(e.getMessage());
+            System.out.println(e);
+        }
+        try {
+            throw new Oops("mine");
+        } catch (RuntimeException e) {
+            System.out.println(e.getMessage());
+            System.out.println(e);
+        }
+        RuntimeException plain = new RuntimeException();
+        System.out.println(plain.getMessage());
+        System.out.println(plain);
+    }
+}
@@ -0,0 +1,22 @@
+public class ThrowableConstructors {
+    static class Quiet extends RuntimeException {
+        Quiet(String message, Throwable cause) {
+            super(message, cause, false, false);
+        }
+    }
+
+    public static void main(String[] args) {
+        System.out.println(new RuntimeException("x").getMessage());
+        RuntimeException cause = new IllegalStateException("cause");
+        System.out.println(new RuntimeException(cause).getMessage());
+        System.out.println(new Exception("wrapped", cause).getCause().getMessage());
+        try {
+            throw new Quiet("quiet", cause);
+        } catch (Quiet e) {
+            System.out.println("caught " + e.getMessage() + " " + (e.getCause() == cause));
+        }
+        System.out.println(new Quiet(null, null).getMessage());
+        // AssertionError(int) isn't one of the constructors of Throwable
+        throw new AssertionError(5);
+    }
+}
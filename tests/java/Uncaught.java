@@ -0,0 +1,10 @@
+public class Uncaught {
+    static void fail() {
+        throw new RuntimeException("boom");
+    }
+
+    public static void main(String[] args) {
+        System.out.println("before");
+        fail();
+    }
+}
@@ -0,0 +1,14 @@
+public class Unsupported {
+    static int length(String s) {
+        if (s != null) {
+            return s.length();
+        }
+        return 0;
+    }
+
+    public static void main(String[] args) {
+        System.out.println("started");
+        double area = 1.5 * length("abc");
+        System.out.println(area * area);
+    }
+}
@@ -0,0 +1,30 @@
+public class Unwinding {
+    static int divide(int a, int b) { return a / b; }
+    static int helper(int a) { int r = divide(a, 0); return r + 1; }
+    static void copy(int[] src) { System.arraycopy(src, 0, src, 1, 5); }
+    static int depth(int n) {
+        if (n == 0) { return divide(1, n); }
+        if (n < 0) { return 0; }
+        return depth(n - 1) + 1;
+    }
+    public static void main(String[] args) {
+        try {
+            System.out.println(helper(5));
+        } catch (ArithmeticException e) {
+            System.out.println("caught");
+        }
+        System.out.println("resumed");
+        int[] a = new int[3];
+        try {
+            copy(a);
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            depth(10);
+        } catch (ArithmeticException e) {
+            System.out.println(e.getMessage());
+        }
+        System.out.println(depth(-1));
+    }
+}
@@ -0,0 +1,31 @@
+public class Varargs {
+    static int sum(int... values) {
+        int sum = 0;
+        for (int value : values) {
+            sum += value;
+        }
+        return sum;
+    }
+
+    static String join(Object... values) {
+        String joined = "";
+        for (Object value : values) {
+            joined = joined + value + ";";
+        }
+        return joined;
+    }
+
+    static int count(String label, Object... values) {
+        return values.length;
+    }
+
+    static int count(String label) {
+        return -1;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(sum(1, 2, 3));
+        System.out.println(join(1, "a", 'c'));
+        System.out.println(count("x", 1, 2));
+    }
+}
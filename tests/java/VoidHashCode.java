@@ -0,0 +1,18 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then the ireturn of
+// Key.hashCode patched to return, so that it returns no value.
+import java.util.HashMap;
+
+public class VoidHashCode {
+    static class Key {
+        public int hashCode() {
+            return 7;
+        }
+    }
+
+    public static void main(String[] args) {
+        HashMap<Key, String> map = new HashMap<>();
+        System.out.println("started");
+        map.put(new Key(), "value");
+        System.out.println(map.size());
+    }
+}
@@ -0,0 +1,14 @@
+// Compiled with `javac -g -XDstringConcat=inline`, then the areturn of
+// Name.toString patched to return, so that it returns no value.
+public class VoidToString {
+    static class Name {
+        public String toString() {
+            return "name";
+        }
+    }
+
+    public static void main(String[] args) {
+        System.out.println("started");
+        System.out.println(new Name());
+    }
+}
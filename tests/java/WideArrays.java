@@ -0,0 +1,32 @@
+public class WideArrays {
+    public static void main(String[] args) {
+        long[] longs = new long[3];
+        longs[0] = Long.MIN_VALUE;
+        longs[1] = -1L;
+        longs[2] = longs[0] + longs[1];
+        for (int i = 0; i < longs.length; i++) {
+            System.out.println(longs[i]);
+        }
+        double[] doubles = {0.1, -2.5e-10, 1e300};
+        double copy = doubles[2];
+        doubles[2] = doubles[0];
+        doubles[0] = copy;
+        for (int i = 0; i < doubles.length; i++) {
+            System.out.println(doubles[i]);
+        }
+        float[] floats = new float[1];
+        floats[0] = 0.75f;
+        System.out.println(floats[0]);
+        try {
+            longs[3] = 1L;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            double[] none = null;
+            System.out.println(none[0]);
+        } catch (NullPointerException e) {
+            System.out.println("null");
+        }
+    }
+}
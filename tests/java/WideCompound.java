@@ -0,0 +1,19 @@
+public class WideCompound {
+    long total;
+
+    public static void main(String[] args) {
+        long[] counts = new long[2];
+        counts[0] += 5L;
+        counts[0] += 5L;
+        counts[1] -= 3L;
+        System.out.println(counts[0]);
+        System.out.println(counts[1]);
+        // the value of the assignment is kept under the array and index
+        long last = counts[0] += 5L;
+        System.out.println(last);
+        WideCompound w = new WideCompound();
+        // and under the object
+        long sum = w.total += 7000000000L;
+        System.out.println(sum + w.total);
+    }
+}
@@ -0,0 +1,5 @@
+public class WideConstants {
+    static final double PI = 3.14159;
+    static final long BIG = 1L << 40;
+    static final float HALF = 0.5f;
+}
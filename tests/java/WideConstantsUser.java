@@ -0,0 +1,13 @@
+// Compiled against a WideConstants whose fields weren't constants, so that
+// they are read with getstatic rather than inlined, and then WideConstants
+// was changed to the constants and recompiled on its own.
+public class WideConstantsUser {
+    public static void main(String[] args) {
+        System.out.println(WideConstants.PI);
+        System.out.println("pi: " + WideConstants.PI);
+        System.out.println(WideConstants.PI > 3.1 && WideConstants.PI < 3.2);
+        System.out.println(WideConstants.PI == 3.14159);
+        System.out.println(WideConstants.BIG + 1);
+        System.out.println(WideConstants.HALF * 3);
+    }
+}
@@ -0,0 +1,14 @@
+public class WideFields {
+    long count;
+    double ratio;
+
+    public static void main(String[] args) {
+        WideFields w = new WideFields();
+        System.out.println(w.count);
+        w.count = 1L << 40;
+        w.ratio = 0.25;
+        w.count += 1;
+        System.out.println(w.count);
+        System.out.println(w.ratio);
+    }
+}
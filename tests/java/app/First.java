@@ -0,0 +1,8 @@
+package app;
+
+public class First {
+    public static void main(String[] args) {
+        System.out.println("first");
+        Second.greet();
+    }
+}
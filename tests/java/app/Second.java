@@ -0,0 +1,11 @@
+package app;
+
+public class Second {
+    static void greet() {
+        System.out.println("second greets");
+    }
+
+    public static void main(String[] args) {
+        System.out.println("second");
+    }
+}
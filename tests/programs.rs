@@ -0,0 +1,1137 @@
+//! Run the programs of `tests/java` end to end, comparing their output
+//! with what `java` prints.
+
+mod common;
+
+use std::io;
+
+use common::{class_file, class_path, jrm, run, run_with};
+use jrm::class_file::ClassFile;
+use jrm::runtime::{GcEvent, GcMode, RuntimeError};
+use jrm::vm::{execute, Options, Vm};
+
+#[test]
+fn println_calls_user_to_string() {
+    assert_eq!(run("PrintObjects"), "(1, 2)\nA!\nxA!\nsquare\n");
+}
+
+#[test]
+fn deterministic_ids_print_the_same_every_run() {
+    let options = || Options {
+        deterministic_ids: true,
+        ..Options::default()
+    };
+    let first = run_with("Ids", options());
+    let second = run_with("Ids", options());
+    assert_eq!(first.out, "Ids@0\nIds@1\n");
+    assert_eq!(first.out, second.out);
+}
+
+#[test]
+fn compound_assignment_to_a_field() {
+    assert_eq!(run("CompoundField"), "10\n11\n");
+}
+
+#[test]
+fn execute_writes_the_output_to_the_writer() {
+    let mut out = vec![];
+    execute(class_file("Hello"), Options::default(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "Hello, World!\n");
+}
+
+#[test]
+fn reading_a_missing_field_is_no_such_field_error() {
+    let run = run_with("MissingField", Options::default());
+    assert_eq!(run.out, "1\n");
+    match run.result {
+        Err(RuntimeError::NoSuchField { class, name }) => {
+            assert_eq!(
+                (class.as_str(), name.as_str()),
+                ("MissingFieldHolder", "gone")
+            );
+        }
+        result => panic!("expected NoSuchFieldError, got {:?}", result),
+    }
+}
+
+#[test]
+fn new_records_the_class_and_initializes_fields() {
+    assert_eq!(run("NewInstance"), "NewInstance$Foo\ntrue\n0\n0\nnull\n");
+}
+
+/// Run the `main` method of the benchmark program `name` of `benches`
+fn run_bench(name: &str) -> String {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/benches");
+    let class = ClassFile::parse(&format!("{}/{}.class", dir, name)).unwrap();
+    let options = Options {
+        class_path: vec![dir.into()],
+        ..Options::default()
+    };
+    let mut out = vec![];
+    execute(class, options, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn fib_benchmark_computes_fib() {
+    assert_eq!(run_bench("Fib"), "196418\n");
+}
+
+#[test]
+fn synchronized_methods_release_the_monitor() {
+    assert_eq!(run("SyncMethods"), "1\ncaught\n12\n");
+}
+
+#[test]
+fn main_overloads_are_not_the_entry_point() {
+    assert_eq!(run("DecoyMain"), "real\n");
+}
+
+#[test]
+fn virtual_calls_dispatch_to_the_most_specific_override() {
+    let expected = "\
+Dispatch$Animal
+Dispatch$Animal says ...
+...
+Dispatch$Dog
+Dispatch$Dog says woof and is a dog
+woof
+Dispatch$Cat
+Dispatch$Cat says meow
+meow
+Dispatch$Puppy
+Dispatch$Puppy says yip and is a dog
+yip
+yip
+Dispatch$Puppy
+";
+    assert_eq!(run("Dispatch"), expected);
+}
+
+#[test]
+fn invokedynamic_before_version_51_is_a_verify_error() {
+    assert_eq!(class_file("OldIndy").version(), (50, 0));
+    let run = run_with("OldIndy", Options::default());
+    match run.result {
+        Err(RuntimeError::Verify { class, message }) => {
+            assert_eq!(class, "OldIndy");
+            assert!(
+                message.contains("requires class file version 51"),
+                "{}",
+                message
+            );
+        }
+        result => panic!("expected a VerifyError, got {:?}", result),
+    }
+}
+
+#[test]
+fn class_literals_name_the_class() {
+    assert_eq!(
+        run("ClassLiterals"),
+        "Foo\nFoo\nclass ClassLiterals\njava.lang.String\n"
+    );
+}
+
+#[test]
+fn uncaught_exceptions_are_reported_like_the_jvm() {
+    let run = run_with("Uncaught", Options::default());
+    assert_eq!(run.out, "before\n");
+    let err = run.result.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Exception in thread \"main\" java.lang.RuntimeException: boom\n\
+         \tat Uncaught.fail(Uncaught.java:3)\n\
+         \tat Uncaught.main(Uncaught.java:8)"
+    );
+    let (code, out, err) = jrm(&[&class_path("Uncaught")]);
+    assert_eq!((code, out.as_str()), (1, "before\n"));
+    assert_eq!(
+        err.lines().next(),
+        Some("Exception in thread \"main\" java.lang.RuntimeException: boom")
+    );
+}
+
+#[test]
+fn array_initializer_reads_back() {
+    assert_eq!(
+        run("ArrayInit"),
+        "3\n1\n2\n3\nIndex 3 out of bounds for length 3\n"
+    );
+}
+
+#[test]
+fn inherited_fields_are_read_through_a_subclass() {
+    assert_eq!(run("InheritedField"), "1\n5\n15\n25\n10\n20\n");
+}
+
+#[test]
+fn every_gc_mode_gives_the_same_output() {
+    for gc in [GcMode::None, GcMode::MarkSweep, GcMode::OnReturn] {
+        let options = Options {
+            gc,
+            verbose_gc: true,
+            ..Options::default()
+        };
+        let run = run_with("GcModes", options);
+        assert!(run.result.is_ok(), "{:?}: {:?}", gc, run.result);
+        assert_eq!(run.out, "20100\n", "{:?}", gc);
+        // only the log differs
+        assert_eq!(run.err.is_empty(), gc != GcMode::OnReturn, "{:?}", gc);
+    }
+    for gc in ["none", "mark-sweep", "on-return"] {
+        let (code, out, _) = jrm(&["--gc", gc, &class_path("GcModes")]);
+        assert_eq!((code, out.as_str()), (0, "20100\n"), "{}", gc);
+    }
+}
+
+#[test]
+fn nested_handlers_match_innermost_first() {
+    assert_eq!(
+        run("NestedCatch"),
+        "inner caught inner\nafter inner\nouter caught outer\n"
+    );
+}
+
+#[test]
+fn system_out_and_err_are_distinct_streams() {
+    let run = run_with("Streams", Options::default());
+    run.result.unwrap();
+    assert_eq!(run.out, "to out\nfalse\ntrue\n");
+    assert_eq!(run.err, "to err\n");
+}
+
+#[test]
+fn thrown_exceptions_carry_their_message() {
+    let expected = "\
+bad
+java.lang.IllegalStateException: bad
+mine
+ThrowMessage$Oops: mine
+null
+java.lang.RuntimeException
+";
+    assert_eq!(run("ThrowMessage"), expected);
+}
+
+#[test]
+fn arraycopy_copies_subranges_and_checks_bounds() {
+    let expected = "\
+11\n12\n13\n0
+10\n10\n11\n12\n13
+arraycopy: last source index 6 out of bounds for int[5]
+11
+";
+    assert_eq!(run("ArrayCopy"), expected);
+}
+
+#[test]
+fn doubles_print_like_double_to_string() {
+    let expected = "\
+0.1\n0.1\n100.0\n1.5E7\n123456.789\n0.001\n1.234E-4\n1.0E20\n-2.5E-10
+3.4028235E38\n1.4E-45\n4.9E-324\n1.7976931348623157E308\n9999999.0\n-0.5
+0.6666666666666666\n1234567.0\n12345678
+";
+    assert_eq!(run("Doubles"), expected);
+}
+
+#[test]
+fn invoke_runs_a_static_method_with_literal_arguments() {
+    let cp = common::java_dir();
+    let cp = cp.to_str().unwrap();
+    let invoke = |target| jrm(&["--cp", cp, "--invoke", target]);
+    assert_eq!(
+        invoke("Calc.add(3, 4)"),
+        (0, "7\n".to_string(), String::new())
+    );
+    assert_eq!(
+        invoke("Calc.greet(\"jrm\", true)"),
+        (0, "HEY\njrm\n".to_string(), String::new())
+    );
+    assert_eq!(
+        invoke("Calc.positive(-1)"),
+        (0, "false\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn trace_shows_a_long_once_with_two_slots() {
+    let options = Options {
+        trace: true,
+        ..Options::default()
+    };
+    let run = run_with("LongTrace", options);
+    run.result.unwrap();
+    assert_eq!(run.out, "5000000000\n");
+    let store = run
+        .err
+        .lines()
+        .find(|line| line.contains("LStore"))
+        .unwrap();
+    assert!(
+        store.starts_with("LongTrace.main:3 LStore(1) [5000000000] (depth 2) "),
+        "{}",
+        store
+    );
+    let call = run
+        .err
+        .lines()
+        .find(|line| line.contains("InvokeVirtual"))
+        .unwrap();
+    assert!(
+        call.contains(", 5000000000] (depth 3) {args = 0, big = 5000000000}"),
+        "{}",
+        call
+    );
+}
+
+#[test]
+fn long_and_double_fields_round_trip() {
+    assert_eq!(run("WideFields"), "0\n1099511627777\n0.25\n");
+}
+
+#[test]
+fn dump_constant_pool_prints_the_pool_and_exits() {
+    let (code, out, _) = jrm(&["--dump-constant-pool", &class_path("CompoundField")]);
+    assert_eq!(code, 0);
+    assert_eq!(out.lines().count(), 39);
+    assert!(out.contains("   #7 = Class              #8             // CompoundField\n"));
+}
+
+#[test]
+fn constructor_bodies_run_after_field_initializers() {
+    let expected = "\
+1\n11\nblock\n2
+1\n11\nblock\n5
+1\n11\nblock\n107\n7
+";
+    assert_eq!(run("InitOrder"), expected);
+}
+
+#[test]
+fn errors_of_instructions_are_catchable() {
+    let expected = "\
+caught read
+caught write
+/ by zero
+Index 2 out of bounds for length 2
+done
+";
+    assert_eq!(run("CaughtErrors"), expected);
+}
+
+#[test]
+fn info_prints_the_overview_of_the_class() {
+    let (code, out, _) = jrm(&["--info", &class_path("Hello")]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        out,
+        "\
+public class Hello extends java/lang/Object
+  version: 61.0
+  source file: Hello.java
+  fields: 0
+  methods: 2
+"
+    );
+}
+
+#[test]
+fn aastore_checks_the_element_type() {
+    assert_eq!(run("ArrayStores"), "1\njava.lang.String\nok\n");
+}
+
+#[test]
+fn the_current_thread_is_main() {
+    assert_eq!(run("CurrentThread"), "main\ntrue\n");
+}
+
+#[test]
+fn instanceof_finds_interfaces_of_superclasses() {
+    assert_eq!(
+        run("Interfaces"),
+        "true\ntrue\ntrue\nfalse\nfalse\ntrue\ntrue\ncaught\n"
+    );
+}
+
+#[test]
+fn assert_statements_only_fire_with_enable_assertions() {
+    assert_eq!(run("Assertions"), "5\nno assertion\ndone\n");
+    let options = Options {
+        enable_assertions: true,
+        ..Options::default()
+    };
+    let run = run_with("Assertions", options);
+    assert_eq!(run.out, "5\nnot positive\n");
+    match run.result {
+        Err(RuntimeError::Uncaught { class, message, .. }) => {
+            assert_eq!(class, "java.lang.AssertionError");
+            assert_eq!(message, None);
+        }
+        result => panic!("expected an AssertionError, got {:?}", result),
+    }
+    let (code, _, err) = jrm(&["-ea", &class_path("Assertions")]);
+    assert_eq!(code, 1);
+    assert!(err.starts_with("Exception in thread \"main\" java.lang.AssertionError\n"));
+}
+
+#[test]
+fn exceptions_unwind_to_the_handler_of_an_outer_method() {
+    let expected = "\
+caught
+resumed
+arraycopy: last source index 5 out of bounds for int[3]
+/ by zero
+0
+";
+    assert_eq!(run("Unwinding"), expected);
+}
+
+#[test]
+fn static_final_wide_fields_are_initialized_from_their_constant_value() {
+    assert_eq!(
+        run("WideConstantsUser"),
+        "3.14159\npi: 3.14159\ntrue\ntrue\n1099511627777\n1.5\n"
+    );
+}
+
+#[test]
+fn max_heap_collects_garbage_before_running_out() {
+    let options = Options {
+        max_heap: Some(50),
+        ..Options::default()
+    };
+    let garbage = run_with("HeapGarbage", options.clone());
+    garbage.result.unwrap();
+    assert_eq!(garbage.out, "19900\n");
+    // every object stays reachable from the array
+    let retain = run_with("HeapRetain", options);
+    assert_eq!(retain.out, "Java heap space\n");
+    match retain.result {
+        Err(RuntimeError::Uncaught { class, message, .. }) => {
+            assert_eq!(class, "java.lang.OutOfMemoryError");
+            assert_eq!(message.as_deref(), Some("Java heap space"));
+        }
+        result => panic!("expected an OutOfMemoryError, got {:?}", result),
+    }
+    assert_eq!(run("HeapRetain"), "all allocated\n");
+}
+
+#[test]
+fn boxed_integers_compare_by_value_and_unbox() {
+    let expected = "\
+true\ntrue\ntrue\n5
+true\nfalse\nfalse\nfalse\nfalse
+-6\n1000\n5\n5\nnpe
+";
+    assert_eq!(run("BoxedIntegers"), expected);
+}
+
+#[test]
+fn string_constants_are_interned() {
+    assert_eq!(run("Interning"), "same\ntrue\nfalse\nfalse\nfalse\ntrue\n");
+}
+
+#[test]
+fn arrays_print_their_class_and_identity_hash() {
+    let out = run("PrintArrays");
+    let lines = out.lines().collect::<Vec<_>>();
+    let prefixes = ["[I@", "[Ljava.lang.String;@", "[[D@", "[I@"];
+    assert_eq!(lines.len(), prefixes.len(), "{}", out);
+    for (line, prefix) in lines.iter().zip(prefixes) {
+        let hash = line
+            .strip_prefix(prefix)
+            .unwrap_or_else(|| panic!("{}", line));
+        assert!(!hash.is_empty(), "{}", line);
+        assert!(hash
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+    }
+    // the same array prints the same every time
+    assert_eq!(lines[0], lines[3]);
+}
+
+#[test]
+fn float_fields_and_arithmetic() {
+    assert_eq!(
+        run("FloatFields"),
+        "2.0\n1.5\n4.0\nInfinity\nNaN\n-2.0\n1.5\n0.3\n0.33333334\n0.0\n"
+    );
+}
+
+#[test]
+fn arrays_helpers_keep_the_array_reachable() {
+    let expected = "\
+[5, -3, 9, 0, -3]
+[-3, -3, 0, 5, 9]
+[P7, P8]
+[P0, P1, P2]
+null
+";
+    for gc in [GcMode::MarkSweep, GcMode::OnReturn] {
+        let options = Options {
+            gc,
+            ..Options::default()
+        };
+        let run = run_with("ArraysHelpers", options);
+        assert!(run.result.is_ok(), "{:?}: {:?}", gc, run.result);
+        assert_eq!(run.out, expected, "{:?}", gc);
+    }
+}
+
+#[test]
+fn run_selects_the_main_class_by_qualified_name() {
+    let cp = common::java_dir();
+    let cp = cp.to_str().unwrap();
+    let run = |class| jrm(&["--cp", cp, "--run", class]);
+    assert_eq!(
+        run("app.Second"),
+        (0, "second\n".to_string(), String::new())
+    );
+    assert_eq!(
+        run("app.First"),
+        (0, "first\nsecond greets\n".to_string(), String::new())
+    );
+    let (code, _, err) = run("app.Third");
+    assert_eq!(code, 1);
+    assert_eq!(err, "Error: class app.Third not found on the class path\n");
+}
+
+#[test]
+fn char_fields_compare_with_char_literals() {
+    assert_eq!(run("CharCompare"), "equal\ntrue\nfalse\n");
+}
+
+#[test]
+fn large_int_constants_flow_through_comparisons() {
+    assert_eq!(
+        run("LargeConstants"),
+        "1\n2\n3\n0\n-1294967296\ntrue\ntrue\n"
+    );
+}
+
+#[test]
+fn statics_are_initialized_before_access_and_are_gc_roots() {
+    for gc in [GcMode::MarkSweep, GcMode::OnReturn] {
+        let options = Options {
+            gc,
+            ..Options::default()
+        };
+        let run = run_with("StaticRoots", options);
+        assert!(run.result.is_ok(), "{:?}: {:?}", gc, run.result);
+        assert_eq!(run.out, "main\nRegistry init\n1\n42\n7\n", "{:?}", gc);
+    }
+}
+
+#[test]
+fn primitive_arrays_start_with_their_defaults() {
+    assert_eq!(
+        run("PrimitiveArrays"),
+        "false\ntrue\n[0.0, 0.0]\n[0, 0, 0, 0]\n11\n[false, false, true]\nx\n0.5\n1099511627776\n"
+    );
+}
+
+#[test]
+fn format_and_printf_follow_the_jdk() {
+    let expected = "5-x
+   42|42   |-0042|+7
+ff FF ffffffff
+hi Format!
+3.14 2.500000   -1.001 3
+2.68 0.13 0.1
+1.500 1.5
+null ABC abc|ab    |    ab
+100% 123456789012 q
+-00003.500|
+n=3
+none
+1000.00 0.001
+-0.00
+d != java.lang.String
+Format specifier '%d'
+Conversion = 'q'
+";
+    assert_eq!(run("Format"), expected);
+}
+
+#[test]
+fn invoke_packs_trailing_arguments_of_varargs_methods() {
+    let cp = common::java_dir();
+    let cp = cp.to_str().unwrap();
+    let invoke = |call| jrm(&["--cp", cp, "--invoke", call]).1;
+    assert_eq!(invoke("Varargs.sum(1, 2, 3)"), "6\n");
+    assert_eq!(invoke("Varargs.sum()"), "0\n");
+    assert_eq!(invoke("Varargs.join(1, \"a\")"), "1;a;\n");
+    // an exact match is preferred over the varargs overload
+    assert_eq!(invoke("Varargs.count(\"x\")"), "-1\n");
+    assert_eq!(invoke("Varargs.count(\"x\", 1, 2, 3)"), "3\n");
+}
+
+#[test]
+fn switches_in_loops_branch_to_the_right_targets() {
+    let expected = [
+        "3", "707", "703", "752", "734", "748", "816", "low", "seven", "big", "other", "0", "111",
+        "110", "100", "0", "-5", "0", "49",
+    ];
+    assert_eq!(run("StateMachine").lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn scanners_read_the_input_of_the_vm() {
+    let mut out = vec![];
+    let mut vm = Vm::with_output(class_file("Scanning"), Options::default(), &mut out);
+    vm.set_input("3 10\n20 -5 rest\nsecond line\nword\n".as_bytes());
+    vm.run_main().unwrap();
+    drop(vm);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "25\n[ rest]\nsecond line\nmismatch word\nfalse\nfalse\n"
+    );
+}
+
+#[test]
+fn profiles_put_the_hottest_method_first() {
+    let options = Options {
+        profile: true,
+        ..Options::default()
+    };
+    let mut vm = Vm::with_output(class_file("Profiled"), options, io::sink());
+    vm.run_main().unwrap();
+    let profile = vm.profile().unwrap();
+    let names = profile
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["Profiled.collatz", "Profiled.main", "Profiled.once"]
+    );
+    // return 1 is iconst_1 and ireturn
+    assert_eq!(profile[2].1, 2);
+    assert!(
+        Vm::with_output(class_file("Profiled"), Options::default(), io::sink())
+            .profile()
+            .is_none()
+    );
+
+    let (code, out, err) = jrm(&["--profile", &class_path("Profiled")]);
+    assert_eq!((code, out.as_str()), (0, "119\n"));
+    let hottest = err.lines().nth(1).unwrap();
+    assert!(hottest.ends_with("  Profiled.collatz"), "{}", err);
+}
+
+#[test]
+fn long_and_double_arguments_take_two_locals() {
+    // 25! wraps around like it does in Java
+    assert_eq!(
+        run("Factorials"),
+        "2432902008176640000\n7034535277573963776\n0.25\n13589934597\n"
+    );
+}
+
+#[test]
+fn require_non_null_returns_the_reference_or_throws() {
+    let run = run_with("NullChecks", Options::default());
+    assert_eq!(
+        run.out,
+        "true\ntrue\nelse\ntrue true\ncaught oops\ncaught null\n"
+    );
+    match run.result {
+        Err(RuntimeError::Uncaught { class, message, .. }) => {
+            assert_eq!(class, "java.lang.NullPointerException");
+            assert_eq!(message.as_deref(), Some("uncaught"));
+        }
+        result => panic!("expected a NullPointerException, got {:?}", result),
+    }
+}
+
+#[test]
+fn trace_files_of_two_runs_are_identical() {
+    let trace = || {
+        let mut trace = vec![];
+        let mut vm = Vm::with_output(class_file("HotCalls"), Options::default(), io::sink());
+        vm.set_trace_file(&mut trace);
+        vm.run_main().unwrap();
+        drop(vm);
+        String::from_utf8(trace).unwrap()
+    };
+    let first = trace();
+    assert_eq!(first, trace());
+    let mut lines = first.lines();
+    assert_eq!(
+        lines.next(),
+        Some("HotCalls.main\t0\tNew(8)\tObject(0 : HotCalls)")
+    );
+    assert_eq!(
+        lines.next(),
+        Some("HotCalls.main\t3\tDup\tObject(0 : HotCalls)")
+    );
+
+    let dir = env!("CARGO_TARGET_TMPDIR");
+    let paths = ["1", "2"].map(|run| format!("{}/HotCalls.{}.trace", dir, run));
+    for path in &paths {
+        let (code, out, _) = jrm(&["--trace-file", path, &class_path("HotCalls")]);
+        assert_eq!((code, out.as_str()), (0, "28500\n"));
+    }
+    let [one, two] = paths.map(|path| std::fs::read(path).unwrap());
+    assert_eq!(one, first.as_bytes());
+    assert_eq!(one, two);
+}
+
+#[test]
+fn booleans_computed_into_locals_branch_later() {
+    let expected = [
+        "false", "false", "even", "d", "true", "true", "11", "0", "true", "false", "other", "!d",
+        "false", "true", "11", "0", "false", "false", "other", "!d", "false", "true", "0", "0",
+        "false", "true", "other", "d", "true", "true", "-11", "1", "true", "true", "even", "d",
+        "false", "true", "-11", "1", "true",
+    ];
+    assert_eq!(run("Booleans").lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn character_helpers_classify_and_convert() {
+    let expected = "true\nA\n7\nfalse false\ntrue 3\ntrue 5\ntrue false\n35 -1\ntrue q\n";
+    assert_eq!(run("Characters"), expected);
+}
+
+#[test]
+fn gc_logs_report_the_collected_ids() {
+    let options = Options {
+        gc: GcMode::OnReturn,
+        deterministic_ids: true,
+        verbose_gc: true,
+        ..Options::default()
+    };
+    let (mut out, mut err) = (vec![], vec![]);
+    let mut events = vec![];
+    let mut vm = Vm::with_streams(class_file("GcModes"), options, &mut out, &mut err);
+    vm.set_gc_log(|event: &GcEvent| events.push(event.clone()));
+    vm.run_main().unwrap();
+    drop(vm);
+    // the garbage allocated before each node, and nothing else
+    assert_eq!(events.len(), 200);
+    let collected = events
+        .iter()
+        .flat_map(|event| event.collected.iter().copied())
+        .collect::<Vec<_>>();
+    assert_eq!(collected, (0..400).step_by(2).collect::<Vec<_>>());
+    assert!(events.iter().all(|event| event.method == "GcModes.push"));
+    assert_eq!(
+        events[1].to_string(),
+        "GC: collected 1 object after GcModes.push: [2]"
+    );
+    // the log replaces printing, and the program's output is unchanged
+    assert!(err.is_empty());
+    assert_eq!(out, b"20100\n");
+}
+
+#[test]
+fn string_builders_reverse_insert_and_check_indices() {
+    let expected = [
+        "hello 42true2.571.5",
+        "19",
+        "e",
+        "5.175.2eurt24 olleh",
+        ".175.2eurt24 olle",
+        "nullx<sb>a9bcy",
+        "nullx<sb>a9bcy!?null<sb>",
+        "ok",
+        "3",
+        "racecar",
+        // the messages of the StringIndexOutOfBoundsExceptions
+        "index 3, length 3",
+        "index -1, length 3",
+        "offset 4, length 3",
+        "15",
+    ];
+    assert_eq!(run("Builders").lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn pop2_and_dup2_keep_the_stack_balanced() {
+    // discarded long results, dup2 of longs and int array compound assignments
+    assert_eq!(run("PopTwo"), "2\n40\n41\n246\n50\n384\n");
+}
+
+#[test]
+fn checked_mode_rejects_a_second_write_to_a_final_field() {
+    let checked = Options {
+        checked: true,
+        ..Options::default()
+    };
+    let cases = [
+        ("FinalWrites", "non-static final field FinalWrites.value"),
+        (
+            "FinalWrites$Limits",
+            "static final field FinalWrites$Limits.LIMIT",
+        ),
+    ];
+    for (name, field) in cases {
+        assert_eq!(run(name), "2\n");
+        let run = run_with(name, checked.clone());
+        assert_eq!(run.out, "");
+        let message = format!("Update to {} attempted twice", field);
+        assert!(
+            matches!(&run.result, Err(RuntimeError::IllegalAccess(m)) if *m == message),
+            "{:?}",
+            run.result
+        );
+    }
+}
+
+#[test]
+fn throwable_constructors_store_the_message_and_cause() {
+    let run = run_with("ThrowableConstructors", Options::default());
+    assert_eq!(
+        run.out,
+        "x\njava.lang.IllegalStateException: cause\ncause\ncaught quiet true\nnull\n"
+    );
+    // the other constructors of library exceptions aren't run silently
+    match run.result {
+        Err(RuntimeError::UnsupportedMethod {
+            class,
+            name,
+            descriptor,
+        }) => {
+            assert_eq!(class, "java/lang/AssertionError");
+            assert_eq!(name, "<init>");
+            assert_eq!(descriptor, "(I)V");
+        }
+        result => panic!("expected an unsupported method, got {:?}", result),
+    }
+}
+
+#[test]
+fn the_fake_clock_advances_by_a_fixed_step() {
+    let options = Options {
+        fake_clock: true,
+        ..Options::default()
+    };
+    let fake = run_with("Clock", options);
+    fake.result.unwrap();
+    // every read advances the clock by a step, and sleeping by its duration,
+    // so the last nanoTime comes 2 steps and 5ms after the second
+    let step = Vm::FAKE_CLOCK_STEP;
+    let expected = [step, step, 3 * step / 1_000_000, 5_000_000 + 2 * step];
+    let lines = fake.out.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..4], expected.map(|n| n.to_string()));
+    assert_eq!(lines[4], "true");
+    assert_eq!(jrm(&["--fake-clock", &class_path("Clock")]).1, fake.out);
+
+    // the real clock only goes forward
+    let out = run("Clock");
+    let lines = out.lines().collect::<Vec<_>>();
+    assert!(lines[1].parse::<i64>().unwrap() >= 0, "{}", out);
+    assert!(lines[3].parse::<i64>().unwrap() >= 5_000_000, "{}", out);
+    assert_eq!(lines[4], "true");
+}
+
+#[test]
+fn ints_stored_into_narrow_fields_are_narrowed() {
+    // booleans keep the lowest bit like the JVM specifies, so 300 and 2 are
+    // false and 3 is true
+    assert_eq!(run("Narrowing"), "44\nfalse\n300\nfalse\ntrue\n113\n4465\n");
+}
+
+#[test]
+fn strict_verify_lists_unsupported_instructions_before_running() {
+    let strict = Options {
+        strict_verify: true,
+        ..Options::default()
+    };
+    let run = run_with("Unsupported", strict.clone());
+    // nothing ran, not even the first println
+    assert_eq!(run.out, "");
+    match run.result {
+        Err(RuntimeError::Verify { class, message }) => {
+            assert_eq!(class, "Unsupported");
+            assert_eq!(
+                message,
+                "uses instructions jrm doesn't support: ifnull (length at 1), i2d (main at 16), \
+                 dmul (main at 17), dmul (main at 24)"
+            );
+        }
+        result => panic!("expected a VerifyError, got {:?}", result),
+    }
+    let (code, out, err) = jrm(&["--strict-verify", &class_path("Unsupported")]);
+    assert_eq!((code, out.as_str()), (1, ""));
+    assert!(err.contains("ifnull (length at 1)"), "{}", err);
+    // classes using only supported instructions run as usual
+    let run = run_with("HotCalls", strict);
+    assert!(run.result.is_ok(), "{:?}", run.result);
+    assert_eq!(run.out, "28500\n");
+}
+
+#[test]
+fn library_constants_are_read_with_getstatic() {
+    assert_eq!(
+        run("LibraryConstants").lines().collect::<Vec<_>>(),
+        [
+            "2147483647",
+            "true",
+            "false",
+            "true",
+            "Infinity",
+            "2147483646",
+            "false"
+        ]
+    );
+}
+
+#[test]
+fn default_methods_run_on_the_receiver() {
+    assert_eq!(
+        run("DefaultMethods").lines().collect::<Vec<_>>(),
+        [
+            "hello plain",
+            "1",
+            "hello child",
+            "hello child!",
+            "go away",
+            "null"
+        ]
+    );
+}
+
+#[test]
+fn hash_maps_put_and_get_entries() {
+    assert_eq!(
+        run("HashMaps").lines().collect::<Vec<_>>(),
+        ["true", "3", "31", "46", "null", "27", "28", "3", "true", "false", "2", "uno", "four"]
+    );
+}
+
+#[test]
+fn trace_names_locals_only_with_debug_info() {
+    let options = Options {
+        trace: true,
+        ..Options::default()
+    };
+    let add = |name| {
+        let run = run_with(name, options.clone());
+        run.result.unwrap();
+        let line = run.err.lines().find(|line| line.contains("IAdd")).unwrap();
+        line.to_string()
+    };
+    assert_eq!(
+        add("Steps"),
+        "Steps.main:4 IAdd [1, 2] (depth 2) {args = 0, a = 1, locals[2] = 0}"
+    );
+    assert_eq!(
+        add("Stripped"),
+        "Stripped.main:4 IAdd [1, 2] (depth 2) {locals[0] = 0, locals[1] = 1, locals[2] = 0}"
+    );
+}
+
+#[test]
+fn compound_assignments_of_longs_shuffle_both_slots() {
+    assert_eq!(
+        run("WideCompound").lines().collect::<Vec<_>>(),
+        ["10", "-3", "15", "14000000000"]
+    );
+}
+
+#[test]
+fn long_and_double_arrays_round_trip_their_elements() {
+    assert_eq!(
+        run("WideArrays").lines().collect::<Vec<_>>(),
+        [
+            "-9223372036854775808",
+            "-1",
+            "9223372036854775807",
+            "1.0E300",
+            "-2.5E-10",
+            "0.1",
+            "0.75",
+            "Index 3 out of bounds for length 3",
+            "null"
+        ]
+    );
+}
+
+#[test]
+fn private_methods_of_other_classes_are_only_callable_when_lenient() {
+    assert_eq!(run("PrivateAccess"), "43\n42\n");
+    let strict = Options {
+        strict_access: true,
+        ..Options::default()
+    };
+    let run = run_with("PrivateAccess", strict);
+    assert_eq!(run.out, "");
+    assert!(
+        matches!(
+            &run.result,
+            Err(RuntimeError::IllegalAccess(message))
+                if message == "class PrivateAccess tried to access private method AccessTarget.secret()I"
+        ),
+        "{:?}",
+        run.result
+    );
+}
+
+#[test]
+fn equal_strings_are_only_identical_when_interned() {
+    let expected = [
+        true, false, true, false, true, false, true, true, false, true, true, true, false, false,
+        false, true,
+    ];
+    let out = run("StringIdentity");
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        expected.map(|b| b.to_string())
+    );
+}
+
+#[test]
+fn verify_reports_a_branch_past_the_end_of_a_method() {
+    let (code, out, _) = jrm(&["verify", &class_path("CorruptBranch"), &class_path("Hello")]);
+    assert_eq!(code, 1);
+    assert_eq!(
+        out,
+        format!(
+            "{}: sum(I)I: control flow leaves the code at 80\n",
+            class_path("CorruptBranch")
+        )
+    );
+    let (code, out, _) = jrm(&["verify", &class_path("Hello")]);
+    assert_eq!((code, out.as_str()), (0, ""));
+}
+
+#[test]
+fn cached_field_sites_read_and_write_the_same_fields() {
+    // the second call of run reuses the sites resolved by the first one
+    assert_eq!(run("FieldLoop"), "1010 3030 2\n");
+}
+
+#[test]
+fn inherited_methods_see_the_class_of_the_receiver() {
+    assert_eq!(
+        run("InheritedThis").lines().collect::<Vec<_>>(),
+        [
+            "InheritedThis$Animal false ...",
+            "InheritedThis$Dog true woof",
+            "InheritedThis$Puppy true woof",
+            "true"
+        ]
+    );
+}
+
+#[test]
+fn unsupported_instructions_fail_the_run_when_reached() {
+    let run = run_with("Unsupported", Options::default());
+    assert_eq!(run.out, "started\n");
+    match run.result {
+        Err(RuntimeError::UnsupportedInstruction {
+            mnemonic,
+            method,
+            pc,
+        }) => assert_eq!(
+            (mnemonic, method.as_str(), pc),
+            ("ifnull", "Unsupported.length", 1)
+        ),
+        result => panic!("expected an unsupported instruction, got {:?}", result),
+    }
+    let (code, out, err) = jrm(&[&class_path("Unsupported")]);
+    assert_eq!((code, out.as_str()), (1, "started\n"));
+    assert!(
+        err.contains("unsupported instruction: ifnull (Unsupported.length at 1)"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn trace_shows_the_mnemonic_of_unsupported_instructions() {
+    let options = Options {
+        trace: true,
+        ..Options::default()
+    };
+    let run = run_with("Unsupported", options);
+    assert!(
+        matches!(
+            run.result,
+            Err(RuntimeError::UnsupportedInstruction { pc: 1, .. })
+        ),
+        "{:?}",
+        run.result
+    );
+    let last = run.err.lines().last().unwrap();
+    assert_eq!(
+        last,
+        "Unsupported.length:1 ifnull [\"abc\"] (depth 1) {s = \"abc\"}"
+    );
+}
+
+#[test]
+fn trace_files_show_the_mnemonic_of_unsupported_instructions() {
+    let mut trace = vec![];
+    let mut vm = Vm::with_output(class_file("Unsupported"), Options::default(), io::sink());
+    vm.set_trace_file(&mut trace);
+    let result = vm.run_main();
+    drop(vm);
+    assert!(
+        matches!(
+            result,
+            Err(RuntimeError::UnsupportedInstruction { pc: 1, .. })
+        ),
+        "{:?}",
+        result
+    );
+    let trace = String::from_utf8(trace).unwrap();
+    assert_eq!(
+        trace.lines().last(),
+        Some("Unsupported.length\t1\tifnull\t\"abc\"")
+    );
+}
+
+#[test]
+fn hash_codes_returning_nothing_are_type_mismatches() {
+    let run = run_with("VoidHashCode", Options::default());
+    assert_eq!(run.out, "started\n");
+    match run.result {
+        Err(RuntimeError::TypeMismatch { expected, found }) => {
+            assert_eq!(expected, "a returned value");
+            assert_eq!(found, "none from VoidHashCode$Key.hashCode");
+        }
+        result => panic!("expected a type mismatch, got {:?}", result),
+    }
+}
+
+#[test]
+fn to_strings_returning_nothing_are_type_mismatches() {
+    let run = run_with("VoidToString", Options::default());
+    assert_eq!(run.out, "started\n");
+    match run.result {
+        Err(RuntimeError::TypeMismatch { expected, found }) => {
+            assert_eq!(expected, "a returned value");
+            assert_eq!(found, "none from VoidToString$Name.toString");
+        }
+        result => panic!("expected a type mismatch, got {:?}", result),
+    }
+}
+
+#[test]
+fn step_debugger_steps_back_to_the_previous_locals() {
+    let options = Options {
+        step: true,
+        ..Options::default()
+    };
+    let (mut out, mut err) = (vec![], vec![]);
+    let mut vm = Vm::with_streams(class_file("Steps"), options, &mut out, &mut err);
+    vm.set_step_input(&b"s\ns\nb\nc\n"[..]);
+    vm.run_main().unwrap();
+    drop(vm);
+    assert_eq!(out, b"3\n");
+    let err = String::from_utf8(err).unwrap();
+    assert_eq!(
+        err.lines().collect::<Vec<_>>(),
+        [
+            "Steps.main:0 IConst(1) [] (depth 0) {args = 0, locals[1] = 0, locals[2] = 0}",
+            "Steps.main:1 IStore(1) [1] (depth 1) {args = 0, locals[1] = 0, locals[2] = 0}",
+            "Steps.main:2 ILoad(1) [] (depth 0) {args = 0, a = 1, locals[2] = 0}",
+            // back before the store, which runs again after c
+            "Steps.main:1 IStore(1) [1] (depth 1) {args = 0, locals[1] = 0, locals[2] = 0}",
+        ]
+    );
+}